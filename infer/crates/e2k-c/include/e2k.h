@@ -0,0 +1,199 @@
+/* このファイルはcbindgenで生成されています。直接編集しないでください。 */
+
+#ifndef E2K_H
+#define E2K_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * 関数の結果。
+ */
+typedef enum E2kResult {
+  /**
+   * 成功した。
+   */
+  E2K_RESULT_OK = 0,
+  /**
+   * 必要な引数がNULLだった。
+   */
+  E2K_RESULT_NULL_POINTER = 1,
+  /**
+   * 入力がUTF-8ではなかった。
+   */
+  E2K_RESULT_INVALID_UTF8 = 2,
+  /**
+   * バッファが足りなかった。必要なバイト数が書き込まれる。
+   */
+  E2K_RESULT_BUFFER_TOO_SMALL = 3,
+  /**
+   * 引数の値が不正だった。
+   */
+  E2K_RESULT_INVALID_ARGUMENT = 4,
+  /**
+   * ライブラリ内部でパニックした。
+   */
+  E2K_RESULT_PANIC = 5,
+} E2kResult;
+
+/**
+ * デコードに使うアルゴリズムの種類。
+ *
+ * 関数や[E2kStrategy::kind]では`uint32_t`として受け取り、範囲外の値は[E2kResult::InvalidArgument]になります。
+ */
+enum E2kStrategyKind
+#ifdef __cplusplus
+  : uint32_t
+#endif // __cplusplus
+ {
+  E2K_STRATEGY_KIND_GREEDY = 0,
+  E2K_STRATEGY_KIND_TOP_K = 1,
+  E2K_STRATEGY_KIND_TOP_P = 2,
+  E2K_STRATEGY_KIND_MIN_P = 3,
+  E2K_STRATEGY_KIND_TYPICAL = 4,
+  E2K_STRATEGY_KIND_TEMPERATURE = 5,
+};
+#ifndef __cplusplus
+typedef uint32_t E2kStrategyKind;
+#endif // __cplusplus
+
+/**
+ * 英単語 -> カタカナの変換器。[e2k_c2k_create]で生成し、[e2k_c2k_destroy]で破棄する。
+ *
+ * 推論は複数のスレッドから同時に行えますが、[e2k_c2k_set_strategy]は他の関数と同時に呼ばないでください。
+ */
+typedef struct E2kC2k E2kC2k;
+
+/**
+ * デコードに使うアルゴリズムとパラメータ。
+ *
+ * [e2k_strategy_default]で既定値を取得してから、必要な値を変更してください。
+ * 値の範囲は[e2k::Strategy::validate]と同じで、範囲外の場合は[E2kResult::InvalidArgument]になります。
+ */
+typedef struct E2kStrategy {
+  /**
+   * アルゴリズムの種類。[E2kStrategyKind]の値。
+   */
+  uint32_t kind;
+  /**
+   * Top-Kの`k`。
+   */
+  size_t k;
+  /**
+   * Top-Pの`top_p`、Min-Pの`min_p`、Typicalの`typical_p`。
+   */
+  float p;
+  /**
+   * ロジットを割る温度。
+   */
+  float temperature;
+  /**
+   * `seed`を使うかどうか。`false`の場合は推論ごとに異なるシードを使う。
+   */
+  bool has_seed;
+  /**
+   * 乱数のシード。
+   */
+  uint64_t seed;
+} E2kStrategy;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * 結果を表す文字列を返す。返される文字列は解放しないでください。
+ *
+ * `result`は[E2kResult]の値。それ以外の値には`"unknown result"`を返す。
+ */
+const char *e2k_result_to_string(uint32_t result);
+
+/**
+ * `kind`の既定のパラメータを`out`に書き込む。
+ *
+ * `kind`は[E2kStrategyKind]の値。それ以外の値の場合は[E2kResult::InvalidArgument]を返す。
+ *
+ * # Safety
+ *
+ * `out`は書き込み可能なポインタである必要がある。
+ */
+enum E2kResult e2k_strategy_default(uint32_t kind,
+                                    struct E2kStrategy *out);
+
+/**
+ * 変換器を生成する。
+ *
+ * `max_length`は読みの最大長。成功した場合、`out`に変換器が書き込まれる。
+ *
+ * # Safety
+ *
+ * `out`は書き込み可能なポインタである必要がある。
+ */
+enum E2kResult e2k_c2k_create(size_t max_length,
+                              struct E2kC2k **out);
+
+/**
+ * 変換器を破棄する。NULLの場合は何もしない。
+ *
+ * # Safety
+ *
+ * `c2k`は[e2k_c2k_create]で生成され、まだ破棄されていない必要がある。
+ */
+void e2k_c2k_destroy(struct E2kC2k *c2k);
+
+/**
+ * [e2k_c2k_infer]などで使うアルゴリズムを設定する。
+ *
+ * # Safety
+ *
+ * `c2k`は有効な変換器、`strategy`は有効なポインタである必要がある。
+ */
+enum E2kResult e2k_c2k_set_strategy(struct E2kC2k *c2k, const struct E2kStrategy *strategy);
+
+/**
+ * 推論を行い、NUL終端されたUTF-8の読みを呼び出し側のバッファに書き込む。
+ *
+ * `written`には、NULを含めた読みのバイト数が書き込まれる。
+ * `buffer_len`が足りない場合は[E2kResult::BufferTooSmall]を返し、`buffer`には何も書き込まない。
+ * このとき`written`の大きさのバッファで呼び直せば成功する（シードを固定していないサンプリングを除く）。
+ * `buffer`がNULLの場合は`buffer_len`を0として扱う。
+ *
+ * # Safety
+ *
+ * `c2k`は有効な変換器、`input`はNUL終端された文字列、
+ * `buffer`はNULLか`buffer_len`バイト書き込めるポインタ、`written`は書き込み可能なポインタである必要がある。
+ */
+enum E2kResult e2k_c2k_infer(const struct E2kC2k *c2k,
+                             const char *input,
+                             char *buffer,
+                             size_t buffer_len,
+                             size_t *written);
+
+/**
+ * 推論を行い、ライブラリが確保したNUL終端されたUTF-8の読みを`out`に書き込む。
+ *
+ * 読みは[e2k_string_free]で解放してください。
+ *
+ * # Safety
+ *
+ * `c2k`は有効な変換器、`input`はNUL終端された文字列、`out`は書き込み可能なポインタである必要がある。
+ */
+enum E2kResult e2k_c2k_infer_alloc(const struct E2kC2k *c2k,
+                                   const char *input,
+                                   char **out);
+
+/**
+ * [e2k_c2k_infer_alloc]が返した文字列を解放する。NULLの場合は何もしない。
+ *
+ * # Safety
+ *
+ * `string`は[e2k_c2k_infer_alloc]が返し、まだ解放されていない必要がある。
+ */
+void e2k_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* E2K_H */
@@ -0,0 +1,335 @@
+//! [e2k](https://github.com/VOICEVOX/e2k/tree/main/infer/crates/e2k-rs)のC API。
+//!
+//! ヘッダーは`include/e2k.h`です。関数は全て[E2kResult]を返し、
+//! 成功した場合のみ出力引数に値を書き込みます。
+
+use std::ffi::{CStr, CString, c_char};
+
+/// 関数の結果。
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum E2kResult {
+    /// 成功した。
+    Ok = 0,
+    /// 必要な引数がNULLだった。
+    NullPointer = 1,
+    /// 入力がUTF-8ではなかった。
+    InvalidUtf8 = 2,
+    /// バッファが足りなかった。必要なバイト数が書き込まれる。
+    BufferTooSmall = 3,
+    /// 引数の値が不正だった。
+    InvalidArgument = 4,
+    /// ライブラリ内部でパニックした。
+    Panic = 5,
+}
+
+/// デコードに使うアルゴリズムの種類。
+///
+/// 関数や[E2kStrategy::kind]では`uint32_t`として受け取り、範囲外の値は[E2kResult::InvalidArgument]になります。
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum E2kStrategyKind {
+    Greedy = 0,
+    TopK = 1,
+    TopP = 2,
+    MinP = 3,
+    Typical = 4,
+    Temperature = 5,
+}
+
+impl E2kStrategyKind {
+    fn from_raw(kind: u32) -> Result<Self, E2kResult> {
+        match kind {
+            0 => Ok(Self::Greedy),
+            1 => Ok(Self::TopK),
+            2 => Ok(Self::TopP),
+            3 => Ok(Self::MinP),
+            4 => Ok(Self::Typical),
+            5 => Ok(Self::Temperature),
+            _ => Err(E2kResult::InvalidArgument),
+        }
+    }
+}
+
+/// デコードに使うアルゴリズムとパラメータ。
+///
+/// [e2k_strategy_default]で既定値を取得してから、必要な値を変更してください。
+/// 値の範囲は[e2k::Strategy::validate]と同じで、範囲外の場合は[E2kResult::InvalidArgument]になります。
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct E2kStrategy {
+    /// アルゴリズムの種類。[E2kStrategyKind]の値。
+    pub kind: u32,
+    /// Top-Kの`k`。
+    pub k: usize,
+    /// Top-Pの`top_p`、Min-Pの`min_p`、Typicalの`typical_p`。
+    pub p: f32,
+    /// ロジットを割る温度。
+    pub temperature: f32,
+    /// `seed`を使うかどうか。`false`の場合は推論ごとに異なるシードを使う。
+    pub has_seed: bool,
+    /// 乱数のシード。
+    pub seed: u64,
+}
+
+impl E2kStrategy {
+    fn to_strategy(self) -> Result<e2k::Strategy, E2kResult> {
+        let seed = self.has_seed.then_some(self.seed);
+        let temperature = self.temperature;
+        let strategy = match E2kStrategyKind::from_raw(self.kind)? {
+            E2kStrategyKind::Greedy => e2k::Strategy::Greedy,
+            E2kStrategyKind::TopK => e2k::Strategy::TopK(e2k::StrategyTopK {
+                k: self.k,
+                temperature,
+                seed,
+            }),
+            E2kStrategyKind::TopP => e2k::Strategy::TopP(e2k::StrategyTopP {
+                top_p: self.p,
+                temperature,
+                seed,
+            }),
+            E2kStrategyKind::MinP => e2k::Strategy::MinP(e2k::StrategyMinP {
+                min_p: self.p,
+                temperature,
+                seed,
+            }),
+            E2kStrategyKind::Typical => e2k::Strategy::Typical(e2k::StrategyTypical {
+                typical_p: self.p,
+                temperature,
+                seed,
+            }),
+            E2kStrategyKind::Temperature => {
+                e2k::Strategy::Temperature(e2k::StrategyTemperature { temperature, seed })
+            }
+        };
+        strategy
+            .validate()
+            .map_err(|_| E2kResult::InvalidArgument)?;
+        Ok(strategy)
+    }
+}
+
+/// 英単語 -> カタカナの変換器。[e2k_c2k_create]で生成し、[e2k_c2k_destroy]で破棄する。
+///
+/// 推論は複数のスレッドから同時に行えますが、[e2k_c2k_set_strategy]は他の関数と同時に呼ばないでください。
+pub struct E2kC2k {
+    inner: e2k::C2k,
+}
+
+/// パニックを[E2kResult::Panic]にする。
+fn catch(f: impl FnOnce() -> Result<(), E2kResult>) -> E2kResult {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(Ok(())) => E2kResult::Ok,
+        Ok(Err(e)) => e,
+        Err(_) => E2kResult::Panic,
+    }
+}
+
+/// # Safety
+///
+/// `input`はNULLか、NUL終端された文字列である必要がある。
+unsafe fn read_input<'a>(input: *const c_char) -> Result<&'a str, E2kResult> {
+    if input.is_null() {
+        return Err(E2kResult::NullPointer);
+    }
+    unsafe { CStr::from_ptr(input) }
+        .to_str()
+        .map_err(|_| E2kResult::InvalidUtf8)
+}
+
+/// 結果を表す文字列を返す。返される文字列は解放しないでください。
+///
+/// `result`は[E2kResult]の値。それ以外の値には`"unknown result"`を返す。
+#[unsafe(no_mangle)]
+pub extern "C" fn e2k_result_to_string(result: u32) -> *const c_char {
+    let result = [
+        E2kResult::Ok,
+        E2kResult::NullPointer,
+        E2kResult::InvalidUtf8,
+        E2kResult::BufferTooSmall,
+        E2kResult::InvalidArgument,
+        E2kResult::Panic,
+    ]
+    .into_iter()
+    .find(|&r| r as u32 == result);
+    let message: &'static CStr = match result {
+        Some(E2kResult::Ok) => c"ok",
+        Some(E2kResult::NullPointer) => c"null pointer",
+        Some(E2kResult::InvalidUtf8) => c"input is not valid UTF-8",
+        Some(E2kResult::BufferTooSmall) => c"buffer is too small",
+        Some(E2kResult::InvalidArgument) => c"invalid argument",
+        Some(E2kResult::Panic) => c"internal error",
+        None => c"unknown result",
+    };
+    message.as_ptr()
+}
+
+/// `kind`の既定のパラメータを`out`に書き込む。
+///
+/// `kind`は[E2kStrategyKind]の値。それ以外の値の場合は[E2kResult::InvalidArgument]を返す。
+///
+/// # Safety
+///
+/// `out`は書き込み可能なポインタである必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_strategy_default(kind: u32, out: *mut E2kStrategy) -> E2kResult {
+    if out.is_null() {
+        return E2kResult::NullPointer;
+    }
+    let parsed = match E2kStrategyKind::from_raw(kind) {
+        Ok(parsed) => parsed,
+        Err(e) => return e,
+    };
+    let mut strategy = E2kStrategy {
+        kind,
+        k: 0,
+        p: 0.0,
+        temperature: 1.0,
+        has_seed: false,
+        seed: 0,
+    };
+    match parsed {
+        E2kStrategyKind::Greedy => {}
+        E2kStrategyKind::TopK => strategy.k = e2k::StrategyTopK::default().k,
+        E2kStrategyKind::TopP => strategy.p = e2k::StrategyTopP::default().top_p,
+        E2kStrategyKind::MinP => strategy.p = e2k::StrategyMinP::default().min_p,
+        E2kStrategyKind::Typical => strategy.p = e2k::StrategyTypical::default().typical_p,
+        E2kStrategyKind::Temperature => {}
+    }
+    unsafe { out.write(strategy) };
+    E2kResult::Ok
+}
+
+/// 変換器を生成する。
+///
+/// `max_length`は読みの最大長。成功した場合、`out`に変換器が書き込まれる。
+///
+/// # Safety
+///
+/// `out`は書き込み可能なポインタである必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_c2k_create(max_length: usize, out: *mut *mut E2kC2k) -> E2kResult {
+    if out.is_null() {
+        return E2kResult::NullPointer;
+    }
+    catch(|| {
+        let c2k = Box::new(E2kC2k {
+            inner: e2k::C2k::new(max_length),
+        });
+        unsafe { out.write(Box::into_raw(c2k)) };
+        Ok(())
+    })
+}
+
+/// 変換器を破棄する。NULLの場合は何もしない。
+///
+/// # Safety
+///
+/// `c2k`は[e2k_c2k_create]で生成され、まだ破棄されていない必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_c2k_destroy(c2k: *mut E2kC2k) {
+    if !c2k.is_null() {
+        drop(unsafe { Box::from_raw(c2k) });
+    }
+}
+
+/// [e2k_c2k_infer]などで使うアルゴリズムを設定する。
+///
+/// # Safety
+///
+/// `c2k`は有効な変換器、`strategy`は有効なポインタである必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_c2k_set_strategy(
+    c2k: *mut E2kC2k,
+    strategy: *const E2kStrategy,
+) -> E2kResult {
+    let (Some(c2k), Some(strategy)) = (unsafe { c2k.as_mut() }, unsafe { strategy.as_ref() })
+    else {
+        return E2kResult::NullPointer;
+    };
+    catch(|| {
+        c2k.inner.set_decode_strategy(strategy.to_strategy()?);
+        Ok(())
+    })
+}
+
+/// 推論を行い、NUL終端されたUTF-8の読みを呼び出し側のバッファに書き込む。
+///
+/// `written`には、NULを含めた読みのバイト数が書き込まれる。
+/// `buffer_len`が足りない場合は[E2kResult::BufferTooSmall]を返し、`buffer`には何も書き込まない。
+/// このとき`written`の大きさのバッファで呼び直せば成功する（シードを固定していないサンプリングを除く）。
+/// `buffer`がNULLの場合は`buffer_len`を0として扱う。
+///
+/// # Safety
+///
+/// `c2k`は有効な変換器、`input`はNUL終端された文字列、
+/// `buffer`はNULLか`buffer_len`バイト書き込めるポインタ、`written`は書き込み可能なポインタである必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_c2k_infer(
+    c2k: *const E2kC2k,
+    input: *const c_char,
+    buffer: *mut c_char,
+    buffer_len: usize,
+    written: *mut usize,
+) -> E2kResult {
+    let Some(c2k) = (unsafe { c2k.as_ref() }) else {
+        return E2kResult::NullPointer;
+    };
+    if written.is_null() {
+        return E2kResult::NullPointer;
+    }
+    catch(|| {
+        let input = unsafe { read_input(input) }?;
+        let output = c2k.inner.infer(input);
+        let len = output.len() + 1;
+        unsafe { written.write(len) };
+        if buffer.is_null() || buffer_len < len {
+            return Err(E2kResult::BufferTooSmall);
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(output.as_ptr(), buffer.cast(), output.len());
+            buffer.add(output.len()).write(0);
+        }
+        Ok(())
+    })
+}
+
+/// 推論を行い、ライブラリが確保したNUL終端されたUTF-8の読みを`out`に書き込む。
+///
+/// 読みは[e2k_string_free]で解放してください。
+///
+/// # Safety
+///
+/// `c2k`は有効な変換器、`input`はNUL終端された文字列、`out`は書き込み可能なポインタである必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_c2k_infer_alloc(
+    c2k: *const E2kC2k,
+    input: *const c_char,
+    out: *mut *mut c_char,
+) -> E2kResult {
+    let Some(c2k) = (unsafe { c2k.as_ref() }) else {
+        return E2kResult::NullPointer;
+    };
+    if out.is_null() {
+        return E2kResult::NullPointer;
+    }
+    catch(|| {
+        let input = unsafe { read_input(input) }?;
+        let output =
+            CString::new(c2k.inner.infer(input)).expect("Unreachable: kana should not contain NUL");
+        unsafe { out.write(output.into_raw()) };
+        Ok(())
+    })
+}
+
+/// [e2k_c2k_infer_alloc]が返した文字列を解放する。NULLの場合は何もしない。
+///
+/// # Safety
+///
+/// `string`は[e2k_c2k_infer_alloc]が返し、まだ解放されていない必要がある。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn e2k_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
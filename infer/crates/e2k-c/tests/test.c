@@ -0,0 +1,87 @@
+// C APIのテスト。tests/test_main.rsからコンパイルして実行される。
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "e2k.h"
+
+#define CHECK(expr)                                                    \
+  do {                                                                 \
+    if (!(expr)) {                                                     \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+              #expr);                                                  \
+      exit(1);                                                         \
+    }                                                                  \
+  } while (0)
+
+#define CHECK_OK(expr)                                             \
+  do {                                                             \
+    E2kResult result = (expr);                                     \
+    if (result != E2K_RESULT_OK) {                                 \
+      fprintf(stderr, "%s:%d: %s failed: %s\n", __FILE__, __LINE__, \
+              #expr, e2k_result_to_string(result));                \
+      exit(1);                                                     \
+    }                                                              \
+  } while (0)
+
+int main(void) {
+  E2kC2k *c2k = NULL;
+  CHECK_OK(e2k_c2k_create(32, &c2k));
+  CHECK(c2k != NULL);
+
+  // ライブラリが確保したバッファと、呼び出し側のバッファで同じ結果になる。
+  char *allocated = NULL;
+  CHECK_OK(e2k_c2k_infer_alloc(c2k, "constants", &allocated));
+  CHECK(allocated != NULL);
+
+  size_t written = 0;
+  CHECK(e2k_c2k_infer(c2k, "constants", NULL, 0, &written) ==
+        E2K_RESULT_BUFFER_TOO_SMALL);
+  CHECK(written == strlen(allocated) + 1);
+
+  char *buffer = malloc(written);
+  CHECK_OK(e2k_c2k_infer(c2k, "constants", buffer, written, &written));
+  CHECK(strcmp(buffer, allocated) == 0);
+  free(buffer);
+  e2k_string_free(allocated);
+
+  // シードを固定したサンプリングは同じ結果になる。
+  E2kStrategy strategy;
+  CHECK_OK(e2k_strategy_default(E2K_STRATEGY_KIND_TOP_K, &strategy));
+  CHECK(strategy.kind == E2K_STRATEGY_KIND_TOP_K);
+  CHECK(strategy.k == 3);
+  strategy.has_seed = true;
+  strategy.seed = 42;
+  CHECK_OK(e2k_c2k_set_strategy(c2k, &strategy));
+  char first[256];
+  char second[256];
+  CHECK_OK(e2k_c2k_infer(c2k, "algorithm", first, sizeof first, &written));
+  CHECK_OK(e2k_c2k_infer(c2k, "algorithm", second, sizeof second, &written));
+  CHECK(strcmp(first, second) == 0);
+
+  // 不正な引数はエラーになる。
+  CHECK_OK(e2k_strategy_default(E2K_STRATEGY_KIND_TOP_P, &strategy));
+  strategy.p = 1.5f;
+  CHECK(e2k_c2k_set_strategy(c2k, &strategy) ==
+        E2K_RESULT_INVALID_ARGUMENT);
+  // 範囲外のアルゴリズムの種類。
+  CHECK(e2k_strategy_default(42, &strategy) == E2K_RESULT_INVALID_ARGUMENT);
+  CHECK_OK(e2k_strategy_default(E2K_STRATEGY_KIND_GREEDY, &strategy));
+  strategy.kind = 42;
+  CHECK(e2k_c2k_set_strategy(c2k, &strategy) ==
+        E2K_RESULT_INVALID_ARGUMENT);
+  CHECK(strcmp(e2k_result_to_string(42), "unknown result") == 0);
+  CHECK(e2k_c2k_infer(c2k, "\xff", first, sizeof first, &written) ==
+        E2K_RESULT_INVALID_UTF8);
+  CHECK(e2k_c2k_infer(c2k, NULL, first, sizeof first, &written) ==
+        E2K_RESULT_NULL_POINTER);
+  CHECK(e2k_c2k_infer_alloc(NULL, "constants", &allocated) ==
+        E2K_RESULT_NULL_POINTER);
+
+  e2k_c2k_destroy(c2k);
+  e2k_c2k_destroy(NULL);
+  e2k_string_free(NULL);
+
+  printf("ok\n");
+  return 0;
+}
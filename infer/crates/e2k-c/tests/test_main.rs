@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+fn crate_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// `include/e2k.h`がcbindgenの出力と一致することを確かめる。
+///
+/// `E2K_UPDATE_HEADER=1`を指定して実行すると、ヘッダーを更新する。
+#[test]
+fn test_header_is_up_to_date() {
+    let mut header = Vec::new();
+    cbindgen::generate(crate_dir()).unwrap().write(&mut header);
+    let header = String::from_utf8(header).unwrap();
+    let path = crate_dir().join("include/e2k.h");
+    if std::env::var_os("E2K_UPDATE_HEADER").is_some() {
+        std::fs::write(&path, &header).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        header,
+        "include/e2k.h is outdated; run `E2K_UPDATE_HEADER=1 cargo test -p e2k-c`"
+    );
+}
+
+/// `tests/test.c`を静的ライブラリとリンクして実行する。
+///
+/// `cargo test`はこのクレートの静的ライブラリもテストと同じ`deps`ディレクトリにビルドするため、それをリンクする。
+#[cfg(unix)]
+#[test]
+fn test_c() {
+    let library = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("libe2k_c.a");
+    assert!(library.exists(), "{} not found", library.display());
+
+    let executable = Path::new(env!("CARGO_TARGET_TMPDIR")).join("e2k_c_test");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = std::process::Command::new(compiler)
+        .arg(crate_dir().join("tests/test.c"))
+        .arg("-I")
+        .arg(crate_dir().join("include"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&executable)
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to compile tests/test.c");
+
+    let output = std::process::Command::new(&executable).output().unwrap();
+    assert!(
+        output.status.success(),
+        "tests/test.c failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,124 @@
+//! 評価データで[e2k::C2k]の精度を測る`eval`サブコマンド。
+//!
+//! 評価データは`単語<TAB>正解の読み`の行からなるTSVで、正解率、文字誤り率（CER）、
+//! モーラ単位の正解率を出力します。`--min-accuracy`などを指定すると、下回った場合に失敗します。
+
+use crate::strategy::StrategyArgs;
+use anyhow::Context as _;
+use clap::ValueEnum;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(clap::Args)]
+pub(crate) struct EvalArgs {
+    /// 評価データ（`単語<TAB>正解の読み`の行からなるTSV）。`-`は標準入力。
+    data: PathBuf,
+
+    /// safetensors形式のモデル。省略した場合は組み込みのモデルを使う。
+    #[arg(long)]
+    model: Option<PathBuf>,
+
+    /// 集計の出力形式。
+    #[arg(short, long, default_value = "text")]
+    format: Format,
+
+    /// 単語ごとの結果（`単語<TAB>正解<TAB>推論<TAB>編集距離`）を書き出すファイル。`-`は標準エラー出力。
+    #[arg(long)]
+    diff: Option<PathBuf>,
+
+    /// `--diff`に正解した単語も書き出す。
+    #[arg(long)]
+    all: bool,
+
+    /// 正解率（0〜1）がこれを下回った場合に失敗する。
+    #[arg(long)]
+    min_accuracy: Option<f64>,
+
+    /// 文字誤り率（0〜1）がこれを上回った場合に失敗する。
+    #[arg(long)]
+    max_cer: Option<f64>,
+
+    /// 読みの最大長。
+    #[arg(short, long, default_value = "32")]
+    max_length: usize,
+
+    #[command(flatten)]
+    strategy: StrategyArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+}
+
+pub(crate) fn run(args: &EvalArgs) -> anyhow::Result<()> {
+    let entries = if args.data.as_os_str() == "-" {
+        e2k::EvalEntry::read_tsv(std::io::stdin().lock())
+    } else {
+        let file = std::fs::File::open(&args.data)
+            .with_context(|| format!("failed to open {}", args.data.display()))?;
+        e2k::EvalEntry::read_tsv(std::io::BufReader::new(file))
+    }
+    .with_context(|| format!("failed to read {}", args.data.display()))?;
+
+    let c2k = match &args.model {
+        Some(path) => {
+            let data = std::fs::read(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            e2k::C2k::from_bytes(&data, args.max_length)
+                .with_context(|| format!("failed to load {}", path.display()))?
+        }
+        None => e2k::C2k::new(args.max_length),
+    };
+    let options = e2k::InferOptions {
+        strategy: args.strategy.strategy()?,
+        ..c2k.default_options().clone()
+    };
+
+    let report = e2k::EvalReport::evaluate(&c2k, &entries, &options)?;
+    let summary = report.summary();
+    match args.format {
+        Format::Text => println!("{summary}"),
+        Format::Json => println!(
+            "{}",
+            serde_json::json!({
+                "words": summary.words,
+                "exact_matches": summary.exact_matches,
+                "accuracy": summary.accuracy,
+                "cer": summary.cer,
+                "mean_char_distance": summary.mean_char_distance,
+                "mora_accuracy": summary.mora_accuracy,
+            })
+        ),
+    }
+
+    if let Some(path) = &args.diff {
+        if path.as_os_str() == "-" {
+            report.write_diff(std::io::stderr().lock(), !args.all)?;
+        } else {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            let mut writer = std::io::BufWriter::new(file);
+            report.write_diff(&mut writer, !args.all)?;
+            writer.flush()?;
+        }
+    }
+
+    if let Some(min_accuracy) = args.min_accuracy {
+        anyhow::ensure!(
+            summary.accuracy >= min_accuracy,
+            "accuracy {} is below {min_accuracy}",
+            summary.accuracy
+        );
+    }
+    if let Some(max_cer) = args.max_cer {
+        anyhow::ensure!(
+            summary.cer <= max_cer,
+            "cer {} is above {max_cer}",
+            summary.cer
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+//! 英単語をカタカナに変換するコマンドラインツール。
+//!
+//! 単語は引数、ファイル、標準入力（1行1単語）から読み込み、標準出力に書き出します。
+//! `eval`サブコマンドでは評価データで精度を測ります。
+
+mod eval;
+mod strategy;
+
+use anyhow::Context as _;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use strategy::StrategyArgs;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "英単語をカタカナに変換する。",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 評価データでC2kの精度を測る。
+    Eval(eval::EvalArgs),
+}
+
+#[derive(clap::Args)]
+struct Args {
+    /// 変換する単語。省略した場合は`--input`か標準入力から1行1単語で読み込む。
+    words: Vec<String>,
+
+    /// 単語を読み込むファイル。`-`は標準入力。複数指定できる。
+    #[arg(short, long)]
+    input: Vec<PathBuf>,
+
+    /// 出力形式。
+    #[arg(short, long, default_value = "text")]
+    format: Format,
+
+    /// 読みの最大長。
+    #[arg(short, long, default_value = "32")]
+    max_length: usize,
+
+    #[command(flatten)]
+    strategy: StrategyArgs,
+
+    /// ビームサーチで対数確率の高い順に最大N個の候補を出力する。アルゴリズムは使われない。
+    #[arg(short, long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    n_best: Option<usize>,
+
+    /// 入力を正規化してから変換する（全角を半角に、大文字を小文字に、連続する空白を1つに）。
+    #[arg(long)]
+    normalize: bool,
+
+    /// ユーザー辞書（`単語<TAB>読み`の行からなるファイル）。辞書にある単語は辞書の読みを出力する。
+    #[arg(short = 'd', long)]
+    user_dict: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Format {
+    /// 読みのみ。n-bestの場合は候補をタブ区切りで出力する。
+    Text,
+    /// `単語<TAB>読み`。n-bestの場合は候補ごとに`単語<TAB>読み<TAB>対数確率`。
+    Tsv,
+    /// JSON Lines。
+    Jsonl,
+}
+
+/// 読みをどこから得たか。
+#[derive(Debug, Clone, Copy)]
+enum Source {
+    Model,
+    UserDict,
+}
+
+impl Source {
+    fn as_str(self) -> &'static str {
+        match self {
+            Source::Model => "model",
+            Source::UserDict => "user_dict",
+        }
+    }
+}
+
+struct Converter {
+    c2k: e2k::C2k,
+    options: e2k::InferOptions,
+    user_dict: e2k::UserDict,
+    normalize: bool,
+    n_best: Option<usize>,
+}
+
+impl Converter {
+    fn new(args: &Args) -> anyhow::Result<Self> {
+        let user_dict = match &args.user_dict {
+            Some(path) => {
+                let file = std::fs::File::open(path)
+                    .with_context(|| format!("failed to open {}", path.display()))?;
+                e2k::UserDict::from_reader(std::io::BufReader::new(file))
+                    .with_context(|| format!("failed to read {}", path.display()))?
+            }
+            None => e2k::UserDict::new(),
+        };
+        let c2k = e2k::C2k::new(args.max_length);
+        let options = e2k::InferOptions {
+            strategy: args.strategy.strategy()?,
+            ..c2k.default_options().clone()
+        };
+        Ok(Self {
+            c2k,
+            options,
+            user_dict,
+            normalize: args.normalize,
+            n_best: args.n_best,
+        })
+    }
+
+    /// 単語を変換し、候補と対数確率を返す。n-bestでない場合、候補は1つで対数確率は`None`。
+    fn convert(&self, word: &str) -> (Vec<(String, Option<f32>)>, Source) {
+        if let Some(reading) = self.user_dict.get(word) {
+            let log_prob = self.n_best.map(|_| 0.0);
+            return (vec![(reading.to_string(), log_prob)], Source::UserDict);
+        }
+        let word = if self.normalize {
+            std::borrow::Cow::Owned(e2k::normalize(word))
+        } else {
+            std::borrow::Cow::Borrowed(word)
+        };
+        let candidates = match self.n_best {
+            Some(n) => self
+                .c2k
+                .infer_n_best(&word, n, &self.options)
+                .into_iter()
+                .map(|c| (c.kana, Some(c.log_prob)))
+                .collect(),
+            None => {
+                let kana = self
+                    .c2k
+                    .infer_with(&word, &self.options)
+                    .expect("Unreachable: strategy is validated in StrategyArgs::strategy");
+                vec![(kana, None)]
+            }
+        };
+        (candidates, Source::Model)
+    }
+
+    fn write(&self, output: &mut impl Write, format: Format, word: &str) -> anyhow::Result<()> {
+        let (candidates, source) = self.convert(word);
+        match format {
+            Format::Text => {
+                let kana = candidates
+                    .into_iter()
+                    .map(|(kana, _)| kana)
+                    .collect::<Vec<_>>();
+                writeln!(output, "{}", kana.join("\t"))?;
+            }
+            Format::Tsv => {
+                for (kana, log_prob) in candidates {
+                    match log_prob {
+                        Some(log_prob) => writeln!(output, "{word}\t{kana}\t{log_prob}")?,
+                        None => writeln!(output, "{word}\t{kana}")?,
+                    }
+                }
+            }
+            Format::Jsonl => {
+                let line = if self.n_best.is_some() {
+                    let candidates = candidates
+                        .into_iter()
+                        .map(|(kana, log_prob)| {
+                            serde_json::json!({"kana": kana, "log_prob": log_prob})
+                        })
+                        .collect::<Vec<_>>();
+                    serde_json::json!({
+                        "word": word,
+                        "candidates": candidates,
+                        "source": source.as_str(),
+                    })
+                } else {
+                    let (kana, _) = candidates.into_iter().next().unwrap_or_default();
+                    serde_json::json!({"word": word, "kana": kana, "source": source.as_str()})
+                };
+                writeln!(output, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 1行1単語で読み込み、空行を除いて変換する。
+fn convert_lines(
+    converter: &Converter,
+    reader: impl BufRead,
+    output: &mut impl Write,
+    format: Format,
+) -> anyhow::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        let word = line.trim();
+        if !word.is_empty() {
+            converter.write(output, format, word)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    if let Some(Command::Eval(args)) = cli.command {
+        return eval::run(&args);
+    }
+    let args = cli.args;
+    let converter = Converter::new(&args)?;
+
+    let stdout = std::io::stdout();
+    let mut output = std::io::BufWriter::new(stdout.lock());
+    for word in &args.words {
+        converter.write(&mut output, args.format, word)?;
+    }
+    for path in &args.input {
+        if path.as_os_str() == "-" {
+            convert_lines(
+                &converter,
+                std::io::stdin().lock(),
+                &mut output,
+                args.format,
+            )?;
+        } else {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            convert_lines(
+                &converter,
+                std::io::BufReader::new(file),
+                &mut output,
+                args.format,
+            )?;
+        }
+    }
+    if args.words.is_empty() && args.input.is_empty() {
+        convert_lines(
+            &converter,
+            std::io::stdin().lock(),
+            &mut output,
+            args.format,
+        )?;
+    }
+    output.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Context as _;
+use clap::ValueEnum;
+
+/// デコードに使うアルゴリズムの引数。変換と評価で共通。
+#[derive(clap::Args, Debug)]
+pub(crate) struct StrategyArgs {
+    /// アルゴリズム。
+    #[arg(short, long, default_value = "greedy")]
+    strategy: StrategyArg,
+
+    /// Top-KのK。
+    #[arg(short = 'k', long, default_value = "3")]
+    top_k: usize,
+
+    /// Top-PのP。
+    #[arg(short = 'p', long, default_value = "0.9")]
+    top_p: f32,
+
+    /// Min-PのP。
+    #[arg(long, default_value = "0.1")]
+    min_p: f32,
+
+    /// TypicalのP。
+    #[arg(long, default_value = "0.9")]
+    typical_p: f32,
+
+    /// Greedy以外のアルゴリズムの温度。
+    #[arg(short = 't', long, default_value = "1.0")]
+    temperature: f32,
+
+    /// 乱数のシード。
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(ValueEnum, Debug, Clone)]
+enum StrategyArg {
+    Greedy,
+    TopK,
+    TopP,
+    MinP,
+    Typical,
+    Temperature,
+}
+
+impl StrategyArgs {
+    pub(crate) fn strategy(&self) -> anyhow::Result<e2k::Strategy> {
+        let strategy = match self.strategy {
+            StrategyArg::Greedy => e2k::Strategy::Greedy,
+            StrategyArg::TopK => e2k::Strategy::TopK(e2k::StrategyTopK {
+                k: self.top_k,
+                temperature: self.temperature,
+                seed: self.seed,
+            }),
+            StrategyArg::TopP => e2k::Strategy::TopP(e2k::StrategyTopP {
+                top_p: self.top_p,
+                temperature: self.temperature,
+                seed: self.seed,
+            }),
+            StrategyArg::MinP => e2k::Strategy::MinP(e2k::StrategyMinP {
+                min_p: self.min_p,
+                temperature: self.temperature,
+                seed: self.seed,
+            }),
+            StrategyArg::Typical => e2k::Strategy::Typical(e2k::StrategyTypical {
+                typical_p: self.typical_p,
+                temperature: self.temperature,
+                seed: self.seed,
+            }),
+            StrategyArg::Temperature => e2k::Strategy::Temperature(e2k::StrategyTemperature {
+                temperature: self.temperature,
+                seed: self.seed,
+            }),
+        };
+        strategy.validate().context("invalid strategy")?;
+        Ok(strategy)
+    }
+}
@@ -0,0 +1,252 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_e2k"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn stdout_lines(output: &Output) -> Vec<String> {
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn c2k() -> e2k::C2k {
+    e2k::C2k::new(32)
+}
+
+#[test]
+fn test_args() {
+    let output = run(&["constants", "hello"], "");
+    let c2k = c2k();
+    assert_eq!(
+        stdout_lines(&output),
+        [c2k.infer("constants"), c2k.infer("hello")]
+    );
+}
+
+#[test]
+fn test_stdin() {
+    let output = run(&[], "constants\n\n  hello  \n");
+    let c2k = c2k();
+    assert_eq!(
+        stdout_lines(&output),
+        [c2k.infer("constants"), c2k.infer("hello")]
+    );
+}
+
+#[test]
+fn test_input_file() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("e2k_cli_input.txt");
+    std::fs::write(&path, "constants\nhello\n").unwrap();
+    let output = run(
+        &[
+            "--format",
+            "tsv",
+            "world",
+            "--input",
+            path.to_str().unwrap(),
+            "-i",
+            "-",
+        ],
+        "stdin\n",
+    );
+    let c2k = c2k();
+    assert_eq!(
+        stdout_lines(&output),
+        ["world", "constants", "hello", "stdin"].map(|word| format!("{word}\t{}", c2k.infer(word)))
+    );
+}
+
+#[test]
+fn test_jsonl() {
+    let output = run(&["--format", "jsonl", "constants"], "");
+    let lines = stdout_lines(&output);
+    assert_eq!(lines.len(), 1);
+    let value: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "word": "constants",
+            "kana": c2k().infer("constants"),
+            "source": "model",
+        })
+    );
+}
+
+#[test]
+fn test_n_best() {
+    let output = run(&["--format", "jsonl", "--n-best", "3", "constants"], "");
+    let value: serde_json::Value = serde_json::from_str(&stdout_lines(&output)[0]).unwrap();
+    let expected = c2k().infer_n_best("constants", 3, &e2k::InferOptions::default());
+    let candidates = value["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), expected.len());
+    for (candidate, expected) in candidates.iter().zip(&expected) {
+        assert_eq!(candidate["kana"], expected.kana);
+        assert!((candidate["log_prob"].as_f64().unwrap() - expected.log_prob as f64).abs() < 1e-4);
+    }
+
+    let output = run(&["--format", "tsv", "-n", "2", "constants"], "");
+    let lines = stdout_lines(&output);
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| line.split('\t').count() == 3));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_e2k"))
+        .args(["-n", "0", "constants"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_strategy() {
+    let args = [
+        "--strategy",
+        "top-k",
+        "-k",
+        "5",
+        "--seed",
+        "42",
+        "constants",
+    ];
+    let first = run(&args, "");
+    let second = run(&args, "");
+    assert_eq!(first.stdout, second.stdout);
+
+    let options = e2k::InferOptions {
+        strategy: e2k::Strategy::TopK(e2k::StrategyTopK {
+            k: 5,
+            temperature: 1.0,
+            seed: Some(42),
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        stdout_lines(&first),
+        [c2k().infer_with("constants", &options).unwrap()]
+    );
+}
+
+#[test]
+fn test_user_dict_and_normalize() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("e2k_cli_user_dict.tsv");
+    std::fs::write(&path, "# 固有名詞\nVOICEVOX\tボイスボックス\n").unwrap();
+    let output = run(
+        &[
+            "--format",
+            "jsonl",
+            "--user-dict",
+            path.to_str().unwrap(),
+            "--normalize",
+            "ＶｏｉｃｅＶｏｘ",
+            "HELLO",
+        ],
+        "",
+    );
+    let values = stdout_lines(&output)
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            serde_json::json!({"word": "ＶｏｉｃｅＶｏｘ", "kana": "ボイスボックス", "source": "user_dict"}),
+            serde_json::json!({"word": "HELLO", "kana": c2k().infer("hello"), "source": "model"}),
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_user_dict() {
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("e2k_cli_invalid.tsv");
+    std::fs::write(&path, "broken\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_e2k"))
+        .args(["--user-dict", path.to_str().unwrap(), "constants"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 1"));
+}
+
+#[test]
+fn test_invalid_strategy() {
+    let output = Command::new(env!("CARGO_BIN_EXE_e2k"))
+        .args(["--strategy", "top-p", "--top-p", "1.5", "constants"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("top_p"));
+}
+
+#[test]
+fn test_eval() {
+    let data = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../e2k-rs/tests/data/heldout_words.tsv"
+    );
+    let diff = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("e2k_cli_eval_diff.tsv");
+    let output = run(
+        &[
+            "eval",
+            data,
+            "--format",
+            "json",
+            "--all",
+            "--diff",
+            diff.to_str().unwrap(),
+        ],
+        "",
+    );
+
+    let c2k = c2k();
+    let entries =
+        e2k::EvalEntry::read_tsv(std::io::BufReader::new(std::fs::File::open(data).unwrap()))
+            .unwrap();
+    let report = e2k::EvalReport::evaluate(&c2k, &entries, c2k.default_options()).unwrap();
+    let summary = report.summary();
+    let json = serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+    assert_eq!(json["words"], entries.len());
+    assert_eq!(json["exact_matches"], summary.exact_matches);
+    assert_eq!(json["cer"], summary.cer);
+
+    let mut expected = Vec::new();
+    report.write_diff(&mut expected, false).unwrap();
+    assert_eq!(std::fs::read(&diff).unwrap(), expected);
+
+    // 閾値を満たさない場合は失敗する。
+    let output = Command::new(env!("CARGO_BIN_EXE_e2k"))
+        .args(["eval", data, "--min-accuracy", "1.1"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is below 1.1"));
+
+    // 変換と同じアルゴリズムの引数を受け付け、同じ範囲で検証する。
+    let output = Command::new(env!("CARGO_BIN_EXE_e2k"))
+        .args(["eval", data, "--strategy", "top-p", "--top-p", "1.5"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("top_p"));
+}
@@ -1,97 +1,290 @@
-use pyo3::prelude::*;
-use pyo3::types::PyDict;
-
-fn extract_strategy(strategy: &str, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<e2k::Strategy> {
-    Ok(match strategy {
-        "greedy" => e2k::Strategy::Greedy,
-        "top_k" => {
-            let k = kwargs
-                .map(|kwargs| kwargs.get_item("k"))
-                .transpose()?
-                .flatten()
-                .map(|k| k.extract::<usize>())
-                .transpose()?;
-
-            let mut strategy = e2k::StrategyTopK::default();
-            if let Some(k) = k {
-                strategy.k = k;
-            }
+mod strategy;
+mod user_dict;
 
-            e2k::Strategy::TopK(strategy)
-        }
-        "top_p" => {
-            let top_p = kwargs
-                .map(|kwargs| kwargs.get_item("p"))
-                .transpose()?
-                .flatten()
-                .map(|top_p| top_p.extract::<f32>())
-                .transpose()?;
-
-            let temperature = kwargs
-                .map(|kwargs| kwargs.get_item("t"))
-                .transpose()?
-                .flatten()
-                .map(|temperature| temperature.extract::<f32>())
-                .transpose()?;
-
-            let mut strategy = e2k::StrategyTopP::default();
-            if let Some(top_p) = top_p {
-                strategy.top_p = top_p;
-            }
-            if let Some(temperature) = temperature {
-                strategy.temperature = temperature;
-            }
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+use std::sync::{PoisonError, RwLock};
+use user_dict::UserDict;
 
-            e2k::Strategy::TopP(strategy)
-        }
-        _ => {
-            return Err(pyo3::exceptions::PyValueError::new_err(
-                "strategy must be one of 'greedy', 'top_k', 'top_p'",
-            ));
-        }
-    })
-}
+pyo3::create_exception!(
+    voicevox_e2k,
+    ModelLoadError,
+    pyo3::exceptions::PyValueError,
+    "モデルの読み込みに失敗した。"
+);
 
-#[pyclass(frozen)]
+#[pyclass(frozen, module = "voicevox_e2k")]
 struct C2k {
-    inner: std::sync::RwLock<e2k::C2k>,
+    inner: e2k::C2k,
+    options: RwLock<e2k::InferOptions>,
+    user_dict: RwLock<Option<Py<UserDict>>>,
+    /// 組み込みのモデルを使っているかどうか。pickleできるのは組み込みのモデルのみ。
+    embedded: bool,
 }
 
 #[pymethods]
 impl C2k {
     #[new]
-    #[pyo3(signature = (max_length = 32))]
-    fn new(max_length: usize) -> Self {
-        Self {
-            inner: std::sync::RwLock::new(e2k::C2k::new(max_length)),
-        }
+    #[pyo3(signature = (max_length = 32, *, strategy = None, user_dict = None))]
+    fn new(
+        max_length: usize,
+        strategy: Option<&Bound<'_, PyAny>>,
+        user_dict: Option<Py<UserDict>>,
+    ) -> PyResult<Self> {
+        Self::with_inner(e2k::C2k::new(max_length), true, strategy, user_dict)
+    }
+
+    /// safetensors形式のファイルからモデルを読み込む。
+    #[staticmethod]
+    #[pyo3(signature = (path, *, max_length = 32, strategy = None, user_dict = None))]
+    fn from_file(
+        py: Python<'_>,
+        path: std::path::PathBuf,
+        max_length: usize,
+        strategy: Option<&Bound<'_, PyAny>>,
+        user_dict: Option<Py<UserDict>>,
+    ) -> PyResult<Self> {
+        let inner = py.allow_threads(|| {
+            let data = std::fs::read(path)?;
+            e2k::C2k::from_bytes(&data, max_length).map_err(load_error)
+        })?;
+        Self::with_inner(inner, false, strategy, user_dict)
+    }
+
+    /// safetensors形式のバイト列からモデルを読み込む。
+    #[staticmethod]
+    #[pyo3(signature = (data, *, max_length = 32, strategy = None, user_dict = None))]
+    fn from_bytes(
+        py: Python<'_>,
+        data: &[u8],
+        max_length: usize,
+        strategy: Option<&Bound<'_, PyAny>>,
+        user_dict: Option<Py<UserDict>>,
+    ) -> PyResult<Self> {
+        let inner = py
+            .allow_threads(|| e2k::C2k::from_bytes(data, max_length))
+            .map_err(load_error)?;
+        Self::with_inner(inner, false, strategy, user_dict)
     }
 
     #[pyo3(signature = (strategy, **kwargs))]
     fn set_decode_strategy(
         &self,
-        strategy: &str,
+        strategy: &Bound<'_, PyAny>,
         kwargs: Option<&Bound<'_, PyDict>>,
     ) -> PyResult<()> {
-        let strategy = extract_strategy(strategy, kwargs)?;
-
-        {
-            let mut inner = self.inner.write().unwrap();
-
-            inner.set_decode_strategy(strategy);
+        let strategy = if let Ok(name) = strategy.downcast::<PyString>() {
+            strategy::strategy_from_name(strategy.py(), name.to_str()?, kwargs)?
+        } else if kwargs.is_some_and(|kwargs| !kwargs.is_empty()) {
+            return Err(pyo3::exceptions::PyTypeError::new_err(
+                "keyword arguments can only be used with a strategy name",
+            ));
+        } else {
+            strategy.clone()
         };
+        let strategy = strategy::extract_strategy(&strategy)?;
+
+        self.write_options().strategy = strategy;
 
         Ok(())
     }
 
-    fn __call__(&self, src: &str) -> String {
-        self.inner.read().unwrap().infer(src)
+    #[getter]
+    fn max_length(&self) -> usize {
+        self.read_options().max_length
+    }
+
+    #[getter]
+    fn strategy<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        strategy::strategy_to_object(py, &self.read_options().strategy)
+    }
+
+    #[getter]
+    fn user_dict(&self, py: Python<'_>) -> Option<Py<UserDict>> {
+        self.user_dict
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|user_dict| user_dict.clone_ref(py))
+    }
+
+    #[setter]
+    fn set_user_dict(&self, user_dict: Option<Py<UserDict>>) {
+        *self
+            .user_dict
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = user_dict;
+    }
+
+    #[pyo3(signature = (word, *, strategy = None))]
+    fn __call__(
+        &self,
+        py: Python<'_>,
+        word: &str,
+        strategy: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<String> {
+        let options = self.options(strategy)?;
+        Ok(py.allow_threads(|| self.infer(word, &options)))
+    }
+
+    #[pyo3(signature = (words, *, strategy = None))]
+    fn convert_batch(
+        &self,
+        py: Python<'_>,
+        words: Vec<String>,
+        strategy: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Vec<String>> {
+        let options = self.options(strategy)?;
+        Ok(py.allow_threads(|| {
+            words
+                .iter()
+                .map(|word| self.infer(word, &options))
+                .collect()
+        }))
+    }
+
+    fn n_best(&self, py: Python<'_>, word: &str, n: usize) -> PyResult<Vec<(String, f32)>> {
+        let options = self.options(None)?;
+        Ok(py.allow_threads(|| {
+            if let Some(reading) = self.lookup(word) {
+                return vec![(reading, 0.0)];
+            }
+            self.inner
+                .infer_n_best(word, n, &options)
+                .into_iter()
+                .map(|candidate| (candidate.kana, candidate.log_prob))
+                .collect()
+        }))
+    }
+
+    #[pyo3(signature = (word, *, strategy = None))]
+    fn convert_with_score(
+        &self,
+        py: Python<'_>,
+        word: &str,
+        strategy: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<(String, f32)> {
+        let options = self.options(strategy)?;
+        Ok(py.allow_threads(|| {
+            if let Some(reading) = self.lookup(word) {
+                return (reading, 0.0);
+            }
+            let mut stream = self
+                .inner
+                .infer_stream_with(word, &options)
+                .expect("Unreachable: strategy is validated in extract_strategy");
+            let (kana, log_prob) =
+                stream
+                    .by_ref()
+                    .fold((String::new(), 0.0), |(mut kana, log_prob), token| {
+                        kana.push(token.kana);
+                        (kana, log_prob + token.probability.ln())
+                    });
+            (
+                kana,
+                log_prob + stream.eos_probability().map_or(0.0, f32::ln),
+            )
+        }))
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "C2k(max_length={}, strategy={})",
+            self.max_length(),
+            self.strategy(py)?.repr()?
+        ))
+    }
+
+    fn __getnewargs_ex__<'py>(&self, py: Python<'py>) -> PyResult<((), Bound<'py, PyDict>)> {
+        if !self.embedded {
+            return Err(pyo3::exceptions::PyTypeError::new_err(
+                "C2k loaded from a custom model cannot be pickled",
+            ));
+        }
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("max_length", self.max_length())?;
+        kwargs.set_item("strategy", self.strategy(py)?)?;
+        kwargs.set_item("user_dict", self.user_dict(py))?;
+        Ok(((), kwargs))
+    }
+}
+
+/// [e2k::LoadError]を`ModelLoadError`にする。
+fn load_error(e: e2k::LoadError) -> PyErr {
+    ModelLoadError::new_err(e.to_string())
+}
+
+impl C2k {
+    fn with_inner(
+        inner: e2k::C2k,
+        embedded: bool,
+        strategy: Option<&Bound<'_, PyAny>>,
+        user_dict: Option<Py<UserDict>>,
+    ) -> PyResult<Self> {
+        let mut options = inner.default_options().clone();
+        if let Some(strategy) = strategy {
+            options.strategy = strategy::extract_strategy(strategy)?;
+        }
+        Ok(Self {
+            inner,
+            options: RwLock::new(options),
+            user_dict: RwLock::new(user_dict),
+            embedded,
+        })
+    }
+
+    fn read_options(&self) -> std::sync::RwLockReadGuard<'_, e2k::InferOptions> {
+        self.options.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_options(&self) -> std::sync::RwLockWriteGuard<'_, e2k::InferOptions> {
+        self.options.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// ユーザー辞書に単語があればその読みを返す。
+    fn lookup(&self, word: &str) -> Option<String> {
+        let user_dict = self
+            .user_dict
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        user_dict
+            .as_ref()?
+            .get()
+            .read()
+            .get(word)
+            .map(str::to_string)
+    }
+
+    /// ユーザー辞書に単語があればその読みを、無ければ推論した読みを返す。
+    fn infer(&self, word: &str, options: &e2k::InferOptions) -> String {
+        self.lookup(word).unwrap_or_else(|| {
+            self.inner
+                .infer_with(word, options)
+                .expect("Unreachable: strategy is validated in extract_strategy")
+        })
+    }
+
+    /// 推論に使うオプションを返す。`strategy`を指定した場合はその戦略を使う。
+    fn options(&self, strategy: Option<&Bound<'_, PyAny>>) -> PyResult<e2k::InferOptions> {
+        let mut options = self.read_options().clone();
+        if let Some(strategy) = strategy {
+            options.strategy = strategy::extract_strategy(strategy)?;
+        }
+        Ok(options)
     }
 }
 
 #[pymodule(name = "voicevox_e2k")]
 fn voicevox_e2k(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<C2k>()?;
+    m.add_class::<UserDict>()?;
+    m.add("ModelLoadError", m.py().get_type::<ModelLoadError>())?;
+    m.add_class::<strategy::Greedy>()?;
+    m.add_class::<strategy::TopK>()?;
+    m.add_class::<strategy::TopP>()?;
+    m.add_class::<strategy::MinP>()?;
+    m.add_class::<strategy::Typical>()?;
+    m.add_class::<strategy::Temperature>()?;
 
     m.add("KANAS", e2k::KANAS)?;
     m.add("ASCII_ENTRIES", e2k::ASCII_ENTRIES)?;
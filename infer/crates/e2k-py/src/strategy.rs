@@ -0,0 +1,370 @@
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple, PyType};
+
+/// [e2k::Strategy::validate]で検証する。エラーメッセージではPython側の引数名（`p`、`t`）を使う。
+fn validate(strategy: e2k::Strategy) -> PyResult<()> {
+    strategy.validate().map_err(|e| {
+        let message = match e {
+            e2k::StrategyError::InvalidProbability { value, .. } => {
+                format!("p must be greater than 0 and at most 1, got {value}")
+            }
+            e2k::StrategyError::InvalidTemperature(t) => {
+                format!("t must be a positive number, got {t}")
+            }
+            e => e.to_string(),
+        };
+        PyValueError::new_err(message)
+    })
+}
+
+fn repr_seed(seed: Option<u64>) -> String {
+    seed.map_or_else(|| "None".to_string(), |seed| seed.to_string())
+}
+
+/// 最も確率の高い文字を選ぶ。
+#[pyclass(frozen, eq, module = "voicevox_e2k")]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Greedy;
+
+#[pymethods]
+impl Greedy {
+    #[new]
+    fn new() -> Self {
+        Self
+    }
+
+    fn __repr__(&self) -> String {
+        "Greedy()".to_string()
+    }
+
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, Bound<'py, PyTuple>) {
+        (slf.get_type(), PyTuple::empty(slf.py()))
+    }
+}
+
+/// 確率の高い`k`個の文字からサンプリングする。
+#[pyclass(frozen, eq, module = "voicevox_e2k")]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TopK {
+    #[pyo3(get)]
+    k: usize,
+    #[pyo3(get)]
+    t: f32,
+    #[pyo3(get)]
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl TopK {
+    #[new]
+    #[pyo3(signature = (k = 3, t = 1.0, seed = None))]
+    fn new(k: usize, t: f32, seed: Option<u64>) -> PyResult<Self> {
+        validate(e2k::Strategy::TopK(e2k::StrategyTopK {
+            k,
+            temperature: t,
+            seed,
+        }))?;
+        Ok(Self { k, t, seed })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TopK(k={}, t={:?}, seed={})",
+            self.k,
+            self.t,
+            repr_seed(self.seed)
+        )
+    }
+
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, (usize, f32, Option<u64>)) {
+        let this = slf.get();
+        (slf.get_type(), (this.k, this.t, this.seed))
+    }
+}
+
+/// 確率の合計が`p`以上になるまで確率の高い順に文字を選び、その中からサンプリングする。
+#[pyclass(frozen, eq, module = "voicevox_e2k")]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TopP {
+    #[pyo3(get)]
+    p: f32,
+    #[pyo3(get)]
+    t: f32,
+    #[pyo3(get)]
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl TopP {
+    #[new]
+    #[pyo3(signature = (p = 0.9, t = 1.0, seed = None))]
+    fn new(p: f32, t: f32, seed: Option<u64>) -> PyResult<Self> {
+        validate(e2k::Strategy::TopP(e2k::StrategyTopP {
+            top_p: p,
+            temperature: t,
+            seed,
+        }))?;
+        Ok(Self { p, t, seed })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TopP(p={:?}, t={:?}, seed={})",
+            self.p,
+            self.t,
+            repr_seed(self.seed)
+        )
+    }
+
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, (f32, f32, Option<u64>)) {
+        let this = slf.get();
+        (slf.get_type(), (this.p, this.t, this.seed))
+    }
+}
+
+/// 最も確率の高い文字の`p`倍以上の確率を持つ文字からサンプリングする。
+#[pyclass(frozen, eq, module = "voicevox_e2k")]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MinP {
+    #[pyo3(get)]
+    p: f32,
+    #[pyo3(get)]
+    t: f32,
+    #[pyo3(get)]
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl MinP {
+    #[new]
+    #[pyo3(signature = (p = 0.1, t = 1.0, seed = None))]
+    fn new(p: f32, t: f32, seed: Option<u64>) -> PyResult<Self> {
+        validate(e2k::Strategy::MinP(e2k::StrategyMinP {
+            min_p: p,
+            temperature: t,
+            seed,
+        }))?;
+        Ok(Self { p, t, seed })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "MinP(p={:?}, t={:?}, seed={})",
+            self.p,
+            self.t,
+            repr_seed(self.seed)
+        )
+    }
+
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, (f32, f32, Option<u64>)) {
+        let this = slf.get();
+        (slf.get_type(), (this.p, this.t, this.seed))
+    }
+}
+
+/// 情報量がエントロピーに近い順に、確率の合計が`p`以上になるまで文字を選び、その中からサンプリングする。
+#[pyclass(frozen, eq, module = "voicevox_e2k")]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Typical {
+    #[pyo3(get)]
+    p: f32,
+    #[pyo3(get)]
+    t: f32,
+    #[pyo3(get)]
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl Typical {
+    #[new]
+    #[pyo3(signature = (p = 0.9, t = 1.0, seed = None))]
+    fn new(p: f32, t: f32, seed: Option<u64>) -> PyResult<Self> {
+        validate(e2k::Strategy::Typical(e2k::StrategyTypical {
+            typical_p: p,
+            temperature: t,
+            seed,
+        }))?;
+        Ok(Self { p, t, seed })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Typical(p={:?}, t={:?}, seed={})",
+            self.p,
+            self.t,
+            repr_seed(self.seed)
+        )
+    }
+
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, (f32, f32, Option<u64>)) {
+        let this = slf.get();
+        (slf.get_type(), (this.p, this.t, this.seed))
+    }
+}
+
+/// 全ての文字からサンプリングする。
+#[pyclass(frozen, eq, module = "voicevox_e2k")]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Temperature {
+    #[pyo3(get)]
+    t: f32,
+    #[pyo3(get)]
+    seed: Option<u64>,
+}
+
+#[pymethods]
+impl Temperature {
+    #[new]
+    #[pyo3(signature = (t = 1.0, seed = None))]
+    fn new(t: f32, seed: Option<u64>) -> PyResult<Self> {
+        validate(e2k::Strategy::Temperature(e2k::StrategyTemperature {
+            temperature: t,
+            seed,
+        }))?;
+        Ok(Self { t, seed })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Temperature(t={:?}, seed={})", self.t, repr_seed(self.seed))
+    }
+
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, (f32, Option<u64>)) {
+        let this = slf.get();
+        (slf.get_type(), (this.t, this.seed))
+    }
+}
+
+/// Pythonのデコード戦略のオブジェクトを[e2k::Strategy]にする。
+pub(crate) fn extract_strategy(strategy: &Bound<'_, PyAny>) -> PyResult<e2k::Strategy> {
+    if strategy.downcast::<Greedy>().is_ok() {
+        Ok(e2k::Strategy::Greedy)
+    } else if let Ok(s) = strategy.downcast::<TopK>() {
+        let s = s.get();
+        Ok(e2k::Strategy::TopK(e2k::StrategyTopK {
+            k: s.k,
+            temperature: s.t,
+            seed: s.seed,
+        }))
+    } else if let Ok(s) = strategy.downcast::<TopP>() {
+        let s = s.get();
+        Ok(e2k::Strategy::TopP(e2k::StrategyTopP {
+            top_p: s.p,
+            temperature: s.t,
+            seed: s.seed,
+        }))
+    } else if let Ok(s) = strategy.downcast::<MinP>() {
+        let s = s.get();
+        Ok(e2k::Strategy::MinP(e2k::StrategyMinP {
+            min_p: s.p,
+            temperature: s.t,
+            seed: s.seed,
+        }))
+    } else if let Ok(s) = strategy.downcast::<Typical>() {
+        let s = s.get();
+        Ok(e2k::Strategy::Typical(e2k::StrategyTypical {
+            typical_p: s.p,
+            temperature: s.t,
+            seed: s.seed,
+        }))
+    } else if let Ok(s) = strategy.downcast::<Temperature>() {
+        let s = s.get();
+        Ok(e2k::Strategy::Temperature(e2k::StrategyTemperature {
+            temperature: s.t,
+            seed: s.seed,
+        }))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "strategy must be one of Greedy, TopK, TopP, MinP, Typical, Temperature, got {}",
+            strategy.get_type().name()?
+        )))
+    }
+}
+
+/// [e2k::Strategy]をPythonのデコード戦略のオブジェクトにする。
+pub(crate) fn strategy_to_object<'py>(
+    py: Python<'py>,
+    strategy: &e2k::Strategy,
+) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match strategy {
+        e2k::Strategy::Greedy => Bound::new(py, Greedy)?.into_any(),
+        e2k::Strategy::TopK(s) => Bound::new(
+            py,
+            TopK {
+                k: s.k,
+                t: s.temperature,
+                seed: s.seed,
+            },
+        )?
+        .into_any(),
+        e2k::Strategy::TopP(s) => Bound::new(
+            py,
+            TopP {
+                p: s.top_p,
+                t: s.temperature,
+                seed: s.seed,
+            },
+        )?
+        .into_any(),
+        e2k::Strategy::MinP(s) => Bound::new(
+            py,
+            MinP {
+                p: s.min_p,
+                t: s.temperature,
+                seed: s.seed,
+            },
+        )?
+        .into_any(),
+        e2k::Strategy::Typical(s) => Bound::new(
+            py,
+            Typical {
+                p: s.typical_p,
+                t: s.temperature,
+                seed: s.seed,
+            },
+        )?
+        .into_any(),
+        e2k::Strategy::Temperature(s) => Bound::new(
+            py,
+            Temperature {
+                t: s.temperature,
+                seed: s.seed,
+            },
+        )?
+        .into_any(),
+    })
+}
+
+/// 文字列とキーワード引数で指定されたデコード戦略をPythonのオブジェクトにする。
+///
+/// 戦略ごとに受け付けないキーワード引数があれば`TypeError`にする。
+pub(crate) fn strategy_from_name<'py>(
+    py: Python<'py>,
+    name: &str,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let (class, allowed): (Bound<'py, PyType>, &[&str]) = match name {
+        "greedy" => (py.get_type::<Greedy>(), &[]),
+        "top_k" => (py.get_type::<TopK>(), &["k", "t", "seed"]),
+        "top_p" => (py.get_type::<TopP>(), &["p", "t", "seed"]),
+        "min_p" => (py.get_type::<MinP>(), &["p", "t", "seed"]),
+        "typical" => (py.get_type::<Typical>(), &["p", "t", "seed"]),
+        "temperature" => (py.get_type::<Temperature>(), &["t", "seed"]),
+        _ => {
+            return Err(PyValueError::new_err(
+                "strategy must be one of 'greedy', 'top_k', 'top_p', 'min_p', 'typical', 'temperature'",
+            ));
+        }
+    };
+    if let Some(kwargs) = kwargs {
+        for key in kwargs.keys() {
+            let key = key.extract::<String>()?;
+            if !allowed.contains(&key.as_str()) {
+                return Err(PyTypeError::new_err(format!(
+                    "unexpected keyword argument '{key}' for strategy '{name}'"
+                )));
+            }
+        }
+    }
+    class.call((), kwargs)
+}
@@ -0,0 +1,123 @@
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyType};
+use std::collections::HashMap;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+/// 単語の読みを登録するユーザー辞書。
+///
+/// `C2k`に設定すると、辞書にある単語はモデルの代わりに辞書の読みを返す。
+/// `C2k`は同じオブジェクトを参照するため、設定後の変更も反映される。
+#[pyclass(frozen, module = "voicevox_e2k")]
+pub(crate) struct UserDict {
+    inner: RwLock<e2k::UserDict>,
+}
+
+impl UserDict {
+    fn from_inner(inner: e2k::UserDict) -> Self {
+        Self {
+            inner: RwLock::new(inner),
+        }
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, e2k::UserDict> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, e2k::UserDict> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn to_py_err(e: e2k::LineFileError) -> PyErr {
+    match e {
+        e2k::LineFileError::Io(e) => e.into(),
+        e @ (e2k::LineFileError::InvalidLine { .. } | e2k::LineFileError::Incompatible) => {
+            PyValueError::new_err(e.to_string())
+        }
+    }
+}
+
+#[pymethods]
+impl UserDict {
+    #[new]
+    #[pyo3(signature = (entries = None))]
+    fn new(entries: Option<HashMap<String, String>>) -> Self {
+        let mut inner = e2k::UserDict::new();
+        for (word, reading) in entries.into_iter().flatten() {
+            inner.insert(&word, &reading);
+        }
+        Self::from_inner(inner)
+    }
+
+    /// `単語<TAB>読み`の行からなる文字列から辞書を読み込む。
+    #[staticmethod]
+    fn parse(text: &str) -> PyResult<Self> {
+        e2k::UserDict::parse(text)
+            .map(Self::from_inner)
+            .map_err(to_py_err)
+    }
+
+    /// `単語<TAB>読み`の行からなるファイルから辞書を読み込む。
+    #[staticmethod]
+    fn from_file(path: std::path::PathBuf) -> PyResult<Self> {
+        let file = std::fs::File::open(path)?;
+        e2k::UserDict::from_reader(std::io::BufReader::new(file))
+            .map(Self::from_inner)
+            .map_err(to_py_err)
+    }
+
+    #[pyo3(signature = (word, default = None))]
+    fn get(&self, word: &str, default: Option<String>) -> Option<String> {
+        self.read().get(word).map(str::to_string).or(default)
+    }
+
+    fn __getitem__(&self, word: &str) -> PyResult<String> {
+        self.read()
+            .get(word)
+            .map(str::to_string)
+            .ok_or_else(|| PyKeyError::new_err(word.to_string()))
+    }
+
+    fn __setitem__(&self, word: &str, reading: &str) -> PyResult<()> {
+        if reading.is_empty() {
+            return Err(PyValueError::new_err("reading must not be empty"));
+        }
+        self.write().insert(word, reading);
+        Ok(())
+    }
+
+    fn __delitem__(&self, word: &str) -> PyResult<()> {
+        self.write()
+            .remove(word)
+            .map(|_| ())
+            .ok_or_else(|| PyKeyError::new_err(word.to_string()))
+    }
+
+    fn __contains__(&self, word: &str) -> bool {
+        self.read().get(word).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.read().len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("UserDict(<{} entries>)", self.__len__())
+    }
+
+    /// 正規化された単語と読みの辞書を返す。
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (word, reading) in self.read().iter() {
+            dict.set_item(word, reading)?;
+        }
+        Ok(dict)
+    }
+
+    fn __reduce__<'py>(
+        slf: &Bound<'py, Self>,
+    ) -> PyResult<(Bound<'py, PyType>, (Bound<'py, PyDict>,))> {
+        Ok((slf.get_type(), (slf.get().to_dict(slf.py())?,)))
+    }
+}
@@ -1,3 +1,9 @@
+import math
+import pickle
+from concurrent.futures import ThreadPoolExecutor
+from pathlib import Path
+
+import pytest
 import voicevox_e2k
 
 
@@ -6,3 +12,230 @@ def test_c2k():
 
     word = "constants"
     assert c2k(word) == "コンスタンツ"
+
+
+def test_c2k_seed():
+    c2k = voicevox_e2k.C2k()
+    c2k.set_decode_strategy("top_k", k=5, seed=42)
+
+    word = "constants"
+    first = c2k(word)
+    for _ in range(8):
+        assert c2k(word) == first
+
+
+def test_c2k_strategies():
+    c2k = voicevox_e2k.C2k()
+
+    word = "constants"
+    for strategy, kwargs in [
+        ("greedy", {}),
+        ("top_k", {"k": 3, "t": 0.5}),
+        ("top_p", {"p": 0.9, "t": 0.5}),
+        ("min_p", {"p": 0.1, "t": 0.5}),
+        ("typical", {"p": 0.9, "t": 0.5}),
+        ("temperature", {"t": 0.5}),
+    ]:
+        c2k.set_decode_strategy(strategy, **kwargs)
+        assert isinstance(c2k(word), str)
+
+
+def test_c2k_threads():
+    c2k = voicevox_e2k.C2k()
+
+    words = ["constants", "hello", "world", "voicevox"] * 4
+    with ThreadPoolExecutor(max_workers=4) as executor:
+        results = list(executor.map(c2k, words))
+    assert results == [c2k(word) for word in words]
+
+
+def test_c2k_convert_batch():
+    c2k = voicevox_e2k.C2k()
+
+    words = ["constants", "hello", "world"]
+    assert c2k.convert_batch(words) == [c2k(word) for word in words]
+    assert c2k.convert_batch([]) == []
+
+
+def test_c2k_n_best():
+    c2k = voicevox_e2k.C2k()
+
+    candidates = c2k.n_best("constants", 3)
+    assert 1 <= len(candidates) <= 3
+    assert all(isinstance(kana, str) for kana, _ in candidates)
+    scores = [score for _, score in candidates]
+    assert scores == sorted(scores, reverse=True)
+    assert all(score <= 0 for score in scores)
+
+
+def test_c2k_convert_with_score():
+    c2k = voicevox_e2k.C2k()
+
+    kana, score = c2k.convert_with_score("constants")
+    assert kana == c2k("constants")
+    assert score <= 0
+    assert math.isfinite(score)
+    # ビーム幅1のビームサーチはGreedyと同じなので、対数確率も一致する。
+    assert c2k.n_best("constants", 1) == [(kana, pytest.approx(score, abs=1e-4))]
+
+
+def test_strategy_objects():
+    c2k = voicevox_e2k.C2k()
+
+    word = "constants"
+    for strategy in [
+        voicevox_e2k.Greedy(),
+        voicevox_e2k.TopK(k=3, t=0.5),
+        voicevox_e2k.TopP(p=0.9, t=0.5),
+        voicevox_e2k.MinP(p=0.1, t=0.5),
+        voicevox_e2k.Typical(p=0.9, t=0.5),
+        voicevox_e2k.Temperature(t=0.5),
+    ]:
+        c2k.set_decode_strategy(strategy)
+        assert c2k.strategy == strategy
+        assert isinstance(c2k(word), str)
+
+
+def test_strategy_per_call():
+    c2k = voicevox_e2k.C2k()
+
+    word = "constants"
+    strategy = voicevox_e2k.TopK(k=5, seed=42)
+    first = c2k(word, strategy=strategy)
+    assert c2k(word, strategy=strategy) == first
+    assert c2k.convert_batch([word], strategy=strategy) == [first]
+    assert c2k.convert_with_score(word, strategy=strategy)[0] == first
+    assert c2k.strategy == voicevox_e2k.Greedy()
+
+    c2k.set_decode_strategy("top_k", k=5, seed=42)
+    assert c2k(word) == first
+
+
+@pytest.mark.parametrize(
+    "make_strategy",
+    [
+        lambda: voicevox_e2k.TopK(k=0),
+        lambda: voicevox_e2k.TopK(t=0.0),
+        lambda: voicevox_e2k.TopP(p=0.0),
+        lambda: voicevox_e2k.TopP(p=1.5),
+        lambda: voicevox_e2k.MinP(p=-0.1),
+        lambda: voicevox_e2k.Typical(p=2.0),
+        lambda: voicevox_e2k.Temperature(t=-1.0),
+        lambda: voicevox_e2k.C2k().set_decode_strategy("top_p", p=1.5),
+        lambda: voicevox_e2k.C2k().set_decode_strategy("unknown"),
+    ],
+)
+def test_strategy_value_error(make_strategy):
+    with pytest.raises(ValueError):
+        make_strategy()
+
+
+def test_strategy_type_error():
+    c2k = voicevox_e2k.C2k()
+
+    with pytest.raises(TypeError, match="'top'"):
+        c2k.set_decode_strategy("top_p", top=0.9)
+    with pytest.raises(TypeError):
+        c2k.set_decode_strategy("greedy", k=3)
+    with pytest.raises(TypeError):
+        c2k.set_decode_strategy(voicevox_e2k.Greedy(), k=3)
+    with pytest.raises(TypeError):
+        c2k("constants", strategy="greedy")
+
+
+def test_repr_and_pickle():
+    strategy = voicevox_e2k.TopP(p=0.5, t=2.0, seed=1)
+    assert repr(strategy) == "TopP(p=0.5, t=2.0, seed=1)"
+    assert pickle.loads(pickle.dumps(strategy)) == strategy
+    assert pickle.loads(pickle.dumps(voicevox_e2k.Greedy())) == voicevox_e2k.Greedy()
+
+    c2k = voicevox_e2k.C2k(max_length=16, strategy=strategy)
+    assert repr(c2k) == "C2k(max_length=16, strategy=TopP(p=0.5, t=2.0, seed=1))"
+    restored = pickle.loads(pickle.dumps(c2k))
+    assert repr(restored) == repr(c2k)
+    assert restored("constants") == c2k("constants")
+
+
+def test_c2k_positional_max_length():
+    assert voicevox_e2k.C2k(16).max_length == 16
+    with pytest.raises(TypeError):
+        voicevox_e2k.C2k(16, voicevox_e2k.Greedy())
+
+
+MODEL_PATH = Path(__file__).parent / "../e2k-rs/models/model-c2k.safetensors"
+
+
+def test_c2k_from_file():
+    if not MODEL_PATH.exists():
+        pytest.skip(f"{MODEL_PATH} is not found")
+
+    c2k = voicevox_e2k.C2k(max_length=16)
+    word = "constants"
+    from_file = voicevox_e2k.C2k.from_file(MODEL_PATH, max_length=16)
+    assert from_file.max_length == 16
+    assert from_file(word) == c2k(word)
+    from_bytes = voicevox_e2k.C2k.from_bytes(
+        MODEL_PATH.read_bytes(), max_length=16, strategy=voicevox_e2k.TopK(seed=1)
+    )
+    assert from_bytes.strategy == voicevox_e2k.TopK(seed=1)
+    assert from_bytes(word, strategy=voicevox_e2k.Greedy()) == c2k(word)
+
+    with pytest.raises(TypeError, match="custom model"):
+        pickle.dumps(from_file)
+
+
+def test_c2k_load_errors(tmp_path):
+    with pytest.raises(FileNotFoundError):
+        voicevox_e2k.C2k.from_file(tmp_path / "missing.safetensors")
+
+    broken = tmp_path / "broken.safetensors"
+    broken.write_bytes(b"not a model")
+    with pytest.raises(voicevox_e2k.ModelLoadError):
+        voicevox_e2k.C2k.from_file(broken)
+    with pytest.raises(ValueError, match="failed to read the model"):
+        voicevox_e2k.C2k.from_bytes(b"not a model")
+
+
+def test_user_dict(tmp_path):
+    user_dict = voicevox_e2k.UserDict({"VOICEVOX": "ボイスボックス"})
+    user_dict["New York"] = "ニューヨーク"
+    assert len(user_dict) == 2
+    assert user_dict["ｖｏｉｃｅｖｏｘ"] == "ボイスボックス"
+    assert "new  york" in user_dict
+    assert user_dict.get("missing") is None
+    assert user_dict.to_dict() == {"voicevox": "ボイスボックス", "new york": "ニューヨーク"}
+    del user_dict["NEW YORK"]
+    assert "new york" not in user_dict
+    with pytest.raises(KeyError):
+        user_dict["missing"]
+    assert pickle.loads(pickle.dumps(user_dict)).to_dict() == user_dict.to_dict()
+
+    path = tmp_path / "user_dict.tsv"
+    path.write_text("# comment\nhello\tハロー\n", encoding="utf-8")
+    assert voicevox_e2k.UserDict.from_file(path).to_dict() == {"hello": "ハロー"}
+    with pytest.raises(ValueError, match="line 2"):
+        voicevox_e2k.UserDict.parse("hello\tハロー\nbroken\n")
+    with pytest.raises(FileNotFoundError):
+        voicevox_e2k.UserDict.from_file(tmp_path / "missing.tsv")
+
+
+def test_c2k_user_dict():
+    user_dict = voicevox_e2k.UserDict({"voicevox": "ボイスボックス"})
+    c2k = voicevox_e2k.C2k(user_dict=user_dict)
+    assert c2k.user_dict is user_dict
+
+    assert c2k("VOICEVOX") == "ボイスボックス"
+    assert c2k.convert_batch(["voicevox", "hello"]) == ["ボイスボックス", c2k("hello")]
+    assert c2k.n_best("voicevox", 3) == [("ボイスボックス", 0.0)]
+    assert c2k.convert_with_score("voicevox") == ("ボイスボックス", 0.0)
+
+    # 設定した後の変更も反映される。
+    user_dict["hello"] = "ハロー"
+    assert c2k("hello") == "ハロー"
+
+    restored = pickle.loads(pickle.dumps(c2k))
+    assert restored("hello") == "ハロー"
+
+    c2k.user_dict = None
+    assert c2k.user_dict is None
+    assert c2k("hello") == voicevox_e2k.C2k()("hello")
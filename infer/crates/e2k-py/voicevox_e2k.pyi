@@ -1,54 +1,369 @@
-from typing import Final, Literal, overload
+from os import PathLike
+from typing import Final, Literal, TypeAlias, overload
 
 KANAS: Final[list[str]]
 """c2kの入力に使える文字の一覧。"""
 ASCII_ENTRIES: Final[list[str]]
 """c2kで出力される文字の一覧。"""
 
+class Greedy:
+    """最も確率の高い文字を選ぶデコード戦略。"""
+
+    def __init__(self) -> None: ...
+
+class TopK:
+    """確率の高い`k`個の文字からサンプリングするデコード戦略。"""
+
+    k: Final[int]
+    t: Final[float]
+    seed: Final[int | None]
+
+    def __init__(self, k: int = 3, t: float = 1.0, seed: int | None = None) -> None:
+        """
+        Parameters
+        ----------
+        k : int, default 3
+            候補の数。1以上。
+        t : float, default 1.0
+            ロジットを割る温度。正の数。
+        seed : int | None, default None
+            乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+
+        Raises
+        ------
+        ValueError
+            パラメータが範囲外の場合。
+        """
+        ...
+
+class TopP:
+    """確率の合計が`p`以上になるまで確率の高い順に文字を選び、その中からサンプリングするデコード戦略。"""
+
+    p: Final[float]
+    t: Final[float]
+    seed: Final[int | None]
+
+    def __init__(self, p: float = 0.9, t: float = 1.0, seed: int | None = None) -> None:
+        """
+        Parameters
+        ----------
+        p : float, default 0.9
+            確率の合計の閾値。0より大きく1以下。
+        t : float, default 1.0
+            ロジットを割る温度。正の数。
+        seed : int | None, default None
+            乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+
+        Raises
+        ------
+        ValueError
+            パラメータが範囲外の場合。
+        """
+        ...
+
+class MinP:
+    """最も確率の高い文字の`p`倍以上の確率を持つ文字からサンプリングするデコード戦略。"""
+
+    p: Final[float]
+    t: Final[float]
+    seed: Final[int | None]
+
+    def __init__(self, p: float = 0.1, t: float = 1.0, seed: int | None = None) -> None:
+        """
+        Parameters
+        ----------
+        p : float, default 0.1
+            最も確率の高い文字に対する比率。0より大きく1以下。
+        t : float, default 1.0
+            ロジットを割る温度。正の数。
+        seed : int | None, default None
+            乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+
+        Raises
+        ------
+        ValueError
+            パラメータが範囲外の場合。
+        """
+        ...
+
+class Typical:
+    """Locally Typical Samplingを行うデコード戦略。"""
+
+    p: Final[float]
+    t: Final[float]
+    seed: Final[int | None]
+
+    def __init__(self, p: float = 0.9, t: float = 1.0, seed: int | None = None) -> None:
+        """
+        Parameters
+        ----------
+        p : float, default 0.9
+            確率の合計の閾値。0より大きく1以下。
+        t : float, default 1.0
+            ロジットを割る温度。正の数。
+        seed : int | None, default None
+            乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+
+        Raises
+        ------
+        ValueError
+            パラメータが範囲外の場合。
+        """
+        ...
+
+class Temperature:
+    """候補を絞り込まず、全ての文字からサンプリングするデコード戦略。"""
+
+    t: Final[float]
+    seed: Final[int | None]
+
+    def __init__(self, t: float = 1.0, seed: int | None = None) -> None:
+        """
+        Parameters
+        ----------
+        t : float, default 1.0
+            ロジットを割る温度。正の数。
+        seed : int | None, default None
+            乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+
+        Raises
+        ------
+        ValueError
+            パラメータが範囲外の場合。
+        """
+        ...
+
+Strategy: TypeAlias = Greedy | TopK | TopP | MinP | Typical | Temperature
+"""デコード戦略。"""
+
+class ModelLoadError(ValueError):
+    """モデルの読み込みに失敗した。"""
+
+class UserDict:
+    """単語の読みを登録するユーザー辞書。
+
+    単語は全角を半角に、大文字を小文字に、連続する空白を1つにして登録・検索する。
+    `C2k`に設定すると、辞書にある単語はモデルの代わりに辞書の読みを返す。
+    `C2k`は同じオブジェクトを参照するため、設定した後の変更も反映される。
+    """
+
+    def __init__(self, entries: dict[str, str] | None = None) -> None:
+        """
+        Parameters
+        ----------
+        entries : dict[str, str] | None, default None
+            単語と読みの辞書。
+        """
+        ...
+
+    @staticmethod
+    def parse(text: str) -> UserDict:
+        """
+        `単語<TAB>読み`の行からなる文字列から辞書を読み込む。空行と`#`で始まる行は無視する。
+
+        Raises
+        ------
+        ValueError
+            単語か読みが無い行があった場合。
+        """
+        ...
+
+    @staticmethod
+    def from_file(path: str | PathLike[str]) -> UserDict:
+        """
+        `単語<TAB>読み`の行からなるUTF-8のファイルから辞書を読み込む。
+
+        Raises
+        ------
+        OSError
+            ファイルを読み込めなかった場合。
+        ValueError
+            単語か読みが無い行があった場合。
+        """
+        ...
+
+    def get(self, word: str, default: str | None = None) -> str | None:
+        """単語の読みを返す。無い場合は`default`を返す。"""
+        ...
+
+    def to_dict(self) -> dict[str, str]:
+        """正規化された単語と読みの辞書を返す。"""
+        ...
+
+    def __getitem__(self, word: str) -> str: ...
+    def __setitem__(self, word: str, reading: str) -> None: ...
+    def __delitem__(self, word: str) -> None: ...
+    def __contains__(self, word: str) -> bool: ...
+    def __len__(self) -> int: ...
+
 class C2k:
-    """英単語 -> カタカナの推論を行う。"""
+    """英単語 -> カタカナの推論を行う。
+
+    `repr`で設定を確認でき、`pickle`で設定ごと保存できる。
+    ただし、`from_file`や`from_bytes`で読み込んだものは`pickle`できない。
+    """
 
-    def __init__(self, *, max_length: int = 32) -> None:
+    def __init__(
+        self,
+        max_length: int = 32,
+        *,
+        strategy: Strategy | None = None,
+        user_dict: UserDict | None = None,
+    ) -> None:
         """
-        新しいインスタンスを生成する。
+        組み込みのモデルで新しいインスタンスを生成する。
 
         Parameters
         ----------
         max_length : int, default 32
             最大の出力長。
+        strategy : Strategy | None, default None
+            デコード戦略。`None`の場合は`Greedy()`。
+        user_dict : UserDict | None, default None
+            ユーザー辞書。
         """
 
         ...
 
+    @staticmethod
+    def from_file(
+        path: str | PathLike[str],
+        *,
+        max_length: int = 32,
+        strategy: Strategy | None = None,
+        user_dict: UserDict | None = None,
+    ) -> C2k:
+        """
+        `train/src/export.py`で書き出したsafetensors形式のファイルからモデルを読み込む。
+
+        引数は`path`以外`__init__`と同じ。
+
+        Raises
+        ------
+        OSError
+            ファイルを読み込めなかった場合。
+        ModelLoadError
+            モデルの形式が不正な場合。
+        """
+        ...
+
+    @staticmethod
+    def from_bytes(
+        data: bytes,
+        *,
+        max_length: int = 32,
+        strategy: Strategy | None = None,
+        user_dict: UserDict | None = None,
+    ) -> C2k:
+        """
+        safetensors形式のバイト列からモデルを読み込む。
+
+        引数は`data`以外`__init__`と同じ。
+
+        Raises
+        ------
+        ModelLoadError
+            モデルの形式が不正な場合。
+        """
+        ...
+
+    @property
+    def max_length(self) -> int:
+        """最大の出力長。"""
+        ...
+
+    @property
+    def strategy(self) -> Strategy:
+        """`strategy`を省略したときに使うデコード戦略。"""
+        ...
+
+    @property
+    def user_dict(self) -> UserDict | None:
+        """
+        ユーザー辞書。`None`を設定すると外す。
+
+        辞書にある単語は、`n_best`と`convert_with_score`では対数確率0の1候補になる。
+        """
+        ...
+    @user_dict.setter
+    def user_dict(self, user_dict: UserDict | None) -> None: ...
+
+    @overload
+    def set_decode_strategy(self, strategy: Strategy) -> None: ...
     @overload
     def set_decode_strategy(self, strategy: Literal["greedy"]) -> None: ...
     @overload
-    def set_decode_strategy(self, strategy: Literal["top_k"], k: int) -> None: ...
+    def set_decode_strategy(
+        self,
+        strategy: Literal["top_k"],
+        k: int,
+        t: float = 1.0,
+        seed: int | None = None,
+    ) -> None: ...
+    @overload
+    def set_decode_strategy(
+        self,
+        strategy: Literal["top_p"],
+        p: float,
+        t: float,
+        seed: int | None = None,
+    ) -> None: ...
+    @overload
+    def set_decode_strategy(
+        self,
+        strategy: Literal["min_p"],
+        p: float,
+        t: float = 1.0,
+        seed: int | None = None,
+    ) -> None: ...
     @overload
     def set_decode_strategy(
-        self, strategy: Literal["top_p"], p: float, t: float
+        self,
+        strategy: Literal["typical"],
+        p: float,
+        t: float = 1.0,
+        seed: int | None = None,
     ) -> None: ...
-    def set_decode_strategy(self, strategy: str, **kwargs) -> None:
+    @overload
+    def set_decode_strategy(
+        self,
+        strategy: Literal["temperature"],
+        t: float,
+        seed: int | None = None,
+    ) -> None: ...
+    def set_decode_strategy(self, strategy: Strategy | str, **kwargs) -> None:
         """
-        デコード戦略を設定する。
+        `strategy`を省略したときに使うデコード戦略を設定する。
 
         Parameters
         ----------
-        strategy : str
-            デコード戦略。
+        strategy : Strategy | str
+            デコード戦略。文字列の場合はキーワード引数でパラメータを指定する。
         **kwargs
             戦略に応じた引数。詳細はメソッドのオーバーロードを参照。
+            `seed`を指定すると、同じ入力に対して常に同じ結果を返す。
+
+        Raises
+        ------
+        ValueError
+            戦略の名前が不正な場合や、パラメータが範囲外の場合。
+        TypeError
+            戦略が受け付けないキーワード引数を指定した場合。
         """
         ...
 
-    def __call__(self, word: str) -> str:
+    def __call__(self, word: str, *, strategy: Strategy | None = None) -> str:
         """
         推論を行う。
 
+        推論中はGILを解放する。
+
         Parameters
         ----------
         word : str
             英単語。
+        strategy : Strategy | None, default None
+            この呼び出しでのみ使うデコード戦略。
 
         Returns
         -------
@@ -56,3 +371,68 @@ class C2k:
             カタカナ。
         """
         ...
+
+    def convert_batch(
+        self, words: list[str], *, strategy: Strategy | None = None
+    ) -> list[str]:
+        """
+        複数の単語の推論を行う。
+
+        推論中はGILを解放する。
+
+        Parameters
+        ----------
+        words : list[str]
+            英単語のリスト。
+        strategy : Strategy | None, default None
+            この呼び出しでのみ使うデコード戦略。
+
+        Returns
+        -------
+        list[str]
+            カタカナのリスト。`words`と同じ順に並ぶ。
+        """
+        ...
+
+    def n_best(self, word: str, n: int) -> list[tuple[str, float]]:
+        """
+        ビームサーチで、対数確率の高い順に最大`n`個の候補を返す。
+
+        デコード戦略は使われない。推論中はGILを解放する。
+
+        Parameters
+        ----------
+        word : str
+            英単語。
+        n : int
+            候補の最大数。ビーム幅にもなる。
+
+        Returns
+        -------
+        list[tuple[str, float]]
+            カタカナと、EOSを含めた出力全体の対数確率の組。
+        """
+        ...
+
+    def convert_with_score(
+        self, word: str, *, strategy: Strategy | None = None
+    ) -> tuple[str, float]:
+        """
+        推論を行い、出力の対数確率とともに返す。
+
+        推論中はGILを解放する。
+
+        Parameters
+        ----------
+        word : str
+            英単語。
+        strategy : Strategy | None, default None
+            この呼び出しでのみ使うデコード戦略。
+
+        Returns
+        -------
+        tuple[str, float]
+            カタカナと、EOSを含めた出力全体の対数確率。`n_best`の対数確率と同じ定義で、
+            最大長に達して終わった場合はEOSの項を含まない。
+        """
+        ...
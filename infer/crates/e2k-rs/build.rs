@@ -1,11 +1,24 @@
+use sha2::Digest;
 use std::path::{Path, PathBuf};
 
 static MODEL_TAG: &str = "v1";
 
+/// タグごとのモデルのSHA-256。ダウンロードしたモデルはこの値で検証される。
+///
+/// `MODEL_TAG`を変更したら、Hugging Faceのモデルのハッシュをここに追加すること。
+/// 無い場合はダウンロードせずにビルドを失敗させる。
+///
+/// v1のハッシュはまだ記載していないため、現状はローカルのモデルか`E2K_MODEL_PATH`が必要。
+static MODEL_SHA256: &[(&str, &str)] = &[];
+
 fn main() -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=models/model-c2k.safetensors");
     println!("cargo:rerun-if-changed=models/model-c2k.safetensors.br");
+    println!("cargo:rerun-if-env-changed=E2K_MODEL_PATH");
+    println!("cargo:rerun-if-env-changed=E2K_MODEL_SHA256");
+    println!("cargo:rerun-if-env-changed=E2K_OFFLINE");
+    println!("cargo:rerun-if-env-changed=CARGO_NET_OFFLINE");
 
     prepare_model()?;
 
@@ -16,12 +29,25 @@ fn prepare_model() -> anyhow::Result<()> {
     let local_model_path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
         .join("./models/model-c2k.safetensors");
 
-    let model_path = if local_model_path.try_exists()? {
+    let model_path = if let Some(path) = std::env::var_os("E2K_MODEL_PATH") {
+        prepare_external_model(Path::new(&path))?
+    } else if local_model_path.try_exists()? {
         local_model_path
     } else {
         prepare_huggingface_model()?
     };
 
+    if let Ok(expected) = std::env::var("E2K_MODEL_SHA256") {
+        verify_checksum(&model_path, &expected)?;
+    }
+
+    // ハッシュが固定されたモデルと一致する場合のみ、そのタグをモデルのバージョンにする。
+    // `E2K_MODEL_PATH`などで指定したそれ以外のモデルでは、メタデータの`version`だけが使われる。
+    let sha256 = sha256(&model_path)?;
+    if let Some((tag, _)) = MODEL_SHA256.iter().find(|(_, pinned)| *pinned == sha256) {
+        println!("cargo:rustc-env=E2K_MODEL_TAG={tag}");
+    }
+
     prepare_compressed_model(&model_path)?;
 
     println!(
@@ -32,6 +58,26 @@ fn prepare_model() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// `E2K_MODEL_PATH`で指定されたモデルを`OUT_DIR`にコピーする。
+///
+/// 圧縮したモデルを指定されたファイルの隣に書き込まないようにするため。
+fn prepare_external_model(path: &Path) -> anyhow::Result<PathBuf> {
+    println!("cargo:rerun-if-changed={}", path.display());
+    if !path.try_exists()? {
+        anyhow::bail!(
+            "E2K_MODEL_PATH is set to {}, but the file does not exist",
+            path.display()
+        );
+    }
+
+    let model_root = PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("external_models");
+    std::fs::create_dir_all(&model_root)?;
+    let model_path = model_root.join("model-c2k.safetensors");
+    std::fs::copy(path, &model_path)?;
+
+    Ok(model_path)
+}
+
 fn prepare_compressed_model(model_path: &Path) -> anyhow::Result<()> {
     let compressed_model_path = model_path.with_extra_extension("br");
     let is_compressed_model_up_to_date = compressed_model_path.try_exists()? && {
@@ -54,14 +100,29 @@ fn prepare_huggingface_model() -> anyhow::Result<PathBuf> {
     let model_version_path = model_root.join("version.txt");
     let model_path = model_root.join("model-c2k.safetensors");
 
+    let Some((_, expected)) = MODEL_SHA256.iter().find(|(tag, _)| *tag == MODEL_TAG) else {
+        anyhow::bail!("no SHA-256 is pinned for the model {MODEL_TAG}; add it to MODEL_SHA256");
+    };
+
+    // 以前のビルドでダウンロードしたモデルも、壊れていないか確かめてから使う。
     let latest_model_exists = model_version_path
         .try_exists()?
         .then(|| std::fs::read_to_string(&model_version_path))
         .transpose()?
         .as_deref()
-        == Some(MODEL_TAG);
+        == Some(MODEL_TAG)
+        && model_path.try_exists()?
+        && verify_checksum(&model_path, expected).is_ok();
 
     if !latest_model_exists {
+        if is_offline() {
+            anyhow::bail!(
+                "the model {MODEL_TAG} needs to be downloaded from Hugging Face, \
+                 but the build is offline (E2K_OFFLINE or CARGO_NET_OFFLINE is set). \
+                 Set E2K_MODEL_PATH to a local model-c2k.safetensors, \
+                 or place it at models/model-c2k.safetensors"
+            );
+        }
         download_to(
             &format!(
                 "https://huggingface.co/VOICEVOX/e2k/resolve/{MODEL_TAG}/model/c2k.safetensors"
@@ -69,12 +130,43 @@ fn prepare_huggingface_model() -> anyhow::Result<PathBuf> {
             &model_path,
         )?;
 
+        if let Err(e) = verify_checksum(&model_path, expected) {
+            std::fs::remove_file(&model_path)?;
+            anyhow::bail!("the downloaded model {MODEL_TAG} is corrupted: {e}");
+        }
+
         std::fs::write(&model_version_path, MODEL_TAG)?;
     }
 
     Ok(model_path)
 }
 
+/// `E2K_OFFLINE`か`CARGO_NET_OFFLINE`が指定されているかどうか。
+fn is_offline() -> bool {
+    ["E2K_OFFLINE", "CARGO_NET_OFFLINE"]
+        .iter()
+        .any(|key| std::env::var(key).is_ok_and(|v| v == "true" || v == "1"))
+}
+
+fn sha256(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = sha2::Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn verify_checksum(path: &Path, expected: &str) -> anyhow::Result<()> {
+    let actual = sha256(path)?;
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        anyhow::bail!(
+            "SHA-256 mismatch for {}: expected {}, got {actual}",
+            path.display(),
+            expected.trim()
+        );
+    }
+
+    Ok(())
+}
+
 fn download_to(url: &str, path: &Path) -> anyhow::Result<()> {
     let response = ureq::get(url).call()?;
     let temp_path = path.with_extra_extension("tmp");
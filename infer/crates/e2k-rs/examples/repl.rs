@@ -18,9 +18,21 @@ struct Args {
     #[clap(short = 'p', long, default_value = "0.9")]
     top_p: f32,
 
-    /// Top-Pの温度。
+    /// Min-PのP。
+    #[clap(long, default_value = "0.1")]
+    min_p: f32,
+
+    /// TypicalのP。
+    #[clap(long, default_value = "0.9")]
+    typical_p: f32,
+
+    /// Greedy以外のアルゴリズムの温度。
     #[clap(short = 't', long, default_value = "1.0")]
     temperature: f32,
+
+    /// 乱数のシード。
+    #[clap(long)]
+    seed: Option<u64>,
 }
 
 #[derive(ValueEnum, Debug, Clone)]
@@ -28,6 +40,9 @@ enum StrategyArg {
     Greedy,
     TopK,
     TopP,
+    MinP,
+    Typical,
+    Temperature,
 }
 
 fn main() {
@@ -40,19 +55,56 @@ fn main() {
             println!("アルゴリズム：Greedy");
         }
         StrategyArg::TopK => {
-            c2k.set_decode_strategy(e2k::Strategy::TopK(e2k::StrategyTopK { k: args.top_k }));
-            println!("アルゴリズム：Top-K, K={}", args.top_k);
+            c2k.set_decode_strategy(e2k::Strategy::TopK(e2k::StrategyTopK {
+                k: args.top_k,
+                temperature: args.temperature,
+                seed: args.seed,
+            }));
+            println!(
+                "アルゴリズム：Top-K, K={}, T={}",
+                args.top_k, args.temperature
+            );
         }
         StrategyArg::TopP => {
             c2k.set_decode_strategy(e2k::Strategy::TopP(e2k::StrategyTopP {
                 top_p: args.top_p,
                 temperature: args.temperature,
+                seed: args.seed,
             }));
             println!(
                 "アルゴリズム：Top-P, P={}, T={}",
                 args.top_p, args.temperature
             );
         }
+        StrategyArg::MinP => {
+            c2k.set_decode_strategy(e2k::Strategy::MinP(e2k::StrategyMinP {
+                min_p: args.min_p,
+                temperature: args.temperature,
+                seed: args.seed,
+            }));
+            println!(
+                "アルゴリズム：Min-P, P={}, T={}",
+                args.min_p, args.temperature
+            );
+        }
+        StrategyArg::Typical => {
+            c2k.set_decode_strategy(e2k::Strategy::Typical(e2k::StrategyTypical {
+                typical_p: args.typical_p,
+                temperature: args.temperature,
+                seed: args.seed,
+            }));
+            println!(
+                "アルゴリズム：Typical, P={}, T={}",
+                args.typical_p, args.temperature
+            );
+        }
+        StrategyArg::Temperature => {
+            c2k.set_decode_strategy(e2k::Strategy::Temperature(e2k::StrategyTemperature {
+                temperature: args.temperature,
+                seed: args.seed,
+            }));
+            println!("アルゴリズム：Temperature, T={}", args.temperature);
+        }
     }
     println!("Ctrl-C で終了します。");
     loop {
@@ -0,0 +1,286 @@
+use crate::{C2k, InferOptions, StrategyError};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+/// [AsyncC2k]の設定。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsyncC2kConfig {
+    /// 同時に実行するブロッキングタスクの最大数。既定値は使用可能な並列度です。
+    pub max_concurrency: usize,
+    /// 1つのブロッキングタスクに割り当てる単語の最大数。既定値は1で、単語ごとにタスクを起動します。
+    ///
+    /// 同じタスクに割り当てた単語も1つずつ順に推論します。モデルの入力をまとめるバッチ推論ではないため、
+    /// 大きくしてもタスクを起動する回数が減るだけです。
+    /// 待っている単語は空いている枠に均等に割り当て、枠が空いているのに1つのタスクに単語が偏ることはありません。
+    pub max_words_per_task: usize,
+    /// 単語を同じタスクに割り当てるために、最初の単語が届いてから待つ時間。
+    /// [AsyncC2kConfig::max_words_per_task]が1の場合は待ちません。
+    pub batch_window: Duration,
+    /// 推論を待っている単語の最大数。これを超えると空きが出るまで待ちます。
+    pub queue_size: usize,
+    /// 1回の推論のタイムアウト。`None`の場合はタイムアウトしません。
+    pub timeout: Option<Duration>,
+}
+
+impl Default for AsyncC2kConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            max_words_per_task: 1,
+            batch_window: Duration::from_millis(1),
+            queue_size: 1024,
+            timeout: None,
+        }
+    }
+}
+
+/// [AsyncC2k]での推論に失敗したときのエラー。
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsyncInferError {
+    /// アルゴリズムのパラメータが[Strategy::validate](crate::Strategy::validate)の範囲外だった。
+    InvalidStrategy(StrategyError),
+    /// [AsyncC2kConfig::timeout]までに推論が終わらなかった。
+    Timeout,
+    /// 推論を行うタスクが終了していた。推論中にパニックした場合などに起こります。
+    Closed,
+}
+
+impl std::fmt::Display for AsyncInferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsyncInferError::InvalidStrategy(e) => write!(f, "invalid strategy: {e}"),
+            AsyncInferError::Timeout => write!(f, "inference timed out"),
+            AsyncInferError::Closed => write!(f, "the inference task has stopped"),
+        }
+    }
+}
+
+impl std::error::Error for AsyncInferError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AsyncInferError::InvalidStrategy(e) => Some(e),
+            AsyncInferError::Timeout | AsyncInferError::Closed => None,
+        }
+    }
+}
+
+struct Job {
+    word: String,
+    options: InferOptions,
+    reply: oneshot::Sender<String>,
+}
+
+struct Shared {
+    c2k: C2k,
+    semaphore: Arc<Semaphore>,
+    config: AsyncC2kConfig,
+}
+
+impl Shared {
+    fn infer(&self, input: &str, options: &InferOptions) -> String {
+        self.c2k
+            .infer_with(input, options)
+            .expect("Unreachable: the options should be validated before queuing")
+    }
+}
+
+/// tokioのランタイムから[C2k]を使うためのハンドル。
+///
+/// 推論はtokioのブロッキング用スレッドで行い、同時に実行するタスクの数は
+/// [AsyncC2kConfig::max_concurrency]までに制限します。
+/// [AsyncC2kConfig::max_words_per_task]を2以上にすると、[AsyncC2k::infer]などで同時に届いた単語を
+/// 同じブロッキングタスクに割り当て、そのタスクで1つずつ順に推論します。
+/// タスクを起動する回数は減りますが、モデルの入力をまとめるバッチ推論は行いません。
+///
+/// 推論を待っているFutureをdropすると、その単語の推論は行いません。
+/// 既に推論中の単語は最後まで推論しますが、[AsyncC2k::infer_batch]では残りの単語を推論しません。
+///
+/// クローンしたハンドルは同じモデルとブロッキングタスクを共有します。
+#[derive(Clone)]
+pub struct AsyncC2k {
+    shared: Arc<Shared>,
+    queue: mpsc::Sender<Job>,
+}
+
+impl std::fmt::Debug for AsyncC2k {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncC2k")
+            .field("config", &self.shared.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncC2k {
+    /// 新しいハンドルを生成する。
+    ///
+    /// 単語をまとめるタスクを起動するため、タイマーを有効にしたtokioのランタイム内で呼び出す必要があります。
+    pub fn new(c2k: C2k, config: AsyncC2kConfig) -> Self {
+        let (queue, receiver) = mpsc::channel(config.queue_size.max(1));
+        let shared = Arc::new(Shared {
+            c2k,
+            semaphore: Arc::new(Semaphore::new(config.max_concurrency.max(1))),
+            config,
+        });
+        tokio::spawn(run_batcher(shared.clone(), receiver));
+        Self { shared, queue }
+    }
+
+    /// 推論に使う[C2k]を返す。
+    pub fn c2k(&self) -> &C2k {
+        &self.shared.c2k
+    }
+
+    /// 設定を返す。
+    pub fn config(&self) -> &AsyncC2kConfig {
+        &self.shared.config
+    }
+
+    /// [C2k::default_options]で推論を行う。
+    pub async fn infer(&self, input: &str) -> Result<String, AsyncInferError> {
+        self.infer_with(input, self.shared.c2k.default_options())
+            .await
+    }
+
+    /// オプションを指定して推論を行う。
+    ///
+    /// [AsyncC2kConfig::max_words_per_task]が2以上の場合、同時に届いた他の単語と同じブロッキングタスクで順に推論します。
+    pub async fn infer_with(
+        &self,
+        input: &str,
+        options: &InferOptions,
+    ) -> Result<String, AsyncInferError> {
+        options
+            .strategy
+            .validate()
+            .map_err(AsyncInferError::InvalidStrategy)?;
+        let (reply, receiver) = oneshot::channel();
+        let job = Job {
+            word: input.to_string(),
+            options: options.clone(),
+            reply,
+        };
+        self.with_timeout(async {
+            self.queue
+                .send(job)
+                .await
+                .map_err(|_| AsyncInferError::Closed)?;
+            receiver.await.map_err(|_| AsyncInferError::Closed)
+        })
+        .await
+    }
+
+    /// 複数の単語を1つのブロッキングタスクで順に推論する。
+    ///
+    /// 結果は`inputs`と同じ順に並びます。
+    pub async fn infer_batch(
+        &self,
+        inputs: Vec<String>,
+        options: &InferOptions,
+    ) -> Result<Vec<String>, AsyncInferError> {
+        options
+            .strategy
+            .validate()
+            .map_err(AsyncInferError::InvalidStrategy)?;
+        let options = options.clone();
+        self.with_timeout(async {
+            let permit = self
+                .shared
+                .semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|_| AsyncInferError::Closed)?;
+            let canceled = CancelOnDrop(Arc::new(AtomicBool::new(false)));
+            let flag = canceled.0.clone();
+            let shared = self.shared.clone();
+            tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                let mut outputs = Vec::with_capacity(inputs.len());
+                for input in &inputs {
+                    if flag.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    outputs.push(shared.infer(input, &options));
+                }
+                outputs
+            })
+            .await
+            .map_err(|_| AsyncInferError::Closed)
+        })
+        .await
+    }
+
+    async fn with_timeout<T>(
+        &self,
+        future: impl std::future::Future<Output = Result<T, AsyncInferError>>,
+    ) -> Result<T, AsyncInferError> {
+        match self.shared.config.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| AsyncInferError::Timeout)?,
+            None => future.await,
+        }
+    }
+}
+
+/// dropされたときにフラグを立てて、ブロッキングタスクに残りの推論を止めさせる。
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 届いた単語を空いている枠に割り当て、ブロッキングタスクで順に推論する。
+///
+/// 全てのハンドルがdropされると終了する。
+async fn run_batcher(shared: Arc<Shared>, mut queue: mpsc::Receiver<Job>) {
+    let max_words_per_task = shared.config.max_words_per_task.max(1);
+    let mut pending = VecDeque::new();
+    loop {
+        if pending.is_empty() {
+            match queue.recv().await {
+                Some(job) => pending.push_back(job),
+                None => break,
+            }
+        }
+        let Ok(permit) = shared.semaphore.clone().acquire_owned().await else {
+            break;
+        };
+        // 空きを待っている間に届いた単語も割り当ての対象にする。
+        while let Ok(job) = queue.try_recv() {
+            pending.push_back(job);
+        }
+        if max_words_per_task > 1 {
+            let deadline = tokio::time::Instant::now() + shared.config.batch_window;
+            let capacity =
+                max_words_per_task.saturating_mul(shared.semaphore.available_permits() + 1);
+            while pending.len() < capacity {
+                match tokio::time::timeout_at(deadline, queue.recv()).await {
+                    Ok(Some(job)) => pending.push_back(job),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+        }
+
+        // 他に空いている枠があれば、残りの単語はそちらに回す。
+        let free = shared.semaphore.available_permits() + 1;
+        let count = pending.len().div_ceil(free).min(max_words_per_task);
+        let jobs = pending.drain(..count).collect::<Vec<_>>();
+        let shared = shared.clone();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            for job in jobs {
+                if job.reply.is_closed() {
+                    continue;
+                }
+                let output = shared.infer(&job.word, &job.options);
+                let _ = job.reply.send(output);
+            }
+        });
+    }
+}
@@ -0,0 +1,387 @@
+use crate::{
+    normalize, C2k, Constraints, InferOptions, LineFileError, OutputFormat, Strategy,
+    StrategyError, StrategyMinP, StrategyTemperature, StrategyTopK, StrategyTopP, StrategyTypical,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+
+/// キャッシュファイルの1行目。後ろにファイルの形式、クレートのバージョン、モデルのハッシュ値が続く。
+const HEADER: &str = "# e2k-cache";
+
+/// キャッシュファイルの形式のバージョン。ヘッダーか[options_key]の形式を変えたら上げる。
+const FORMAT_VERSION: u32 = 2;
+
+/// 推論結果をキャッシュする[C2k]。
+///
+/// 入力は[normalize]で正規化してから推論し、正規化した入力と[InferOptions]の組をキーにして、
+/// 最近使われていないものから捨てる（LRU）方式で最大`capacity`件の結果を保持します。
+/// 正規化するため、結果は[C2k::infer]ではなく、[normalize]した入力に対する[C2k::infer]と同じになります。
+/// 例えば`"Constants"`と`"constants"`は同じ結果になります。
+/// キャッシュするのは[InferOptions::is_deterministic]が`true`の場合のみで、
+/// それ以外は毎回推論します。
+///
+/// 複数のスレッドから同時に使えます。推論中はロックを保持しません。
+#[derive(Debug)]
+pub struct CachedC2k {
+    c2k: C2k,
+    cache: Mutex<Lru>,
+}
+
+/// [CachedC2k]のキャッシュの統計。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// キャッシュにあった回数。
+    pub hits: u64,
+    /// キャッシュに無く、推論した回数。キャッシュしないオプションでの推論は含まない。
+    pub misses: u64,
+    /// キャッシュされている結果の数。
+    pub len: usize,
+    /// キャッシュできる結果の最大数。
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// ヒット率。1回も使われていない場合は0。
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+impl CachedC2k {
+    /// 新しいインスタンスを生成する。
+    ///
+    /// # Arguments
+    ///
+    /// - `c2k`: 推論に使う変換器。
+    /// - `capacity`: キャッシュする結果の最大数。0の場合はキャッシュしない。
+    pub fn new(c2k: C2k, capacity: usize) -> Self {
+        Self {
+            c2k,
+            cache: Mutex::new(Lru::new(capacity)),
+        }
+    }
+
+    /// 推論に使う[C2k]を返す。
+    pub fn c2k(&self) -> &C2k {
+        &self.c2k
+    }
+
+    /// [C2k::default_options]で推論を行う。
+    pub fn infer(&self, input: &str) -> String {
+        self.infer_with(input, self.c2k.default_options())
+            .expect("Unreachable: the default options should be valid")
+    }
+
+    /// オプションを指定して推論を行う。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate]の範囲外の場合はエラーを返します。
+    pub fn infer_with(&self, input: &str, options: &InferOptions) -> Result<String, StrategyError> {
+        options.strategy.validate()?;
+        let input = normalize(input);
+        if !options.is_deterministic() {
+            return self.c2k.infer_with(&input, options);
+        }
+        let key = (options_key(options), input);
+        if let Some(output) = self.lock().get(&key) {
+            return Ok(output);
+        }
+        let output = self.c2k.infer_with(&key.1, options)?;
+        self.lock().insert(key, output.clone());
+        Ok(output)
+    }
+
+    /// キャッシュの統計を返す。
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.lock();
+        CacheStats {
+            hits: cache.hits,
+            misses: cache.misses,
+            len: cache.entries.len(),
+            capacity: cache.capacity,
+        }
+    }
+
+    /// キャッシュと統計を消去する。
+    pub fn clear(&self) {
+        let mut cache = self.lock();
+        *cache = Lru::new(cache.capacity);
+    }
+
+    /// キャッシュを書き出す。
+    ///
+    /// 最近使われていないものから順に書き出すため、[CachedC2k::warm]で読み込むと同じ順序になります。
+    /// ファイルにはモデルの重みと保持する形式から計算したハッシュ値を含め、
+    /// バージョンの無いモデルでも、異なるモデルで保存したキャッシュは読み込まないようにします。
+    pub fn save<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let cache = self.lock();
+        writeln!(writer, "{}", self.header())?;
+        for key in cache.order.values() {
+            let (options, input) = key;
+            writeln!(writer, "{options}\t{input}\t{}", cache.entries[key].output)?;
+        }
+        writer.flush()
+    }
+
+    /// [CachedC2k::save]で書き出したキャッシュを読み込み、読み込んだ結果の数を返す。
+    ///
+    /// 既にある結果は上書きします。統計は変わりません。
+    pub fn warm<R: std::io::BufRead>(&self, reader: R) -> Result<usize, LineFileError> {
+        let mut lines = reader.lines();
+        match lines.next().transpose()? {
+            Some(header) if header == self.header() => {}
+            Some(header) if header.starts_with(HEADER) => return Err(LineFileError::Incompatible),
+            Some(_) | None => return Err(LineFileError::InvalidLine { line: 1 }),
+        }
+        let mut entries = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            let mut fields = line.splitn(3, '\t');
+            let (Some(options), Some(input), Some(output)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(LineFileError::InvalidLine { line: i + 2 });
+            };
+            entries.push(((options.to_string(), input.to_string()), output.to_string()));
+        }
+        let count = entries.len();
+        let mut cache = self.lock();
+        for (key, output) in entries {
+            cache.insert(key, output);
+        }
+        Ok(count)
+    }
+
+    fn header(&self) -> String {
+        format!(
+            "{HEADER}\t{FORMAT_VERSION}\t{}\t{:016x}",
+            env!("CARGO_PKG_VERSION"),
+            self.c2k.model_digest()
+        )
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// キャッシュのキーに使うオプションの文字列。
+///
+/// 出力に影響するフィールドだけを決まった形式で並べる。シードは[InferOptions::seed]で
+/// 実際に使われるものに、禁止文字は順序と重複によらないよう並べ替えたコードポイントにする。
+/// 形式を変えた場合は[FORMAT_VERSION]を上げること。
+fn options_key(options: &InferOptions) -> String {
+    let InferOptions {
+        strategy,
+        max_length,
+        seed: _,
+        constraints: Constraints {
+            banned_chars,
+            min_length,
+        },
+        output_format,
+    } = options;
+    let strategy = match strategy {
+        Strategy::Greedy => "greedy".to_string(),
+        Strategy::TopK(StrategyTopK {
+            k,
+            temperature,
+            seed: _,
+        }) => format!("top_k,k={k},t={temperature}"),
+        Strategy::TopP(StrategyTopP {
+            top_p,
+            temperature,
+            seed: _,
+        }) => format!("top_p,p={top_p},t={temperature}"),
+        Strategy::MinP(StrategyMinP {
+            min_p,
+            temperature,
+            seed: _,
+        }) => format!("min_p,p={min_p},t={temperature}"),
+        Strategy::Typical(StrategyTypical {
+            typical_p,
+            temperature,
+            seed: _,
+        }) => format!("typical,p={typical_p},t={temperature}"),
+        Strategy::Temperature(StrategyTemperature {
+            temperature,
+            seed: _,
+        }) => format!("temperature,t={temperature}"),
+    };
+    let seed = options
+        .seed()
+        .map_or_else(|| "none".to_string(), |seed| seed.to_string());
+    let mut banned = banned_chars.clone();
+    banned.sort_unstable();
+    banned.dedup();
+    let banned = banned
+        .iter()
+        .map(|&c| format!("{:x}", u32::from(c)))
+        .collect::<Vec<_>>()
+        .join(",");
+    let output_format = match output_format {
+        OutputFormat::Katakana => "katakana",
+        OutputFormat::Hiragana => "hiragana",
+    };
+    format!(
+        "{strategy};seed={seed};max_length={max_length};min_length={min_length};\
+         banned={banned};format={output_format}"
+    )
+}
+
+type Key = (String, String);
+
+struct LruEntry {
+    output: String,
+    tick: u64,
+}
+
+/// 最近使われていないものから捨てるキャッシュ。
+///
+/// `order`は最後に使われた時刻からキーへの対応で、最も古いものが先頭になる。
+struct Lru {
+    entries: HashMap<Key, LruEntry>,
+    order: BTreeMap<u64, Key>,
+    tick: u64,
+    capacity: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl std::fmt::Debug for Lru {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lru")
+            .field("len", &self.entries.len())
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &Key) -> Option<String> {
+        let tick = self.next_tick();
+        let Some(entry) = self.entries.get_mut(key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let key = self
+            .order
+            .remove(&entry.tick)
+            .expect("Unreachable: order should contain every entry");
+        entry.tick = tick;
+        self.order.insert(tick, key);
+        Some(entry.output.clone())
+    }
+
+    fn insert(&mut self, key: Key, output: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let tick = self.next_tick();
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.order.remove(&entry.tick);
+            entry.output = output;
+            entry.tick = tick;
+            self.order.insert(tick, key);
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.insert(tick, key.clone());
+        self.entries.insert(key, LruEntry { output, tick });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(input: &str) -> Key {
+        (String::new(), input.to_string())
+    }
+
+    #[test]
+    fn test_options_key() {
+        assert_eq!(
+            options_key(&InferOptions::default()),
+            "greedy;seed=none;max_length=32;min_length=0;banned=;format=katakana"
+        );
+        let options = InferOptions {
+            strategy: Strategy::TopP(StrategyTopP {
+                top_p: 0.8,
+                temperature: 1.5,
+                seed: Some(1),
+            }),
+            seed: Some(42),
+            constraints: Constraints {
+                banned_chars: vec!['ン', 'ア', 'ン'],
+                min_length: 2,
+            },
+            output_format: OutputFormat::Hiragana,
+            ..Default::default()
+        };
+        assert_eq!(
+            options_key(&options),
+            "top_p,p=0.8,t=1.5;seed=42;max_length=32;min_length=2;banned=30a2,30f3;format=hiragana"
+        );
+
+        // 実際に使われるシードと禁止文字の集合が同じなら同じキーになる。
+        let same = InferOptions {
+            strategy: Strategy::TopP(StrategyTopP {
+                top_p: 0.8,
+                temperature: 1.5,
+                seed: Some(42),
+            }),
+            seed: None,
+            constraints: Constraints {
+                banned_chars: vec!['ア', 'ン'],
+                min_length: 2,
+            },
+            ..options.clone()
+        };
+        assert_eq!(options_key(&same), options_key(&options));
+    }
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(2);
+        lru.insert(key("a"), "ア".to_string());
+        lru.insert(key("b"), "ビ".to_string());
+        assert_eq!(lru.get(&key("a")), Some("ア".to_string()));
+        // "b"が最も古いため捨てられる。
+        lru.insert(key("c"), "シ".to_string());
+        assert_eq!(lru.get(&key("b")), None);
+        assert_eq!(lru.get(&key("a")), Some("ア".to_string()));
+        assert_eq!(lru.get(&key("c")), Some("シ".to_string()));
+        assert_eq!((lru.hits, lru.misses), (3, 1));
+        assert_eq!(lru.entries.len(), lru.order.len());
+
+        let mut lru = Lru::new(0);
+        lru.insert(key("a"), "ア".to_string());
+        assert_eq!(lru.get(&key("a")), None);
+    }
+}
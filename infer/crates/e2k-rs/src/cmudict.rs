@@ -0,0 +1,312 @@
+use crate::{C2k, InferOptions, P2k, StrategyError};
+use std::collections::HashMap;
+
+/// [CMUdict](https://github.com/cmusphinx/cmudict)形式の発音辞書。
+///
+/// 以下の形式に対応しています。
+///
+/// - `WORD  W ER1 D`のように、単語と発音記号を空白で区切った行。単語の大文字・小文字は区別しません。
+/// - `WORD(2)  ...`のような、2つ目以降の発音。
+/// - `;;;`で始まるコメント行と、発音記号の後の`#`以降の行末コメント。`#HASH-MARK`のように`#`で始まる単語は単語として扱います。
+///
+/// [CmuDict::from_reader]はUTF-8として読めない行をLatin-1として読むため、
+/// Latin-1で書かれたcmudict-0.7bもそのまま読み込めます。
+#[derive(Debug, Clone, Default)]
+pub struct CmuDict {
+    entries: HashMap<String, Vec<Vec<String>>>,
+}
+
+/// [CmuDict]の読み込みに失敗したときのエラー。
+#[derive(Debug)]
+pub enum CmuDictError {
+    /// 読み込みに失敗した。
+    Io(std::io::Error),
+    /// 発音記号が無い行があった。
+    MissingPronunciation {
+        /// 行番号（1始まり）。
+        line: usize,
+    },
+}
+
+impl std::fmt::Display for CmuDictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CmuDictError::Io(e) => write!(f, "failed to read the dictionary: {e}"),
+            CmuDictError::MissingPronunciation { line } => {
+                write!(f, "line {line}: missing pronunciation")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CmuDictError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CmuDictError::Io(e) => Some(e),
+            CmuDictError::MissingPronunciation { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CmuDictError {
+    fn from(e: std::io::Error) -> Self {
+        CmuDictError::Io(e)
+    }
+}
+
+impl CmuDict {
+    /// 文字列から辞書を読み込む。
+    pub fn parse(text: &str) -> Result<Self, CmuDictError> {
+        let mut dict = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            dict.parse_line(i + 1, line)?;
+        }
+        Ok(dict)
+    }
+
+    /// [std::io::BufRead]から辞書を読み込む。
+    ///
+    /// UTF-8として読めない行はLatin-1として読みます。
+    pub fn from_reader<R: std::io::BufRead>(mut reader: R) -> Result<Self, CmuDictError> {
+        let mut dict = Self::default();
+        let mut buf = Vec::new();
+        let mut line_number = 0;
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            line_number += 1;
+            let bytes = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+            let line = match std::str::from_utf8(bytes) {
+                Ok(line) => std::borrow::Cow::Borrowed(line),
+                Err(_) => std::borrow::Cow::Owned(bytes.iter().map(|&b| char::from(b)).collect()),
+            };
+            dict.parse_line(line_number, &line)?;
+        }
+        Ok(dict)
+    }
+
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), CmuDictError> {
+        if line.starts_with(";;;") {
+            return Ok(());
+        }
+        let mut tokens = line.split_whitespace();
+        let Some(word) = tokens.next() else {
+            return Ok(());
+        };
+        // 単語の後の`#`以降はコメント。
+        let pronunciation = tokens
+            .take_while(|p| !p.starts_with('#'))
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+        if pronunciation.is_empty() {
+            return Err(CmuDictError::MissingPronunciation { line: line_number });
+        }
+        let word = strip_variant(word);
+        self.entries
+            .entry(word.to_lowercase())
+            .or_default()
+            .push(pronunciation);
+        Ok(())
+    }
+
+    /// 単語の発音を全て返す。1つ目が最も一般的な発音です。
+    pub fn get(&self, word: &str) -> Option<&[Vec<String>]> {
+        self.entries
+            .get(&word.trim().to_lowercase())
+            .map(|v| v.as_slice())
+    }
+
+    /// 単語の最も一般的な発音を返す。
+    pub fn pronunciation(&self, word: &str) -> Option<&[String]> {
+        self.get(word).and_then(|v| v.first()).map(|v| v.as_slice())
+    }
+
+    /// 登録されている単語の数。
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 単語が1つも登録されていないかどうか。
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// `WORD(2)`の`(2)`を取り除く。
+fn strip_variant(word: &str) -> &str {
+    if let Some((base, variant)) = word.split_once('(') {
+        if variant
+            .strip_suffix(')')
+            .is_some_and(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+        {
+            return base;
+        }
+    }
+    word
+}
+
+/// [CmuDictConverter]でどちらの経路を使って変換したか。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionPath {
+    /// 辞書の発音を[P2k]で変換した。
+    Dictionary,
+    /// 辞書に無かったため、[C2k]で綴りから変換した。
+    Spelling,
+}
+
+/// [CmuDictConverter]の変換結果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// 読み。
+    pub kana: String,
+    /// 変換に使った経路。
+    pub path: ConversionPath,
+}
+
+/// 辞書に発音がある単語は[P2k]、無い単語は[C2k]で変換する変換器。
+pub struct CmuDictConverter {
+    dict: CmuDict,
+    p2k: P2k,
+    c2k: C2k,
+}
+
+impl std::fmt::Debug for CmuDictConverter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CmuDictConverter")
+            .field("dict_len", &self.dict.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CmuDictConverter {
+    /// 新しいインスタンスを生成する。
+    pub fn new(dict: CmuDict, p2k: P2k, c2k: C2k) -> Self {
+        Self { dict, p2k, c2k }
+    }
+
+    /// 推論を行う。
+    ///
+    /// 辞書を使う場合は[P2k::default_options]、使わない場合は[C2k::default_options]が使われます。
+    pub fn infer(&self, word: &str) -> Conversion {
+        match self.dict.pronunciation(word) {
+            Some(pronunciation) => Conversion {
+                kana: self.p2k.infer(pronunciation),
+                path: ConversionPath::Dictionary,
+            },
+            None => Conversion {
+                kana: self.c2k.infer(word),
+                path: ConversionPath::Spelling,
+            },
+        }
+    }
+
+    /// オプションを指定して推論を行う。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate](crate::Strategy::validate)の範囲外の場合はエラーを返します。
+    pub fn infer_with(
+        &self,
+        word: &str,
+        options: &InferOptions,
+    ) -> Result<Conversion, StrategyError> {
+        Ok(match self.dict.pronunciation(word) {
+            Some(pronunciation) => Conversion {
+                kana: self.p2k.infer_with(pronunciation, options)?,
+                path: ConversionPath::Dictionary,
+            },
+            None => Conversion {
+                kana: self.c2k.infer_with(word, options)?,
+                path: ConversionPath::Spelling,
+            },
+        })
+    }
+
+    /// 辞書を返す。
+    pub fn dict(&self) -> &CmuDict {
+        &self.dict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let dict = CmuDict::parse(
+            ";;; # CMUdict
+WORD  W ER1 D
+WORD(2)  W ER0 D
+read R EH1 D # past tense
+
+it's IH1 T S
+",
+        )
+        .unwrap();
+        assert_eq!(dict.len(), 3);
+        assert_eq!(
+            dict.get("word").unwrap(),
+            &[
+                vec!["W".to_string(), "ER1".to_string(), "D".to_string()],
+                vec!["W".to_string(), "ER0".to_string(), "D".to_string()],
+            ]
+        );
+        assert_eq!(dict.pronunciation("READ").unwrap(), &["R", "EH1", "D"]);
+        assert_eq!(dict.pronunciation("it's").unwrap(), &["IH1", "T", "S"]);
+        assert!(dict.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_parse_missing_pronunciation() {
+        let error = CmuDict::parse("WORD  W ER1 D\nBROKEN\n").unwrap_err();
+        assert!(matches!(
+            error,
+            CmuDictError::MissingPronunciation { line: 2 }
+        ));
+    }
+
+    #[test]
+    fn test_parse_hash_headword() {
+        let dict = CmuDict::parse(
+            "#HASH-MARK  HH AE1 SH M AA2 R K
+#SHARP-SIGN  SH AA1 R P S AY1 N # comment
+",
+        )
+        .unwrap();
+        assert_eq!(dict.len(), 2);
+        assert_eq!(
+            dict.pronunciation("#hash-mark").unwrap(),
+            &["HH", "AE1", "SH", "M", "AA2", "R", "K"]
+        );
+        assert_eq!(dict.pronunciation("#sharp-sign").unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_from_reader_latin1() {
+        // cmudict-0.7bはLatin-1で書かれている。
+        let dict = CmuDict::from_reader(
+            &b"WORD  W ER1 D\r\nD\xc9J\xc0  D EY1 ZH AA2\ncaf\xc3\xa9  K AE0 F EY1"[..],
+        )
+        .unwrap();
+        assert_eq!(dict.len(), 3);
+        assert_eq!(dict.pronunciation("word").unwrap(), &["W", "ER1", "D"]);
+        assert_eq!(
+            dict.pronunciation("déjà").unwrap(),
+            &["D", "EY1", "ZH", "AA2"]
+        );
+        assert_eq!(
+            dict.pronunciation("café").unwrap(),
+            &["K", "AE0", "F", "EY1"]
+        );
+    }
+
+    #[test]
+    fn test_strip_variant() {
+        assert_eq!(strip_variant("word(2)"), "word");
+        assert_eq!(strip_variant("word(a)"), "word(a)");
+        assert_eq!(strip_variant("(word)"), "(word)");
+        assert_eq!(strip_variant("word"), "word");
+    }
+}
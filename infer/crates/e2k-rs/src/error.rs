@@ -0,0 +1,145 @@
+/// モデルの読み込みに失敗したときのエラー。
+#[derive(Debug)]
+pub enum LoadError {
+    /// ファイルを読み込めなかった。
+    Io(std::io::Error),
+    /// safetensorsとして読み込めなかった。
+    SafeTensors(safetensors::SafeTensorError),
+    /// 必要なメタデータが無かった。
+    MissingMetadata {
+        /// メタデータのキー。
+        key: String,
+    },
+    /// テンソルが無いか、形が不正だった。
+    InvalidTensor {
+        /// テンソルの名前。
+        name: String,
+        /// 不正な理由。
+        reason: String,
+    },
+    /// メタデータの値が不正だった。
+    InvalidMetadata {
+        /// メタデータのキー。
+        key: String,
+        /// 不正な理由。
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "failed to open the model: {e}"),
+            LoadError::SafeTensors(e) => write!(f, "failed to read the model: {e}"),
+            LoadError::InvalidTensor { name, reason } => {
+                write!(f, "invalid tensor `{name}`: {reason}")
+            }
+            LoadError::MissingMetadata { key } => write!(f, "missing metadata: {key}"),
+            LoadError::InvalidMetadata { key, reason } => {
+                write!(f, "invalid metadata `{key}`: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            LoadError::SafeTensors(e) => Some(e),
+            LoadError::InvalidTensor { .. }
+            | LoadError::MissingMetadata { .. }
+            | LoadError::InvalidMetadata { .. } => None,
+        }
+    }
+}
+
+impl From<safetensors::SafeTensorError> for LoadError {
+    fn from(e: safetensors::SafeTensorError) -> Self {
+        LoadError::SafeTensors(e)
+    }
+}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+/// [Strategy::validate](crate::Strategy::validate)で見つかった不正なパラメータ。
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrategyError {
+    /// `k`が1未満だった。
+    InvalidK(usize),
+    /// 確率のパラメータが0より大きく1以下でなかった。
+    InvalidProbability {
+        /// パラメータの名前（`top_p`、`min_p`、`typical_p`）。
+        name: &'static str,
+        /// 指定された値。
+        value: f32,
+    },
+    /// 温度が正の有限の値でなかった。
+    InvalidTemperature(f32),
+}
+
+impl std::fmt::Display for StrategyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StrategyError::InvalidK(k) => write!(f, "k must be at least 1, got {k}"),
+            StrategyError::InvalidProbability { name, value } => {
+                write!(
+                    f,
+                    "{name} must be greater than 0 and at most 1, got {value}"
+                )
+            }
+            StrategyError::InvalidTemperature(t) => {
+                write!(f, "temperature must be a positive number, got {t}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StrategyError {}
+
+/// ユーザー辞書やキャッシュなど、1行ごとの形式のファイルの読み込みに失敗したときのエラー。
+#[derive(Debug)]
+pub enum LineFileError {
+    /// 読み込みに失敗した。
+    Io(std::io::Error),
+    /// 形式が不正な行があった。
+    InvalidLine {
+        /// 行番号（1始まり）。
+        line: usize,
+    },
+    /// 異なるバージョンのクレートかモデルで保存されたファイルだった。
+    ///
+    /// [CachedC2k::warm](crate::CachedC2k::warm)のみが返します。
+    Incompatible,
+}
+
+impl std::fmt::Display for LineFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineFileError::Io(e) => write!(f, "failed to read the file: {e}"),
+            LineFileError::InvalidLine { line } => write!(f, "line {line}: invalid entry"),
+            LineFileError::Incompatible => {
+                write!(f, "the file was saved with a different version or model")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LineFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LineFileError::Io(e) => Some(e),
+            LineFileError::InvalidLine { .. } | LineFileError::Incompatible => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LineFileError {
+    fn from(e: std::io::Error) -> Self {
+        LineFileError::Io(e)
+    }
+}
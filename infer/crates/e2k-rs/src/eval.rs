@@ -0,0 +1,290 @@
+use crate::{C2k, InferOptions, LineFileError, StrategyError};
+
+/// 評価に使う、単語と正解の読みの組。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalEntry {
+    /// 英単語。
+    pub word: String,
+    /// 正解の読み。
+    pub reference: String,
+}
+
+impl EvalEntry {
+    /// `単語<TAB>読み`の行からなる評価データを読み込む。
+    ///
+    /// 空行と`#`で始まる行は無視します。3列目以降があっても無視します。
+    pub fn read_tsv<R: std::io::BufRead>(reader: R) -> Result<Vec<Self>, LineFileError> {
+        let mut entries = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let (Some(word), Some(reference)) = (fields.next(), fields.next()) else {
+                return Err(LineFileError::InvalidLine { line: i + 1 });
+            };
+            let (word, reference) = (word.trim(), reference.trim());
+            if word.is_empty() || reference.is_empty() {
+                return Err(LineFileError::InvalidLine { line: i + 1 });
+            }
+            entries.push(Self {
+                word: word.to_string(),
+                reference: reference.to_string(),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// 1単語の評価結果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordResult {
+    /// 英単語。
+    pub word: String,
+    /// 正解の読み。
+    pub reference: String,
+    /// 推論した読み。
+    pub hypothesis: String,
+    /// 文字単位の編集距離。
+    pub char_distance: usize,
+    /// モーラ単位の編集距離。
+    pub mora_distance: usize,
+}
+
+impl WordResult {
+    /// 正解の読みと推論した読みから評価結果を作る。
+    pub fn new(word: &str, reference: &str, hypothesis: &str) -> Self {
+        let reference_chars = reference.chars().collect::<Vec<_>>();
+        let hypothesis_chars = hypothesis.chars().collect::<Vec<_>>();
+        Self {
+            word: word.to_string(),
+            reference: reference.to_string(),
+            hypothesis: hypothesis.to_string(),
+            char_distance: edit_distance(&reference_chars, &hypothesis_chars),
+            mora_distance: edit_distance(&split_morae(reference), &split_morae(hypothesis)),
+        }
+    }
+
+    /// 推論した読みが正解と一致したかどうか。
+    pub fn is_exact_match(&self) -> bool {
+        self.reference == self.hypothesis
+    }
+}
+
+/// 評価結果の集計。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalSummary {
+    /// 単語数。
+    pub words: usize,
+    /// 正解と一致した単語数。
+    pub exact_matches: usize,
+    /// 正解と一致した単語の割合。
+    pub accuracy: f64,
+    /// 文字誤り率（CER）。編集距離の合計を正解の文字数の合計で割ったもの。
+    pub cer: f64,
+    /// 1単語あたりの文字単位の編集距離の平均。
+    pub mean_char_distance: f64,
+    /// モーラ単位の正解率。1からモーラ単位の編集距離の合計を正解のモーラ数の合計で割ったものを引いたもの。
+    /// 挿入が多く編集距離が正解のモーラ数を超える場合は0になります。
+    pub mora_accuracy: f64,
+}
+
+impl std::fmt::Display for EvalSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "words: {}", self.words)?;
+        writeln!(
+            f,
+            "accuracy: {:.2}% ({}/{})",
+            self.accuracy * 100.0,
+            self.exact_matches,
+            self.words
+        )?;
+        writeln!(f, "cer: {:.2}%", self.cer * 100.0)?;
+        writeln!(f, "mean edit distance: {:.3}", self.mean_char_distance)?;
+        write!(f, "mora accuracy: {:.2}%", self.mora_accuracy * 100.0)
+    }
+}
+
+/// 評価データ全体の評価結果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvalReport {
+    /// 単語ごとの評価結果。評価データと同じ順に並ぶ。
+    pub results: Vec<WordResult>,
+}
+
+impl EvalReport {
+    /// 評価データの各単語を推論し、評価する。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate](crate::Strategy::validate)の範囲外の場合はエラーを返します。
+    pub fn evaluate(
+        c2k: &C2k,
+        entries: &[EvalEntry],
+        options: &InferOptions,
+    ) -> Result<Self, StrategyError> {
+        let results = entries
+            .iter()
+            .map(|entry| {
+                let hypothesis = c2k.infer_with(&entry.word, options)?;
+                Ok(WordResult::new(&entry.word, &entry.reference, &hypothesis))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { results })
+    }
+
+    /// 評価結果を集計する。
+    pub fn summary(&self) -> EvalSummary {
+        let words = self.results.len();
+        if words == 0 {
+            return EvalSummary::default();
+        }
+        let exact_matches = self.results.iter().filter(|r| r.is_exact_match()).count();
+        let char_distance = self.results.iter().map(|r| r.char_distance).sum::<usize>();
+        let reference_chars = self
+            .results
+            .iter()
+            .map(|r| r.reference.chars().count())
+            .sum::<usize>();
+        let mora_distance = self.results.iter().map(|r| r.mora_distance).sum::<usize>();
+        let reference_morae = self
+            .results
+            .iter()
+            .map(|r| split_morae(&r.reference).len())
+            .sum::<usize>();
+        EvalSummary {
+            words,
+            exact_matches,
+            accuracy: exact_matches as f64 / words as f64,
+            cer: char_distance as f64 / reference_chars.max(1) as f64,
+            mean_char_distance: char_distance as f64 / words as f64,
+            mora_accuracy: (1.0 - mora_distance as f64 / reference_morae.max(1) as f64).max(0.0),
+        }
+    }
+
+    /// 単語ごとの結果を`単語<TAB>正解<TAB>推論<TAB>編集距離`の形式で書き出す。
+    ///
+    /// `errors_only`が`true`の場合、正解と一致しなかった単語のみを書き出します。
+    pub fn write_diff<W: std::io::Write>(
+        &self,
+        mut writer: W,
+        errors_only: bool,
+    ) -> std::io::Result<()> {
+        for result in &self.results {
+            if errors_only && result.is_exact_match() {
+                continue;
+            }
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}",
+                result.word, result.reference, result.hypothesis, result.char_distance
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 2つの列のレーベンシュタイン距離を返す。
+pub fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// 読みをモーラに分ける。
+///
+/// 拗音などの小書きの仮名（`ャ`、`ァ`など）は前の文字と合わせて1モーラとし、
+/// 促音（`ッ`）、撥音（`ン`）、長音（`ー`）はそれぞれ1モーラとします。
+pub fn split_morae(kana: &str) -> Vec<String> {
+    const SMALL_KANAS: &[char] = &[
+        'ァ', 'ィ', 'ゥ', 'ェ', 'ォ', 'ャ', 'ュ', 'ョ', 'ヮ', 'ぁ', 'ぃ', 'ぅ', 'ぇ', 'ぉ', 'ゃ',
+        'ゅ', 'ょ', 'ゎ',
+    ];
+    let mut morae = Vec::<String>::new();
+    for c in kana.chars() {
+        match morae.last_mut() {
+            Some(last) if SMALL_KANAS.contains(&c) => last.push(c),
+            _ => morae.push(c.to_string()),
+        }
+    }
+    morae
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+        assert_eq!(
+            edit_distance(&chars("コンスタンツ"), &chars("コンスタンス")),
+            1
+        );
+        assert_eq!(edit_distance::<char>(&[], &[]), 0);
+    }
+
+    #[test]
+    fn test_split_morae() {
+        assert_eq!(
+            split_morae("チョコレート"),
+            ["チョ", "コ", "レ", "ー", "ト"]
+        );
+        assert_eq!(split_morae("ファッション"), ["ファ", "ッ", "ショ", "ン"]);
+        assert_eq!(split_morae("ァ"), ["ァ"]);
+        assert!(split_morae("").is_empty());
+    }
+
+    #[test]
+    fn test_summary() {
+        let report = EvalReport {
+            results: vec![
+                WordResult::new("chocolate", "チョコレート", "チョコレート"),
+                WordResult::new("fashion", "ファッション", "ファション"),
+            ],
+        };
+        let summary = report.summary();
+        assert_eq!(summary.words, 2);
+        assert_eq!(summary.exact_matches, 1);
+        assert_eq!(summary.accuracy, 0.5);
+        assert_eq!(summary.cer, 1.0 / 12.0);
+        assert_eq!(summary.mean_char_distance, 0.5);
+        assert_eq!(summary.mora_accuracy, 1.0 - 1.0 / 9.0);
+
+        // 挿入が多くても負にならない。
+        let inserted = EvalReport {
+            results: vec![WordResult::new("a", "エー", "エーエーエーエー")],
+        };
+        assert_eq!(inserted.summary().mora_accuracy, 0.0);
+
+        let mut diff = Vec::new();
+        report.write_diff(&mut diff, true).unwrap();
+        assert_eq!(
+            String::from_utf8(diff).unwrap(),
+            "fashion\tファッション\tファション\t1\n"
+        );
+    }
+
+    #[test]
+    fn test_read_tsv() {
+        let entries =
+            EvalEntry::read_tsv("# comment\nhello\tハロー\n\nworld\tワールド\n".as_bytes())
+                .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].word, "world");
+        assert_eq!(entries[1].reference, "ワールド");
+        assert!(matches!(
+            EvalEntry::read_tsv("hello\n".as_bytes()),
+            Err(LineFileError::InvalidLine { line: 1 })
+        ));
+    }
+}
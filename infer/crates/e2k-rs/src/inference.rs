@@ -1,46 +1,368 @@
-use crate::{constants, layers};
+use crate::{
+    constants, layers, sampling, LayerType, LoadError, ModelInfo, PositionEncoding, StrategyError,
+};
 use educe::Educe;
-use itertools::Itertools;
 use std::{collections::HashMap, hash::Hash};
 
 /// デコードに使うアルゴリズム。
 ///
-/// [StrategyTopK] 、 [StrategyTopP] も参照。
-#[derive(Debug)]
+/// [StrategyTopK] 、 [StrategyTopP] 、 [StrategyMinP] 、 [StrategyTypical] 、
+/// [StrategyTemperature] も参照。
+#[derive(Debug, Clone, Default, PartialEq)]
 pub enum Strategy {
+    #[default]
     Greedy,
     TopK(StrategyTopK),
     TopP(StrategyTopP),
+    MinP(StrategyMinP),
+    Typical(StrategyTypical),
+    Temperature(StrategyTemperature),
 }
 
 /// Top-Kアルゴリズムのパラメータ。
-#[derive(Debug, Educe)]
+#[derive(Debug, Clone, PartialEq, Educe)]
 #[educe(Default)]
 pub struct StrategyTopK {
     #[educe(Default(expression = 3))]
     pub k: usize,
+    /// ロジットを割る温度。大きいほど確率の低い候補が選ばれやすくなる。
+    #[educe(Default(expression = 1.0))]
+    pub temperature: f32,
+    /// 乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+    pub seed: Option<u64>,
 }
 
 /// Top-Pアルゴリズムのパラメータ。
-#[derive(Debug, Educe)]
+#[derive(Debug, Clone, PartialEq, Educe)]
 #[educe(Default)]
 pub struct StrategyTopP {
     #[educe(Default(expression = 0.9))]
     pub top_p: f32,
+    /// ロジットを割る温度。大きいほど確率の低い候補が選ばれやすくなる。
+    #[educe(Default(expression = 1.0))]
+    pub temperature: f32,
+    /// 乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+    pub seed: Option<u64>,
+}
+
+/// Min-Pアルゴリズムのパラメータ。
+///
+/// 最も確率の高い候補の`min_p`倍以上の確率を持つ候補からサンプリングする。
+#[derive(Debug, Clone, PartialEq, Educe)]
+#[educe(Default)]
+pub struct StrategyMinP {
+    #[educe(Default(expression = 0.1))]
+    pub min_p: f32,
+    /// ロジットを割る温度。大きいほど確率の低い候補が選ばれやすくなる。
+    #[educe(Default(expression = 1.0))]
+    pub temperature: f32,
+    /// 乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+    pub seed: Option<u64>,
+}
+
+/// Typicalアルゴリズム（Locally Typical Sampling）のパラメータ。
+///
+/// 情報量が分布のエントロピーに近い候補から順に、確率の合計が`typical_p`以上になるまで
+/// 候補に加え、その中からサンプリングする。
+#[derive(Debug, Clone, PartialEq, Educe)]
+#[educe(Default)]
+pub struct StrategyTypical {
+    #[educe(Default(expression = 0.9))]
+    pub typical_p: f32,
+    /// ロジットを割る温度。大きいほど確率の低い候補が選ばれやすくなる。
+    #[educe(Default(expression = 1.0))]
+    pub temperature: f32,
+    /// 乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+    pub seed: Option<u64>,
+}
+
+/// 温度のみを使うアルゴリズムのパラメータ。
+///
+/// 候補を絞り込まず、全ての候補からサンプリングする。
+#[derive(Debug, Clone, PartialEq, Educe)]
+#[educe(Default)]
+pub struct StrategyTemperature {
+    /// ロジットを割る温度。大きいほど確率の低い候補が選ばれやすくなる。
     #[educe(Default(expression = 1.0))]
     pub temperature: f32,
+    /// 乱数のシード。`None`の場合は推論ごとに異なるシードを使う。
+    pub seed: Option<u64>,
+}
+
+impl Strategy {
+    /// パラメータが有効な範囲にあるかを確かめる。
+    ///
+    /// 有効な範囲は以下の通りです。[C2k::infer_with]などはこの範囲外のパラメータをエラーにし、
+    /// 各言語のバインディングもこの範囲を使います。
+    ///
+    /// - `k`：1以上。
+    /// - `top_p`、`min_p`、`typical_p`：0より大きく1以下。
+    /// - `temperature`：正の有限の値。
+    pub fn validate(&self) -> Result<(), StrategyError> {
+        let (probability, temperature) = match self {
+            Strategy::Greedy => return Ok(()),
+            Strategy::TopK(StrategyTopK { k, temperature, .. }) => {
+                if *k < 1 {
+                    return Err(StrategyError::InvalidK(*k));
+                }
+                (None, *temperature)
+            }
+            Strategy::TopP(StrategyTopP {
+                top_p, temperature, ..
+            }) => (Some(("top_p", *top_p)), *temperature),
+            Strategy::MinP(StrategyMinP {
+                min_p, temperature, ..
+            }) => (Some(("min_p", *min_p)), *temperature),
+            Strategy::Typical(StrategyTypical {
+                typical_p,
+                temperature,
+                ..
+            }) => (Some(("typical_p", *typical_p)), *temperature),
+            Strategy::Temperature(StrategyTemperature { temperature, .. }) => (None, *temperature),
+        };
+        if let Some((name, value)) = probability {
+            if !(value > 0.0 && value <= 1.0) {
+                return Err(StrategyError::InvalidProbability { name, value });
+            }
+        }
+        if !(temperature > 0.0 && temperature.is_finite()) {
+            return Err(StrategyError::InvalidTemperature(temperature));
+        }
+        Ok(())
+    }
+
+    fn seed(&self) -> Option<u64> {
+        match self {
+            Strategy::Greedy => None,
+            Strategy::TopK(StrategyTopK { seed, .. }) => *seed,
+            Strategy::TopP(StrategyTopP { seed, .. }) => *seed,
+            Strategy::MinP(StrategyMinP { seed, .. }) => *seed,
+            Strategy::Typical(StrategyTypical { seed, .. }) => *seed,
+            Strategy::Temperature(StrategyTemperature { seed, .. }) => *seed,
+        }
+    }
+
+    fn sample<R: rand::Rng + ?Sized>(&self, x: &ndarray::ArrayView1<f32>, rng: &mut R) -> usize {
+        match self {
+            Strategy::Greedy => sampling::argmax(x),
+            Strategy::TopK(StrategyTopK { k, temperature, .. }) => {
+                sampling::top_k(x, *k, *temperature, rng)
+            }
+            Strategy::TopP(StrategyTopP {
+                top_p, temperature, ..
+            }) => sampling::top_p(x, *top_p, *temperature, rng),
+            Strategy::MinP(StrategyMinP {
+                min_p, temperature, ..
+            }) => sampling::min_p(x, *min_p, *temperature, rng),
+            Strategy::Typical(StrategyTypical {
+                typical_p,
+                temperature,
+                ..
+            }) => sampling::typical(x, *typical_p, *temperature, rng),
+            Strategy::Temperature(StrategyTemperature { temperature, .. }) => {
+                sampling::temperature(x, *temperature, rng)
+            }
+        }
+    }
+}
+
+/// 読み込んだ重みをメモリ上に保持する形式。
+///
+/// [C2k::with_precision]や[P2k::from_bytes_with_precision]などで指定できます。
+/// 全結合層・再帰層・注意機構の重みが対象で、埋め込みは常にf32で保持されます。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precision {
+    /// f32で保持する。最も速いが、メモリはf16のモデルの2倍になる。
+    #[default]
+    F32,
+    /// f16で保持し、推論時にf32に変換する。モデルと同じ値になる。
+    F16,
+    /// 行ごとにスケールを持つint8に量子化して保持する。f16のさらに半分のメモリになる。
+    Int8,
+}
+
+/// 推論時のオプション。
+///
+/// [C2k::infer_with]や[P2k::infer_with]などで推論ごとに指定できます。
+#[derive(Debug, Clone, PartialEq, Educe)]
+#[educe(Default)]
+pub struct InferOptions {
+    /// デコードに使うアルゴリズム。
+    pub strategy: Strategy,
+    /// 読みの最大長。
+    #[educe(Default(expression = 32))]
+    pub max_length: usize,
+    /// 乱数のシード。指定した場合、アルゴリズムに設定されたシードより優先されます。
+    pub seed: Option<u64>,
+    /// 出力に対する制約。
+    pub constraints: Constraints,
+    /// 出力の形式。
+    pub output_format: OutputFormat,
+}
+
+impl InferOptions {
+    pub(crate) fn seed(&self) -> Option<u64> {
+        self.seed.or_else(|| self.strategy.seed())
+    }
+
+    /// 同じ入力に対して常に同じ結果を返すかどうか。
+    ///
+    /// [Strategy::Greedy]か、シードが指定されている場合に`true`になります。
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self.strategy, Strategy::Greedy) || self.seed().is_some()
+    }
+}
+
+/// 出力に対する制約。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    /// 出力に含めない文字。
+    pub banned_chars: Vec<char>,
+    /// 読みの最小長。この長さに達するまでは出力を終了しない。
+    ///
+    /// ただし[Constraints::banned_chars]によって出力できる文字が無くなった場合は、この長さに達する前に終了します。
+    pub min_length: usize,
+}
+
+/// 出力の形式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// カタカナで出力する。
+    #[default]
+    Katakana,
+    /// ひらがなで出力する。
+    Hiragana,
+}
+
+impl OutputFormat {
+    fn convert(self, c: char) -> char {
+        match self {
+            OutputFormat::Katakana => c,
+            OutputFormat::Hiragana => match c {
+                'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+                _ => c,
+            },
+        }
+    }
+}
+
+/// [BaseE2k]の出力として使える型。
+pub trait OutputSymbol: Clone {
+    /// `chars`のいずれかの文字を含むかどうか。[Constraints::banned_chars]の判定に使う。
+    fn contains_any(&self, chars: &[char]) -> bool;
+    /// [OutputFormat]に従って変換する。
+    fn convert(&self, format: OutputFormat) -> Self;
+}
+
+impl OutputSymbol for char {
+    fn contains_any(&self, chars: &[char]) -> bool {
+        chars.contains(self)
+    }
+
+    fn convert(&self, format: OutputFormat) -> Self {
+        format.convert(*self)
+    }
+}
+
+impl OutputSymbol for String {
+    fn contains_any(&self, chars: &[char]) -> bool {
+        self.chars().any(|c| chars.contains(&c))
+    }
+
+    fn convert(&self, format: OutputFormat) -> Self {
+        self.chars().map(|c| format.convert(c)).collect()
+    }
+}
+
+/// 出力した1文字と、その文字を出力したときの入力に対する注意の重み。
+///
+/// [C2k::infer_with_alignment]や[P2k::infer_with_alignment]で取得できます。
+#[derive(Debug, Clone, PartialEq)]
+pub struct KanaAlignment<O = char> {
+    /// 出力した文字。
+    pub kana: O,
+    /// 入力の各文字に対する注意の重み。全ヘッドの平均。
+    pub weights: Vec<f32>,
+    /// 入力の各文字に対するヘッドごとの注意の重み。`head_weights[head][index]`。
+    pub head_weights: Vec<Vec<f32>>,
+    /// 最も注意の重みが大きい入力の文字の位置。
+    pub source_index: usize,
+}
+
+/// [C2k::infer_stream]や[P2k::infer_stream]で出力される1文字。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamedKana<O = char> {
+    /// 出力した文字。
+    pub kana: O,
+    /// モデルがこの文字を出力する確率。
+    ///
+    /// [Constraints]で出力できないトークンを除いた分布での確率です。
+    /// 温度やアルゴリズムによる絞り込みは考慮しません。
+    pub probability: f32,
+}
+
+/// [C2k::infer_n_best]や[P2k::infer_n_best]で出力される候補。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<T = String> {
+    /// 出力。
+    pub kana: T,
+    /// 出力全体（EOSを含む）の対数確率。
+    pub log_prob: f32,
+}
+
+/// 1文字ずつ推論するイテレータ。
+///
+/// [C2k::infer_stream]や[P2k::infer_stream]で取得できます。途中でドロップすると推論を打ち切ります。
+pub struct InferStream<'a, O = char> {
+    decoder: Option<Decoder<'a, rand_chacha::ChaCha8Rng>>,
+    out_table: &'a HashMap<usize, O>,
+    output_format: OutputFormat,
+}
+
+impl<O> std::fmt::Debug for InferStream<'_, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InferStream").finish_non_exhaustive()
+    }
+}
+
+impl<O> InferStream<'_, O> {
+    /// EOSを出力して推論が終わった場合、モデルがEOSを出力した確率を返す。
+    ///
+    /// 推論が終わっていない場合や、最大長に達して終わった場合は`None`を返します。
+    /// 出力した文字の確率の対数とこの確率の対数の合計は、[Candidate::log_prob]と同じ定義の対数確率になります。
+    pub fn eos_probability(&self) -> Option<f32> {
+        self.decoder.as_ref()?.eos_probability
+    }
+}
+
+impl<O: OutputSymbol> Iterator for InferStream<'_, O> {
+    type Item = StreamedKana<O>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.decoder.as_mut()?.next()?;
+        Some(StreamedKana {
+            kana: self.out_table[&token.index].convert(self.output_format),
+            probability: token.probability,
+        })
+    }
+}
+
+/// サンプリングに使う乱数生成器を作る。
+///
+/// シードが同じであれば、プラットフォームによらず同じ乱数列を生成する。
+fn seeded_rng(seed: u64) -> rand_chacha::ChaCha8Rng {
+    use rand::SeedableRng;
+    rand_chacha::ChaCha8Rng::seed_from_u64(seed)
 }
 
 #[cfg(any(
     not(all(target_arch = "wasm32", target_os = "unknown")),
     feature = "getrandom_on_wasm32_unknown"
 ))]
-fn generate_random<T: num_traits::ToBytes>(_data: &[T]) -> usize {
+fn generate_seed() -> u64 {
     use rand::Rng;
     let mut rng = rand::rng();
-    rng.random::<f64>()
-        .to_bits()
-        .wrapping_rem(usize::MAX as u64) as usize
+    rng.random()
 }
 
 #[cfg(all(
@@ -48,7 +370,7 @@ fn generate_random<T: num_traits::ToBytes>(_data: &[T]) -> usize {
     target_os = "unknown",
     not(feature = "getrandom_on_wasm32_unknown")
 ))]
-fn generate_random<T: num_traits::ToBytes>(data: &[T]) -> usize {
+fn generate_seed() -> u64 {
     use std::hash::Hasher;
 
     static HASH_SEED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
@@ -57,223 +379,709 @@ fn generate_random<T: num_traits::ToBytes>(data: &[T]) -> usize {
     let random_pointer = Box::into_raw(Box::new(())) as usize;
     let val = HASH_SEED.fetch_add(random_pointer, std::sync::atomic::Ordering::Relaxed);
     val.hash(&mut hasher);
-    for d in data {
-        d.to_le_bytes().hash(&mut hasher);
-    }
 
-    let data = hasher.finish() as usize;
-    HASH_SEED.fetch_add(data, std::sync::atomic::Ordering::Relaxed);
-    data
+    let seed = hasher.finish();
+    HASH_SEED.fetch_add(seed as usize, std::sync::atomic::Ordering::Relaxed);
+    seed
 }
 
+/// モデル本体。
+///
+/// 層の次元数や再帰層の層数・方向はテンソルの形と名前から、層の種類は[ModelInfo]から決める。
 struct S2s {
     e_emb: layers::Embedding,
     k_emb: layers::Embedding,
-    encoder: layers::Gru,
-    encoder_reverse: layers::Gru,
+    encoder: layers::Rnn,
     encoder_fc: layers::Linear,
-    pre_decoder: layers::Gru,
-    post_decoder: layers::Gru,
-    attn: layers::Mha,
+    decoder: DecoderLayers,
     fc: layers::Linear,
-    max_length: usize,
+    sos_idx: usize,
+    eos_idx: usize,
+}
 
-    strategy: Strategy,
+/// デコーダの層。
+enum DecoderLayers {
+    Rnn(Box<RnnDecoder>),
+    Transformer(TransformerDecoder),
+}
+
+/// 再帰層のデコーダ。
+struct RnnDecoder {
+    pre_decoder: layers::Rnn,
+    attn: layers::Mha,
+    post_decoder: layers::Rnn,
+}
+
+/// Transformerのデコーダ。
+struct TransformerDecoder {
+    /// `decoder.layers.{i}`。
+    layers: Vec<layers::TransformerDecoderLayer>,
+    /// `decoder.norm`。`nn.TransformerDecoder`の`norm`を指定した場合のみある。
+    norm: Option<layers::LayerNorm>,
+    position_encoding: PositionEncoding,
+}
+
+/// モデルの重みの読み込み元。
+struct Weights<'a> {
+    tensors: safetensors::SafeTensors<'a>,
+    /// 全結合層の重みを保持する形式。
+    precision: Precision,
+    /// `tensors`の元のバイト列。ある場合、f16の重みはコピーせずにこのバイト列を参照する。
+    shared: Option<layers::SharedBytes>,
+}
+
+impl<'a> Weights<'a> {
+    fn new(tensors: safetensors::SafeTensors<'a>, precision: Precision) -> Self {
+        Self {
+            tensors,
+            precision,
+            shared: None,
+        }
+    }
+
+    /// 共有できるバイト列から読み込む。
+    fn shared(data: &'a layers::SharedBytes, precision: Precision) -> Result<Self, LoadError> {
+        Ok(Self {
+            tensors: safetensors::SafeTensors::deserialize(data.as_bytes())?,
+            precision,
+            shared: Some(data.clone()),
+        })
+    }
+
+    /// 重みの名前・型・形・値と保持する形式から計算したFNV-1aのハッシュ値。
+    ///
+    /// 同じ重みを同じ形式で読み込んだモデルかどうかを確かめるために使う。
+    fn digest(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut update = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        update(format!("{:?}", self.precision).as_bytes());
+        let mut tensors = self.tensors.tensors();
+        tensors.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        for (name, tensor) in &tensors {
+            update(format!("{name}:{:?}:{:?}", tensor.dtype(), tensor.shape()).as_bytes());
+            update(tensor.data());
+        }
+        hash
+    }
 }
 
 fn get_array_f16<E, D>(
-    weights: &safetensors::SafeTensors,
+    weights: &Weights,
     key: &str,
-) -> ndarray::ArrayBase<ndarray::OwnedRepr<E>, D>
+) -> Result<ndarray::ArrayBase<ndarray::OwnedRepr<E>, D>, LoadError>
 where
     E: ndarray_safetensors::Float16ConversionSupportedElement,
     D: ndarray::Dimension,
 {
-    ndarray_safetensors::parse_fp16_tensor_view_data(
-        &weights
-            .tensor(key)
-            .unwrap_or_else(|e| panic!("model corrupted: {key} not found, {e:?}")),
+    let tensor = get_tensor(weights, key)?;
+    ndarray_safetensors::parse_fp16_tensor_view_data(&tensor)
+        .map_err(|e| invalid_tensor(key, format!("failed to parse, {e:?}")))?
+        .into_dimensionality()
+        .map_err(|e| invalid_tensor(key, format!("dimension mismatch, {e}")))
+}
+
+fn get_tensor<'a>(
+    weights: &Weights<'a>,
+    key: &str,
+) -> Result<safetensors::tensor::TensorView<'a>, LoadError> {
+    weights
+        .tensors
+        .tensor(key)
+        .map_err(|e| invalid_tensor(key, format!("not found, {e}")))
+}
+
+fn invalid_tensor(name: &str, reason: String) -> LoadError {
+    LoadError::InvalidTensor {
+        name: name.to_string(),
+        reason,
+    }
+}
+
+fn has_tensor(weights: &Weights, key: &str) -> bool {
+    weights.tensors.tensor(key).is_ok()
+}
+
+fn load_linear(weights: &Weights, name: &str) -> Result<layers::Linear, LoadError> {
+    load_linear_chunk(
+        weights,
+        &format!("{name}.weight"),
+        &format!("{name}.bias"),
+        (0, 1),
     )
-    .unwrap_or_else(|e| panic!("model corrupted: failed to parse {key}, {e:?}"))
-    .into_dimensionality()
-    .unwrap_or_else(|e| panic!("model corrupted: dimension mismatch in {key}, {e:?}"))
+}
+
+/// 重みとバイアスを行方向に`chunks`等分したうちの、`chunk`番目を全結合層として読み込む。
+///
+/// 重みは[Weights::precision]の形式になる。
+fn load_linear_chunk(
+    weights: &Weights,
+    weight_key: &str,
+    bias_key: &str,
+    (chunk, chunks): (usize, usize),
+) -> Result<layers::Linear, LoadError> {
+    let tensor = get_tensor(weights, weight_key)?;
+    let &[rows, cols] = tensor.shape() else {
+        return Err(invalid_tensor(
+            weight_key,
+            format!("expected 2 dimensions, got {:?}", tensor.shape()),
+        ));
+    };
+    if rows % chunks != 0 {
+        return Err(invalid_tensor(
+            weight_key,
+            format!("{rows} rows cannot be split into {chunks}"),
+        ));
+    }
+    let rows = rows / chunks;
+    let range = chunk * rows..(chunk + 1) * rows;
+    let bias: ndarray::Array1<f32> = get_array_f16(weights, bias_key)?;
+    if bias.len() != rows * chunks {
+        return Err(invalid_tensor(
+            bias_key,
+            format!("expected [{}], got {:?}", rows * chunks, bias.shape()),
+        ));
+    }
+    let bias = bias.slice(ndarray::s![range.clone()]).to_owned();
+
+    if let (Precision::F16, safetensors::Dtype::F16, Some(shared)) =
+        (weights.precision, tensor.dtype(), &weights.shared)
+    {
+        let offset = tensor.data().as_ptr() as usize - shared.as_bytes().as_ptr() as usize
+            + range.start * cols * 2;
+        if let Some(linear) =
+            layers::Linear::shared_f16(shared.clone(), offset, (rows, cols), bias.clone())
+        {
+            return Ok(linear);
+        }
+    }
+    let weight: ndarray::Array2<f32> = get_array_f16(weights, weight_key)?;
+    let mut linear = layers::Linear::new(weight.slice(ndarray::s![range, ..]).to_owned(), bias);
+    linear.quantize(weights.precision);
+    Ok(linear)
+}
+
+fn load_layer_norm(weights: &Weights, name: &str) -> Result<layers::LayerNorm, LoadError> {
+    Ok(layers::LayerNorm::new(
+        get_array_f16(weights, &format!("{name}.weight"))?,
+        get_array_f16(weights, &format!("{name}.bias"))?,
+    ))
+}
+
+fn load_mha(weights: &Weights, name: &str, n_heads: usize) -> Result<layers::Mha, LoadError> {
+    let weight_key = format!("{name}.in_proj_weight");
+    let bias_key = format!("{name}.in_proj_bias");
+    let [q_proj, k_proj, v_proj] =
+        [0, 1, 2].map(|i| load_linear_chunk(weights, &weight_key, &bias_key, (i, 3)));
+    let q_proj = q_proj?;
+    let dim = q_proj.in_features();
+    if q_proj.out_features() != dim {
+        return Err(invalid_tensor(
+            &weight_key,
+            format!("expected [{}, {dim}]", 3 * dim),
+        ));
+    }
+    if dim % n_heads != 0 {
+        return Err(invalid_tensor(
+            &weight_key,
+            format!("embedding size {dim} is not divisible by the number of heads {n_heads}"),
+        ));
+    }
+    Ok(layers::Mha::new(
+        q_proj,
+        k_proj?,
+        v_proj?,
+        load_linear(weights, &format!("{name}.out_proj"))?,
+        n_heads,
+    ))
+}
+
+/// `{name}.weight_ih_l0`、`{name}.weight_ih_l1`、...と続く限り再帰層を読み込む。
+///
+/// `_reverse`の付いた重みがある場合は双方向になる。
+fn load_rnn(
+    weights: &Weights,
+    name: &str,
+    layer_type: LayerType,
+) -> Result<layers::Rnn, LoadError> {
+    let gates = match layer_type {
+        LayerType::Gru => 3,
+        LayerType::Lstm => 4,
+        LayerType::Transformer => unreachable!("Transformer is not a recurrent layer"),
+    };
+    // 入力と隠れ状態に対する全結合層と、入力・隠れ状態の次元数を読み込む。
+    let load = |suffix: &str| -> Result<_, LoadError> {
+        let linear = |kind: &str| {
+            load_linear_chunk(
+                weights,
+                &format!("{name}.weight_{kind}_{suffix}"),
+                &format!("{name}.bias_{kind}_{suffix}"),
+                (0, 1),
+            )
+        };
+        let (ih, hh) = (linear("ih")?, linear("hh")?);
+        let hidden = hh.in_features();
+        if hh.out_features() != gates * hidden || ih.out_features() != gates * hidden {
+            return Err(invalid_tensor(
+                &format!("{name}.weight_hh_{suffix}"),
+                format!(
+                    "expected [{}, {hidden}], got [{}, {hidden}]",
+                    gates * hidden,
+                    hh.out_features()
+                ),
+            ));
+        }
+        let input = ih.in_features();
+        Ok(((ih, hh), input, hidden))
+    };
+
+    let bidirectional = has_tensor(weights, &format!("{name}.weight_ih_l0_reverse"));
+    let directions = if bidirectional { 2 } else { 1 };
+    let mut layers = Vec::new();
+    let mut input_dim = None;
+    while has_tensor(weights, &format!("{name}.weight_ih_l{}", layers.len())) {
+        let l = layers.len();
+        let (forward, input, hidden) = load(&format!("l{l}"))?;
+        let reverse = if has_tensor(weights, &format!("{name}.weight_ih_l{l}_reverse")) {
+            Some(load(&format!("l{l}_reverse"))?.0)
+        } else {
+            None
+        };
+        if reverse.is_some() != bidirectional {
+            return Err(invalid_tensor(
+                &format!("{name}.weight_ih_l{l}_reverse"),
+                "all layers should have the same direction".to_string(),
+            ));
+        }
+        if let Some(expected) = input_dim.filter(|&dim| dim != input) {
+            return Err(invalid_tensor(
+                &format!("{name}.weight_ih_l{l}"),
+                format!("expected input size {expected}, got {input}"),
+            ));
+        }
+        input_dim = Some(hidden * directions);
+        layers.push((forward, reverse));
+    }
+    if layers.is_empty() {
+        return Err(invalid_tensor(
+            &format!("{name}.weight_ih_l0"),
+            "not found".to_string(),
+        ));
+    }
+
+    Ok(match layer_type {
+        LayerType::Gru => {
+            let gru = |(ih, hh), reverse| layers::Gru::new(layers::GruCell::new(ih, hh), reverse);
+            layers::Rnn::Gru(layers::StackedGru::new(
+                layers
+                    .into_iter()
+                    .map(|(f, r)| (gru(f, false), r.map(|r| gru(r, true))))
+                    .collect(),
+            ))
+        }
+        LayerType::Lstm => {
+            let lstm =
+                |(ih, hh), reverse| layers::Lstm::new(layers::LstmCell::new(ih, hh), reverse);
+            layers::Rnn::Lstm(layers::StackedLstm::new(
+                layers
+                    .into_iter()
+                    .map(|(f, r)| (lstm(f, false), r.map(|r| lstm(r, true))))
+                    .collect(),
+            ))
+        }
+        LayerType::Transformer => unreachable!(),
+    })
+}
+
+/// `decoder.layers.0`、`decoder.layers.1`、...と続く限りTransformerのデコーダ層を読み込む。
+/// `decoder.norm`があれば最後の層の後の正規化として読み込む。
+///
+/// 重みの名前はPyTorchの`nn.TransformerDecoder`と同じ。
+fn load_transformer_decoder(
+    weights: &Weights,
+    info: &ModelInfo,
+) -> Result<TransformerDecoder, LoadError> {
+    let n_heads = info.n_heads;
+    let mut decoder_layers = Vec::new();
+    loop {
+        let name = format!("decoder.layers.{}", decoder_layers.len());
+        if !has_tensor(weights, &format!("{name}.self_attn.in_proj_weight")) {
+            break;
+        }
+        decoder_layers.push(layers::TransformerDecoderLayer::new(
+            load_mha(weights, &format!("{name}.self_attn"), n_heads)?,
+            load_mha(weights, &format!("{name}.multihead_attn"), n_heads)?,
+            load_linear(weights, &format!("{name}.linear1"))?,
+            load_linear(weights, &format!("{name}.linear2"))?,
+            [
+                load_layer_norm(weights, &format!("{name}.norm1"))?,
+                load_layer_norm(weights, &format!("{name}.norm2"))?,
+                load_layer_norm(weights, &format!("{name}.norm3"))?,
+            ],
+        ));
+    }
+    if decoder_layers.is_empty() {
+        return Err(invalid_tensor(
+            "decoder.layers.0.self_attn.in_proj_weight",
+            "not found".to_string(),
+        ));
+    }
+    let norm = if has_tensor(weights, "decoder.norm.weight") {
+        Some(load_layer_norm(weights, "decoder.norm")?)
+    } else {
+        None
+    };
+    Ok(TransformerDecoder {
+        layers: decoder_layers,
+        norm,
+        position_encoding: info.position_encoding,
+    })
 }
 
 impl S2s {
-    fn new(weights: safetensors::SafeTensors, max_length: usize) -> Self {
-        let e_emb = layers::Embedding::new(get_array_f16(&weights, "e_emb.weight"));
-        let k_emb = layers::Embedding::new(get_array_f16(&weights, "k_emb.weight"));
-        let encoder = layers::Gru::new(
-            layers::GruCell::new(
-                get_array_f16(&weights, "encoder.weight_ih_l0"),
-                get_array_f16(&weights, "encoder.weight_hh_l0"),
-                get_array_f16(&weights, "encoder.bias_ih_l0"),
-                get_array_f16(&weights, "encoder.bias_hh_l0"),
-            ),
-            false,
-        );
-        let encoder_reverse = layers::Gru::new(
-            layers::GruCell::new(
-                get_array_f16(&weights, "encoder.weight_ih_l0_reverse"),
-                get_array_f16(&weights, "encoder.weight_hh_l0_reverse"),
-                get_array_f16(&weights, "encoder.bias_ih_l0_reverse"),
-                get_array_f16(&weights, "encoder.bias_hh_l0_reverse"),
-            ),
-            true,
-        );
-        let encoder_fc = layers::Linear::new(
-            get_array_f16(&weights, "encoder_fc.0.weight"),
-            get_array_f16(&weights, "encoder_fc.0.bias"),
-        );
-        let pre_decoder = layers::Gru::new(
-            layers::GruCell::new(
-                get_array_f16(&weights, "pre_decoder.weight_ih_l0"),
-                get_array_f16(&weights, "pre_decoder.weight_hh_l0"),
-                get_array_f16(&weights, "pre_decoder.bias_ih_l0"),
-                get_array_f16(&weights, "pre_decoder.bias_hh_l0"),
-            ),
-            false,
-        );
-        let post_decoder = layers::Gru::new(
-            layers::GruCell::new(
-                get_array_f16(&weights, "post_decoder.weight_ih_l0"),
-                get_array_f16(&weights, "post_decoder.weight_hh_l0"),
-                get_array_f16(&weights, "post_decoder.bias_ih_l0"),
-                get_array_f16(&weights, "post_decoder.bias_hh_l0"),
-            ),
-            false,
-        );
-        let attn = layers::Mha::new(
-            get_array_f16(&weights, "attn.in_proj_weight"),
-            get_array_f16(&weights, "attn.in_proj_bias"),
-            get_array_f16(&weights, "attn.out_proj.weight"),
-            get_array_f16(&weights, "attn.out_proj.bias"),
-            4,
-        );
-        let fc = layers::Linear::new(
-            get_array_f16(&weights, "fc.weight"),
-            get_array_f16(&weights, "fc.bias"),
-        );
-        let strategy = Strategy::Greedy;
-        Self {
+    /// 重みを読み込む。
+    fn new(weights: Weights, info: &ModelInfo) -> Result<Self, LoadError> {
+        let e_emb = layers::Embedding::new(get_array_f16(&weights, "e_emb.weight")?);
+        let k_emb = layers::Embedding::new(get_array_f16(&weights, "k_emb.weight")?);
+        let encoder = load_rnn(&weights, "encoder", info.encoder_type)?;
+        let encoder_fc = load_linear(&weights, "encoder_fc.0")?;
+        let decoder = match info.decoder_type {
+            LayerType::Gru | LayerType::Lstm => {
+                let pre_decoder = load_rnn(&weights, "pre_decoder", info.decoder_type)?;
+                let post_decoder = load_rnn(&weights, "post_decoder", info.decoder_type)?;
+                for (name, rnn) in [
+                    ("pre_decoder", &pre_decoder),
+                    ("post_decoder", &post_decoder),
+                ] {
+                    if rnn.is_bidirectional() {
+                        return Err(invalid_tensor(
+                            &format!("{name}.weight_ih_l0_reverse"),
+                            "decoder should be unidirectional".to_string(),
+                        ));
+                    }
+                }
+                DecoderLayers::Rnn(Box::new(RnnDecoder {
+                    pre_decoder,
+                    attn: load_mha(&weights, "attn", info.n_heads)?,
+                    post_decoder,
+                }))
+            }
+            LayerType::Transformer => {
+                DecoderLayers::Transformer(load_transformer_decoder(&weights, info)?)
+            }
+        };
+        let fc = load_linear(&weights, "fc")?;
+        Ok(Self {
             e_emb,
             k_emb,
             encoder,
-            encoder_reverse,
             encoder_fc,
-            pre_decoder,
-            post_decoder,
-            attn,
+            decoder,
             fc,
-            max_length,
-            strategy,
+            sos_idx: info.sos_idx,
+            eos_idx: info.eos_idx,
+        })
+    }
+
+    fn encode(&self, source: &ndarray::Array1<usize>) -> ndarray::Array2<f32> {
+        let e_emb = self.e_emb.forward(source);
+        let (enc_out, _) = self.encoder.forward(&e_emb.view(), None);
+        let enc_out = self.encoder_fc.forward_2d(&enc_out.view());
+        enc_out.mapv(|x| x.tanh())
+    }
+
+    /// デコーダを1ステップ進める。
+    ///
+    /// 次のトークンのロジットと、各ヘッドのソースに対する注意の重み（`[n_heads, source_len]`）を返す。
+    /// Transformerの場合、注意の重みは最後の層のソースに対する注意の重みになる。
+    fn step(
+        &self,
+        enc_out: &ndarray::ArrayView2<f32>,
+        state: &mut DecoderState,
+    ) -> (ndarray::Array1<f32>, ndarray::Array2<f32>) {
+        let dec_emb = self
+            .k_emb
+            .forward(&ndarray::Array1::from_elem(1, state.last));
+        let (x, attn_weights) = match &self.decoder {
+            DecoderLayers::Rnn(decoder) => {
+                let RnnDecoder {
+                    pre_decoder,
+                    attn,
+                    post_decoder,
+                } = decoder.as_ref();
+                let (dec_out, h1) = pre_decoder.forward(&dec_emb.view(), state.h1.as_ref());
+                state.h1 = Some(h1);
+                let (attn_out, attn_weights) = attn.forward(&dec_out.view(), enc_out, enc_out);
+                let x = ndarray::concatenate(
+                    ndarray::Axis(dec_out.ndim() - 1),
+                    &[dec_out.view(), attn_out.view()],
+                )
+                .unwrap();
+                let (x, h2) = post_decoder.forward(&x.view(), state.h2.as_ref());
+                state.h2 = Some(h2);
+                (
+                    x.index_axis_move(ndarray::Axis(0), 0),
+                    attn_weights.index_axis_move(ndarray::Axis(1), 0),
+                )
+            }
+            DecoderLayers::Transformer(decoder) => {
+                let dim = dec_emb.ncols();
+                if state.caches.is_empty() {
+                    state.caches = vec![ndarray::Array2::zeros((0, dim)); decoder.layers.len()];
+                }
+                let mut x = dec_emb.index_axis_move(ndarray::Axis(0), 0);
+                if decoder.position_encoding == PositionEncoding::Sinusoidal {
+                    let position = state.caches[0].nrows();
+                    x += &layers::sinusoidal_position_encoding(position, dim);
+                }
+                let mut attn_weights = None;
+                for (layer, cache) in decoder.layers.iter().zip(&mut state.caches) {
+                    let (y, weights) = layer.forward_step(&x.view(), cache, enc_out);
+                    x = y;
+                    attn_weights = Some(weights);
+                }
+                if let Some(norm) = &decoder.norm {
+                    x = norm.forward_1d(&x.view());
+                }
+                (
+                    x,
+                    attn_weights.expect("Unreachable: There should be at least one layer"),
+                )
+            }
+        };
+        (self.fc.forward_1d(&x.view()), attn_weights)
+    }
+}
+
+/// デコーダの隠れ状態。
+#[derive(Clone)]
+struct DecoderState {
+    last: usize,
+    /// 再帰層の場合の、`pre_decoder`と`post_decoder`の隠れ状態。
+    h1: Option<layers::RnnState>,
+    h2: Option<layers::RnnState>,
+    /// Transformerの場合の、層ごとのそれまでの入力。
+    caches: Vec<ndarray::Array2<f32>>,
+}
+
+impl DecoderState {
+    fn new(sos_idx: usize) -> Self {
+        Self {
+            last: sos_idx,
+            h1: None,
+            h2: None,
+            caches: Vec::new(),
         }
     }
+}
 
-    fn greedy(&self, step_dec: &ndarray::ArrayView1<f32>) -> usize {
-        let max = *step_dec
-            .iter()
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
-        let argmax = step_dec.iter().position(|&x| x == max).unwrap();
-        argmax
-    }
-
-    fn top_k(&self, step_dec: &ndarray::ArrayView1<f32>, k: usize) -> usize {
-        let step_dec = step_dec.to_vec();
-        let random = generate_random(&step_dec);
-        let mut indices = (0..step_dec.len()).collect::<Vec<_>>();
-        indices.sort_unstable_by(|&i, &j| step_dec[j].partial_cmp(&step_dec[i]).unwrap());
-        indices.truncate(k);
-
-        indices[random % indices.len()]
-    }
-
-    fn top_p(&self, step_dec: &ndarray::ArrayView1<f32>, top_p: f32, temperature: f32) -> usize {
-        let random = generate_random(&step_dec.to_vec());
-        let step_dec = step_dec.exp() / temperature;
-        let sum = step_dec.sum();
-        let step_dec = step_dec / sum;
-        let mut sorted = step_dec.iter().copied().enumerate().collect::<Vec<_>>();
-        sorted.sort_unstable_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
-        let mut i = 0;
-        let mut cumsum = 0.0;
-        while cumsum < top_p {
-            cumsum += sorted[i].1;
-            i += 1;
+/// デコーダが出力した1トークン。
+struct DecodedToken {
+    index: usize,
+    /// 温度を1としたときの、このトークンの確率。
+    probability: f32,
+    /// 各ヘッドのソースに対する注意の重み（`[n_heads, source_len]`）。
+    attention: ndarray::Array2<f32>,
+}
+
+/// 1ステップずつデコードするイテレータ。EOSを出力した時点で終了する。
+///
+/// `banned`に含まれるインデックスは出力されず、`min_length`に達するまではEOSが出力されない。
+struct Decoder<'a, R: rand::Rng> {
+    s2s: &'a S2s,
+    enc_out: ndarray::Array2<f32>,
+    state: DecoderState,
+    strategy: Strategy,
+    max_length: usize,
+    min_length: usize,
+    banned: Vec<usize>,
+    step: usize,
+    finished: bool,
+    /// EOSを出力して終了した場合の、温度を1としたときのEOSの確率。
+    eos_probability: Option<f32>,
+    rng: R,
+}
+
+impl<'a, R: rand::Rng> Decoder<'a, R> {
+    fn new(
+        s2s: &'a S2s,
+        source: &ndarray::Array1<usize>,
+        options: &InferOptions,
+        banned: Vec<usize>,
+        rng: R,
+    ) -> Self {
+        Self {
+            s2s,
+            enc_out: s2s.encode(source),
+            state: DecoderState::new(s2s.sos_idx),
+            strategy: options.strategy.clone(),
+            max_length: options.max_length,
+            min_length: options.constraints.min_length,
+            banned,
+            step: 0,
+            finished: false,
+            eos_probability: None,
+            rng,
+        }
+    }
+}
+
+impl<R: rand::Rng> Iterator for Decoder<'_, R> {
+    type Item = DecodedToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.step >= self.max_length {
+            return None;
+        }
+        let (mut x, attention) = self.s2s.step(&self.enc_out.view(), &mut self.state);
+        mask_logits(
+            &mut x,
+            &self.banned,
+            self.s2s.eos_idx,
+            self.step < self.min_length,
+        );
+        let index = self.strategy.sample(&x.view(), &mut self.rng);
+        self.step += 1;
+        self.state.last = index;
+        let probabilities = sampling::softmax(&x.view(), 1.0);
+        if index == self.s2s.eos_idx {
+            self.finished = true;
+            self.eos_probability = Some(probabilities[index]);
+            return None;
         }
-        let candidates = sorted[..i].iter().map(|(i, _)| *i).collect::<Vec<_>>();
+        let probability = probabilities[index];
+        Some(DecodedToken {
+            index,
+            probability,
+            attention,
+        })
+    }
+}
 
-        candidates[random % candidates.len()]
+/// 出力できないトークンのロジットを`-inf`にする。
+///
+/// `banned`に含まれるトークンと、`ban_eos`が`true`の場合はEOSが対象。
+/// すべてのトークンが対象になる場合は、EOSのロジットを元に戻して出力を終了させる。
+fn mask_logits(logits: &mut ndarray::Array1<f32>, banned: &[usize], eos_idx: usize, ban_eos: bool) {
+    let eos_logit = logits[eos_idx];
+    for &idx in banned {
+        logits[idx] = f32::NEG_INFINITY;
     }
+    if ban_eos {
+        logits[eos_idx] = f32::NEG_INFINITY;
+    }
+    if logits.iter().all(|&x| x == f32::NEG_INFINITY) {
+        logits[eos_idx] = eos_logit;
+    }
+}
 
-    fn decode(&self, x: &ndarray::ArrayView1<f32>) -> usize {
-        match &self.strategy {
-            Strategy::Greedy => self.greedy(x),
-            Strategy::TopK(StrategyTopK { k }) => self.top_k(x, *k),
-            Strategy::TopP(StrategyTopP { top_p, temperature }) => {
-                self.top_p(x, *top_p, *temperature)
-            }
+/// 各ヘッドの重みを合計が1になるよう正規化する。合計が0のヘッドは0のままにする。
+fn normalize_heads(mut head_weights: ndarray::Array2<f32>) -> ndarray::Array2<f32> {
+    for mut weights in head_weights.rows_mut() {
+        let sum = weights.sum();
+        if sum > 0.0 {
+            weights /= sum;
         }
     }
+    head_weights
+}
 
-    fn forward(&self, source: &ndarray::Array1<usize>) -> ndarray::Array1<usize> {
-        let e_emb = self.e_emb.forward(source);
-        let (enc_out, _) = self.encoder.forward(&e_emb.view(), None);
-        let (enc_out_rev, _) = self.encoder_reverse.forward(&e_emb.view(), None);
-        let enc_out = ndarray::concatenate(
-            ndarray::Axis(enc_out.ndim() - 1),
-            &[enc_out.view(), enc_out_rev.view()],
-        )
-        .unwrap();
-        let enc_out = self.encoder_fc.forward_2d(&enc_out.view());
-        let enc_out = enc_out.mapv(|x| x.tanh());
-        let mut result = vec![constants::SOS_IDX];
-        let mut h1: Option<ndarray::Array1<f32>> = None;
-        let mut h2: Option<ndarray::Array1<f32>> = None;
-        for _ in 0..self.max_length {
-            let dec_emb = self
-                .k_emb
-                .forward(&ndarray::Array1::from_elem(1, *result.last().unwrap()));
-            let (dec_out, h1_) = match h1 {
-                Some(h1) => self.pre_decoder.forward(&dec_emb.view(), Some(h1.view())),
-                None => self.pre_decoder.forward(&dec_emb.view(), None),
-            };
-            h1 = Some(h1_);
-            let attn_out = self
-                .attn
-                .forward(&dec_out.view(), &enc_out.view(), &enc_out.view());
-            let x = ndarray::concatenate(
-                ndarray::Axis(dec_out.ndim() - 1),
-                &[dec_out.view(), attn_out.view()],
-            )
-            .unwrap();
-            let (x, h2_) = match h2 {
-                Some(h2) => self.post_decoder.forward(&x.view(), Some(h2.view())),
-                None => self.post_decoder.forward(&x.view(), None),
-            };
-            h2 = Some(h2_);
-            let x = self.fc.forward_2d(&x.view());
-            let x = x.index_axis(ndarray::Axis(0), 0);
-            result.push(self.decode(&x));
-            if result.last().unwrap() == &constants::EOS_IDX {
-                break;
+/// ビームサーチを行い、対数確率の高い順に最大`n`個の出力とその対数確率を返す。
+///
+/// ビーム幅は`n`で、EOSで終わった候補が`n`個集まった時点で探索を打ち切る。
+/// `max_length`に達した候補はEOSで終わったものとして扱う。
+fn beam_search(
+    s2s: &S2s,
+    source: &ndarray::Array1<usize>,
+    n: usize,
+    options: &InferOptions,
+    banned: &[usize],
+) -> Vec<(Vec<usize>, f32)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let enc_out = s2s.encode(source);
+    let mut beams = vec![(Vec::new(), DecoderState::new(s2s.sos_idx), 0.0f32)];
+    let mut finished = Vec::new();
+    for step in 0..options.max_length {
+        let mut expanded = Vec::with_capacity(beams.len());
+        let mut candidates = Vec::new();
+        for (i, (_, state, score)) in beams.iter().enumerate() {
+            let mut state = state.clone();
+            let (mut logits, _) = s2s.step(&enc_out.view(), &mut state);
+            mask_logits(
+                &mut logits,
+                banned,
+                s2s.eos_idx,
+                step < options.constraints.min_length,
+            );
+            let log_probs = sampling::log_softmax(&logits.view());
+            candidates.extend(
+                log_probs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| p.is_finite())
+                    .map(|(index, p)| (i, index, score + p)),
+            );
+            expanded.push(state);
+        }
+        candidates.sort_unstable_by(|a, b| b.2.total_cmp(&a.2));
+        let mut next = Vec::with_capacity(n);
+        for (i, index, score) in candidates.into_iter().take(n) {
+            let tokens = &beams[i].0;
+            if index == s2s.eos_idx {
+                finished.push((tokens.clone(), score));
+            } else {
+                let mut tokens = tokens.clone();
+                tokens.push(index);
+                let mut state = expanded[i].clone();
+                state.last = index;
+                next.push((tokens, state, score));
             }
         }
-
-        ndarray::Array1::from(result)
+        beams = next;
+        if beams.is_empty() || finished.len() >= n {
+            break;
+        }
     }
+    if finished.len() < n {
+        finished.extend(beams.into_iter().map(|(tokens, _, score)| (tokens, score)));
+    }
+    finished.sort_by(|a, b| b.1.total_cmp(&a.1));
+    finished.truncate(n);
+    finished
 }
 
-/// [C2k] の基底となる構造体。
-/// 基本的には[C2k]を使ってください。
-pub struct BaseE2k<I: Hash + Eq, O: Clone> {
+/// [C2k] 、 [P2k] の基底となる構造体。
+///
+/// 任意の入力・出力の語彙を持つモデルで推論できます。
+/// `train/`でエクスポートしたモデルはsafetensorsのメタデータに語彙を含むため、
+/// [BaseE2k::from_bytes]で読み込めます。
+///
+/// 英単語やARPAbetをカタカナに変換する場合は[C2k]か[P2k]を使ってください。
+pub struct BaseE2k<I: Hash + Eq, O: OutputSymbol> {
     s2s: S2s,
     in_table: HashMap<I, usize>,
     out_table: HashMap<usize, O>,
+    info: ModelInfo,
+    options: InferOptions,
+    /// [Weights::digest]の値。
+    digest: u64,
+}
+
+impl<I: Hash + Eq, O: OutputSymbol> std::fmt::Debug for BaseE2k<I, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BaseE2k")
+            .field("info", &self.info)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
 }
 
-impl<I: Hash + Eq, O: Clone> BaseE2k<I, O> {
+impl<I: Hash + Eq, O: OutputSymbol> BaseE2k<I, O> {
     /// 新しいインスタンスを生成する。
     ///
     /// # Arguments
@@ -282,43 +1090,456 @@ impl<I: Hash + Eq, O: Clone> BaseE2k<I, O> {
     /// - `in_table`: 入力のテーブル。キーが入力、値がモデルの入力に変換されるインデックス。
     /// - `out_table`: 出力のテーブル。キーがモデルの出力に変換されるインデックス、値が出力。
     /// - `max_length`: 読みの最大長。
+    ///
+    /// ヘッド数とSOS/EOSのインデックスには組み込みの値が使われます。
+    /// 異なるモデルの場合は[BaseE2k::with_model_info]を使ってください。
+    ///
+    /// # Panics
+    ///
+    /// 重みが不正な場合はパニックします。
     pub fn new(
         tensors: safetensors::SafeTensors,
         in_table: HashMap<I, usize>,
         out_table: HashMap<usize, O>,
         max_length: usize,
     ) -> Self {
-        Self {
-            s2s: S2s::new(tensors, max_length),
+        Self::with_model_info(
+            tensors,
             in_table,
             out_table,
-        }
+            ModelInfo::default(),
+            max_length,
+        )
+        .unwrap_or_else(|e| panic!("model corrupted: {e}"))
     }
-    fn infer(&self, input: &[I]) -> Vec<O> {
-        let source = input
-            .iter()
-            .filter_map(|c| self.in_table.get(c).copied())
-            .collect_vec();
-        if source.is_empty() {
-            return Vec::new();
-        }
-        let source = [constants::SOS_IDX]
-            .into_iter()
-            .chain(source)
-            .chain([constants::EOS_IDX]);
-        let source = ndarray::Array1::from_iter(source);
-        let target = self.s2s.forward(&source);
-        target
+
+    /// モデルの情報を指定して新しいインスタンスを生成する。
+    ///
+    /// `info`のヘッド数、SOS/EOSのインデックス、層の種類がモデルに使われます。語彙は使われません。
+    /// 層の次元数や再帰層の層数・方向は重みの形と名前から決まります。
+    ///
+    /// 重みの名前はPyTorchのモジュールと同じで、層の種類ごとに以下が必要です。
+    ///
+    /// - 共通：`e_emb`、`k_emb`、`encoder_fc.0`、`fc`
+    /// - エンコーダ（`nn.GRU`か`nn.LSTM`）：`encoder`
+    /// - 再帰層のデコーダ（`nn.GRU`か`nn.LSTM`、単方向）：`pre_decoder`、`attn`、`post_decoder`
+    /// - Transformerのデコーダ（`nn.TransformerDecoder`）：`decoder.layers.{i}`と、あれば`decoder.norm`
+    pub fn with_model_info(
+        tensors: safetensors::SafeTensors,
+        in_table: HashMap<I, usize>,
+        out_table: HashMap<usize, O>,
+        info: ModelInfo,
+        max_length: usize,
+    ) -> Result<Self, LoadError> {
+        Self::with_precision(
+            tensors,
+            in_table,
+            out_table,
+            info,
+            Precision::default(),
+            max_length,
+        )
+    }
+
+    /// 重みを保持する形式を指定して新しいインスタンスを生成する。
+    ///
+    /// `precision`以外は[BaseE2k::with_model_info]と同じです。
+    pub fn with_precision(
+        tensors: safetensors::SafeTensors,
+        in_table: HashMap<I, usize>,
+        out_table: HashMap<usize, O>,
+        info: ModelInfo,
+        precision: Precision,
+        max_length: usize,
+    ) -> Result<Self, LoadError> {
+        Self::from_weights(
+            Weights::new(tensors, precision),
+            in_table,
+            out_table,
+            info,
+            max_length,
+        )
+    }
+
+    fn from_weights(
+        weights: Weights,
+        in_table: HashMap<I, usize>,
+        out_table: HashMap<usize, O>,
+        info: ModelInfo,
+        max_length: usize,
+    ) -> Result<Self, LoadError> {
+        let digest = weights.digest();
+        let s2s = S2s::new(weights, &info)?;
+        if let Some(&idx) = in_table
+            .values()
+            .find(|&&idx| idx >= s2s.e_emb.num_embeddings())
+        {
+            return Err(invalid_tensor(
+                "e_emb.weight",
+                format!("input index {idx} is out of the embedding"),
+            ));
+        }
+        if let Some(&idx) = out_table.keys().find(|&&idx| idx >= s2s.fc.out_features()) {
+            return Err(invalid_tensor(
+                "fc.weight",
+                format!("output index {idx} is out of the output layer"),
+            ));
+        }
+        Ok(Self {
+            s2s,
+            in_table,
+            out_table,
+            info,
+            options: InferOptions {
+                max_length,
+                ..Default::default()
+            },
+            digest,
+        })
+    }
+
+    /// 推論を行う。
+    ///
+    /// [BaseE2k::new]と[BaseE2k::set_decode_strategy]で設定したオプションを使います。
+    /// 入力テーブルに存在しない入力は無視されます。
+    pub fn infer(&self, input: &[I]) -> Vec<O> {
+        self.infer_unchecked(input, &self.options)
+    }
+
+    /// オプションを指定して推論を行う。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate]の範囲外の場合はエラーを返します。
+    pub fn infer_with(&self, input: &[I], options: &InferOptions) -> Result<Vec<O>, StrategyError> {
+        options.strategy.validate()?;
+        Ok(self.infer_unchecked(input, options))
+    }
+
+    /// 指定した乱数生成器を使って推論を行う。
+    ///
+    /// アルゴリズムに設定されたシードは無視され、`rng`がサンプリングに使われます。
+    pub fn infer_with_rng<R: rand::Rng + ?Sized>(&self, input: &[I], rng: &mut R) -> Vec<O> {
+        self.sample(input, &self.options, rng)
+    }
+
+    /// 推論を行い、出力ごとに入力に対する注意の重みを返す。
+    ///
+    /// 重みの位置は`input`の位置です。
+    /// 重みはSOS/EOSに対する重みを除いて合計が1になるよう正規化されており、
+    /// 入力テーブルに存在しない入力に対する重みは0になります。
+    /// 注意がすべてSOS/EOSに向いているヘッドの重みは、すべて0になります。
+    /// アルゴリズムのパラメータが[Strategy::validate]の範囲外の場合はエラーを返します。
+    pub fn infer_with_alignment(
+        &self,
+        input: &[I],
+        options: &InferOptions,
+    ) -> Result<Vec<KanaAlignment<O>>, StrategyError> {
+        options.strategy.validate()?;
+        let seed = options.seed().unwrap_or_else(generate_seed);
+        let Some((positions, decoder)) = self.decoder(input, options, seeded_rng(seed)) else {
+            return Ok(Vec::new());
+        };
+        Ok(decoder
+            .map(|token| {
+                let attention = token.attention;
+                let mut head_weights = ndarray::Array2::zeros((attention.nrows(), input.len()));
+                for (i, &position) in positions.iter().enumerate() {
+                    head_weights
+                        .column_mut(position)
+                        .assign(&attention.column(i + 1));
+                }
+                let head_weights = normalize_heads(head_weights);
+                let weights = head_weights
+                    .mean_axis(ndarray::Axis(0))
+                    .expect("Unreachable: There should be at least one head");
+                let source_index = sampling::argmax(&weights.view());
+                KanaAlignment {
+                    kana: self.out_table[&token.index].convert(options.output_format),
+                    weights: weights.to_vec(),
+                    head_weights: head_weights.outer_iter().map(|w| w.to_vec()).collect(),
+                    source_index,
+                }
+            })
+            .collect())
+    }
+
+    /// 1つずつ推論を行うイテレータを返す。
+    ///
+    /// [BaseE2k::infer]と同じオプションを使います。
+    /// エンコードはこのメソッドの呼び出し時に行われ、デコードはイテレータを進めるごとに行われます。
+    pub fn infer_stream(&self, input: &[I]) -> InferStream<'_, O> {
+        self.stream_unchecked(input, &self.options)
+    }
+
+    /// オプションを指定して、1つずつ推論を行うイテレータを返す。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate]の範囲外の場合はエラーを返します。
+    pub fn infer_stream_with(
+        &self,
+        input: &[I],
+        options: &InferOptions,
+    ) -> Result<InferStream<'_, O>, StrategyError> {
+        options.strategy.validate()?;
+        Ok(self.stream_unchecked(input, options))
+    }
+
+    /// 検証済みのオプションで、1つずつ推論を行うイテレータを返す。
+    fn stream_unchecked(&self, input: &[I], options: &InferOptions) -> InferStream<'_, O> {
+        let seed = options.seed().unwrap_or_else(generate_seed);
+        let decoder = self
+            .decoder(input, options, seeded_rng(seed))
+            .map(|(_, decoder)| decoder);
+        InferStream {
+            decoder,
+            out_table: &self.out_table,
+            output_format: options.output_format,
+        }
+    }
+
+    /// ビームサーチで、対数確率の高い順に最大`n`個の候補を返す。
+    ///
+    /// ビーム幅は`n`です。アルゴリズムとシードは使われませんが、
+    /// 最大長、[Constraints]、[OutputFormat]は反映されます。
+    pub fn infer_n_best(
+        &self,
+        input: &[I],
+        n: usize,
+        options: &InferOptions,
+    ) -> Vec<Candidate<Vec<O>>> {
+        let Some((_, source)) = self.encode_input(input) else {
+            return Vec::new();
+        };
+        let banned = self.banned_indices(options);
+        beam_search(&self.s2s, &source, n, options, &banned)
+            .into_iter()
+            .map(|(tokens, log_prob)| Candidate {
+                kana: tokens
+                    .into_iter()
+                    .map(|index| self.out_table[&index].convert(options.output_format))
+                    .collect(),
+                log_prob,
+            })
+            .collect()
+    }
+
+    /// [BaseE2k::infer]で使われるオプションを返す。
+    pub fn default_options(&self) -> &InferOptions {
+        &self.options
+    }
+
+    /// モデルの情報を返す。
+    pub fn model_info(&self) -> &ModelInfo {
+        &self.info
+    }
+
+    /// 重みと保持する形式から計算したハッシュ値を返す。
+    pub(crate) fn model_digest(&self) -> u64 {
+        self.digest
+    }
+
+    /// アルゴリズムを設定する。
+    ///
+    /// [BaseE2k::infer]で使われるオプションを変更します。
+    /// 推論ごとにアルゴリズムを変える場合は[BaseE2k::infer_with]を使ってください。
+    ///
+    /// # Panics
+    ///
+    /// パラメータが[Strategy::validate]の範囲外の場合。
+    pub fn set_decode_strategy(&mut self, strategy: Strategy) {
+        if let Err(e) = strategy.validate() {
+            panic!("invalid strategy: {e}");
+        }
+        self.options.strategy = strategy;
+    }
+
+    /// 検証済みのオプションで推論を行う。
+    fn infer_unchecked(&self, input: &[I], options: &InferOptions) -> Vec<O> {
+        let seed = options.seed().unwrap_or_else(generate_seed);
+        self.sample(input, options, &mut seeded_rng(seed))
+    }
+
+    fn sample<R: rand::Rng + ?Sized>(
+        &self,
+        input: &[I],
+        options: &InferOptions,
+        rng: &mut R,
+    ) -> Vec<O> {
+        let Some((_, decoder)) = self.decoder(input, options, rng) else {
+            return Vec::new();
+        };
+        decoder
+            .map(|token| self.out_table[&token.index].convert(options.output_format))
+            .collect()
+    }
+
+    /// 入力をモデルの入力に変換する。
+    ///
+    /// モデルに渡した入力の位置と、SOS/EOSを付けたモデルの入力を返す。
+    /// 有効な入力が無い場合は`None`を返す。
+    fn encode_input(&self, input: &[I]) -> Option<(Vec<usize>, ndarray::Array1<usize>)> {
+        let (positions, source): (Vec<_>, Vec<_>) = input
             .iter()
-            .skip(1)
-            .take_while(|&&x| x != constants::EOS_IDX)
-            .map(|&x| self.out_table[&x].clone())
+            .enumerate()
+            .filter_map(|(i, c)| self.in_table.get(c).map(|&idx| (i, idx)))
+            .unzip();
+        if source.is_empty() {
+            return None;
+        }
+        let source = [self.s2s.sos_idx]
+            .into_iter()
+            .chain(source)
+            .chain([self.s2s.eos_idx]);
+        Some((positions, ndarray::Array1::from_iter(source)))
+    }
+
+    /// 入力をエンコードし、デコーダを作る。
+    ///
+    /// モデルに渡した入力の位置とデコーダを返す。
+    /// 有効な入力が無い場合は`None`を返す。
+    fn decoder<R: rand::Rng>(
+        &self,
+        input: &[I],
+        options: &InferOptions,
+        rng: R,
+    ) -> Option<(Vec<usize>, Decoder<'_, R>)> {
+        let (positions, source) = self.encode_input(input)?;
+        let banned = self.banned_indices(options);
+        Some((
+            positions,
+            Decoder::new(&self.s2s, &source, options, banned, rng),
+        ))
+    }
+
+    fn banned_indices(&self, options: &InferOptions) -> Vec<usize> {
+        self.out_table
+            .iter()
+            .filter(|(_, o)| o.contains_any(&options.constraints.banned_chars))
+            .map(|(&i, _)| i)
             .collect()
     }
+}
+
+impl BaseE2k<String, String> {
+    /// safetensors形式のバイト列から、メタデータに含まれる語彙を使ってインスタンスを生成する。
+    ///
+    /// 語彙はメタデータの`input_vocab`と`output_vocab`に、文字列のJSON配列として格納してください。
+    /// 配列の位置がモデルの入力・出力のインデックスになります。
+    /// その他のキーについては[ModelInfo]を参照してください。
+    pub fn from_bytes(data: &[u8], max_length: usize) -> Result<Self, LoadError> {
+        Self::from_bytes_with_precision(data, max_length, Precision::default())
+    }
+
+    /// 重みを保持する形式を指定して、safetensors形式のバイト列からインスタンスを生成する。
+    pub fn from_bytes_with_precision(
+        data: &[u8],
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        let info = ModelInfo::read(data, None)?;
+        let in_table = vocab_table(&info.input_vocab);
+        let out_table = info.output_vocab.iter().cloned().enumerate().collect();
+        let tensors = safetensors::SafeTensors::deserialize(data)?;
+        Self::with_precision(tensors, in_table, out_table, info, precision, max_length)
+    }
 
-    fn set_decode_strategy(&mut self, strategy: Strategy) {
-        self.s2s.strategy = strategy;
+    /// safetensors形式のファイルを読み込んでインスタンスを生成する。
+    ///
+    /// `precision`が[Precision::F16]でモデルがf16の場合、全結合層・再帰層・注意機構の重みは
+    /// コピーされずに読み込んだバイト列を直接参照します。
+    /// それ以外の場合は[BaseE2k::from_bytes_with_precision]と同じく変換した重みを保持します。
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        Self::from_shared(&read_file(path.as_ref())?, max_length, precision)
     }
+
+    /// safetensors形式のファイルをメモリマップしてインスタンスを生成する。
+    ///
+    /// `precision`が[Precision::F16]でモデルがf16の場合、重みはファイルを直接参照するため、
+    /// 同じファイルを読み込んだプロセス間でページキャッシュが共有されます。
+    ///
+    /// # Safety
+    ///
+    /// インスタンスが存在する間、他のプロセスなどがファイルを変更したり切り詰めたりしてはいけません。
+    /// 変更された場合の動作は未定義です。
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_file_mmap(
+        path: impl AsRef<std::path::Path>,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        // SAFETY: ファイルが変更されないことは呼び出し側が保証する。
+        Self::from_shared(&unsafe { map_file(path.as_ref()) }?, max_length, precision)
+    }
+
+    fn from_shared(
+        data: &layers::SharedBytes,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        let info = ModelInfo::read(data.as_bytes(), None)?;
+        let in_table = vocab_table(&info.input_vocab);
+        let out_table = info.output_vocab.iter().cloned().enumerate().collect();
+        let weights = Weights::shared(data, precision)?;
+        Self::from_weights(weights, in_table, out_table, info, max_length)
+    }
+}
+
+/// ファイルを読み込む。
+fn read_file(path: &std::path::Path) -> Result<layers::SharedBytes, LoadError> {
+    Ok(layers::SharedBytes(std::sync::Arc::new(std::fs::read(
+        path,
+    )?)))
+}
+
+/// ファイルをメモリマップする。
+///
+/// # Safety
+///
+/// 返したバイト列が使われている間、ファイルが変更されてはいけない。
+#[cfg(feature = "mmap")]
+unsafe fn map_file(path: &std::path::Path) -> Result<layers::SharedBytes, LoadError> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: ファイルが変更されないことは呼び出し側が保証する。
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    Ok(layers::SharedBytes(std::sync::Arc::new(mmap)))
+}
+
+/// 入力の語彙から入力のテーブルを作る。
+fn vocab_table(vocab: &[String]) -> HashMap<String, usize> {
+    vocab
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v.clone(), i))
+        .collect()
+}
+
+/// 出力の語彙から、1文字ずつの出力テーブルを作る。
+fn kana_table(vocab: &[String]) -> HashMap<usize, char> {
+    vocab
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            (
+                i,
+                c.chars()
+                    .next()
+                    .expect("Unreachable: ModelInfo should reject empty strings"),
+            )
+        })
+        .collect()
+}
+
+/// 1文字ずつの候補を文字列の候補にする。
+fn join_candidates(candidates: Vec<Candidate<Vec<char>>>) -> Vec<Candidate> {
+    candidates
+        .into_iter()
+        .map(|c| Candidate {
+            kana: c.kana.into_iter().collect(),
+            log_prob: c.log_prob,
+        })
+        .collect()
 }
 
 /// 英単語 -> カタカナの変換器。
@@ -339,9 +1560,24 @@ impl C2k {
     ///
     /// - `max_length`: 読みの最大長。
     pub fn new(max_length: usize) -> Self {
-        static MODEL: std::sync::LazyLock<Vec<u8>> = std::sync::LazyLock::new(|| {
+        Self::with_precision(max_length, Precision::default())
+    }
+
+    /// 重みを保持する形式を指定して新しいインスタンスを生成する。
+    ///
+    /// 組み込みのモデルはf16で、[Precision::F16]の場合は全結合層などの重みをコピーせず、
+    /// モデルのバイト列を直接参照します。そのため[Precision::F32]より使用メモリが減ります。
+    /// `compress_model` featureが有効な場合は、展開したモデルのバイト列を最初の呼び出しから
+    /// プロセスの終了まで保持するため、[Precision::F32]と[Precision::Int8]ではそれに加えて重みを保持します。
+    ///
+    /// # Arguments
+    ///
+    /// - `max_length`: 読みの最大長。
+    /// - `precision`: 重みを保持する形式。
+    pub fn with_precision(max_length: usize, precision: Precision) -> Self {
+        static MODEL: std::sync::LazyLock<layers::SharedBytes> = std::sync::LazyLock::new(|| {
             cfg_elif::expr::cfg!(if (docsrs) {
-                Vec::new()
+                layers::SharedBytes(std::sync::Arc::new(Vec::new()))
             } else if (feature == "compress_model") {
                 {
                     use std::io::Read;
@@ -352,45 +1588,448 @@ impl C2k {
                     let mut input = brotli_decompressor::Decompressor::new(model.as_slice(), 4096);
                     let mut buf = Vec::new();
                     input.read_to_end(&mut buf).expect("Model is corrupted");
-                    buf
+                    layers::SharedBytes(std::sync::Arc::new(buf))
                 }
             } else {
-                include_bytes!(concat!(env!("E2K_MODEL_ROOT"), "/model-c2k.safetensors")).to_vec()
+                {
+                    /// f16の重みを直接参照できるよう、バイト列をアラインする。
+                    #[repr(C, align(8))]
+                    struct Aligned<T: ?Sized>(T);
+                    static MODEL: &Aligned<[u8]> = &Aligned(*include_bytes!(concat!(
+                        env!("E2K_MODEL_ROOT"),
+                        "/model-c2k.safetensors"
+                    )));
+                    layers::SharedBytes(std::sync::Arc::new(&MODEL.0))
+                }
             })
         });
-        let weights = safetensors::SafeTensors::deserialize(&MODEL).expect("Model is corrupted");
-        let inner = BaseE2k::new(
-            weights,
-            constants::ASCII_ENTRIES
-                .iter()
-                .enumerate()
-                .map(|(i, &c)| (c.to_string(), i))
-                .collect(),
-            constants::KANAS
-                .iter()
-                .enumerate()
-                .map(|(i, &c)| {
-                    (
-                        i,
-                        c.chars()
-                            .next()
-                            .expect("Unreachable: There should be no empty string"),
-                    )
-                })
-                .collect(),
-            max_length,
-        );
-        Self { inner }
+        let mut info = ModelInfo::read(MODEL.as_bytes(), Some(constants::ASCII_ENTRIES))
+            .expect("Model is corrupted");
+        if info.version.is_none() {
+            info.version = option_env!("E2K_MODEL_TAG").map(|tag| tag.to_string());
+        }
+        let weights = Weights::shared(&MODEL, precision).expect("Model is corrupted");
+        Self::with_model_info(weights, info, max_length).expect("Model is corrupted")
+    }
+
+    /// safetensors形式のバイト列からインスタンスを生成する。
+    ///
+    /// `train/`でエクスポートしたモデルなど、組み込みのモデル以外を使う場合に使います。
+    /// メタデータが無い値には組み込みの値が使われます。詳細は[ModelInfo]を参照してください。
+    pub fn from_bytes(data: &[u8], max_length: usize) -> Result<Self, LoadError> {
+        Self::from_bytes_with_precision(data, max_length, Precision::default())
+    }
+
+    /// 重みを保持する形式を指定して、safetensors形式のバイト列からインスタンスを生成する。
+    pub fn from_bytes_with_precision(
+        data: &[u8],
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        let info = ModelInfo::read(data, Some(constants::ASCII_ENTRIES))?;
+        let tensors = safetensors::SafeTensors::deserialize(data)?;
+        Self::with_model_info(Weights::new(tensors, precision), info, max_length)
+    }
+
+    /// safetensors形式のファイルを読み込んでインスタンスを生成する。
+    ///
+    /// 重みの扱いについては[BaseE2k::from_file]を参照してください。
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        Self::from_shared(&read_file(path.as_ref())?, max_length, precision)
+    }
+
+    /// safetensors形式のファイルをメモリマップしてインスタンスを生成する。
+    ///
+    /// 重みの扱いについては[BaseE2k::from_file_mmap]を参照してください。
+    ///
+    /// # Safety
+    ///
+    /// インスタンスが存在する間、他のプロセスなどがファイルを変更したり切り詰めたりしてはいけません。
+    /// 変更された場合の動作は未定義です。
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_file_mmap(
+        path: impl AsRef<std::path::Path>,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        // SAFETY: ファイルが変更されないことは呼び出し側が保証する。
+        Self::from_shared(&unsafe { map_file(path.as_ref()) }?, max_length, precision)
+    }
+
+    fn from_shared(
+        data: &layers::SharedBytes,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        let info = ModelInfo::read(data.as_bytes(), Some(constants::ASCII_ENTRIES))?;
+        Self::with_model_info(Weights::shared(data, precision)?, info, max_length)
+    }
+
+    fn with_model_info(
+        weights: Weights,
+        info: ModelInfo,
+        max_length: usize,
+    ) -> Result<Self, LoadError> {
+        let in_table = vocab_table(&info.input_vocab);
+        let out_table = kana_table(&info.output_vocab);
+        let inner = BaseE2k::from_weights(weights, in_table, out_table, info, max_length)?;
+        Ok(Self { inner })
+    }
+
+    /// モデルの情報を返す。
+    ///
+    /// メタデータにバージョンが無い場合、ダウンロードしたモデルのタグがバージョンになります。
+    pub fn model_info(&self) -> &ModelInfo {
+        self.inner.model_info()
+    }
+
+    /// 重みと保持する形式から計算したハッシュ値を返す。
+    pub(crate) fn model_digest(&self) -> u64 {
+        self.inner.model_digest()
     }
 
     /// 推論を行う。
+    ///
+    /// [C2k::new]と[C2k::set_decode_strategy]で設定したオプションを使います。
     pub fn infer(&self, input: &str) -> String {
-        let input = input.chars().map(|c| c.to_string()).collect::<Vec<_>>();
-        self.inner.infer(&input).into_iter().collect()
+        self.inner.infer(&Self::split(input)).into_iter().collect()
+    }
+
+    /// オプションを指定して推論を行う。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate]の範囲外の場合はエラーを返します。
+    pub fn infer_with(&self, input: &str, options: &InferOptions) -> Result<String, StrategyError> {
+        Ok(self
+            .inner
+            .infer_with(&Self::split(input), options)?
+            .into_iter()
+            .collect())
+    }
+
+    /// 指定した乱数生成器を使って推論を行う。
+    ///
+    /// アルゴリズムに設定されたシードは無視され、`rng`がサンプリングに使われます。
+    pub fn infer_with_rng<R: rand::Rng + ?Sized>(&self, input: &str, rng: &mut R) -> String {
+        self.inner
+            .infer_with_rng(&Self::split(input), rng)
+            .into_iter()
+            .collect()
+    }
+
+    /// 推論を行い、出力した文字ごとに入力の文字に対する注意の重みを返す。
+    ///
+    /// 重みの位置は入力の文字（[char]）単位です。
+    /// 重みはSOS/EOSに対する重みを除いて合計が1になるよう正規化されており、
+    /// モデルが扱えない文字に対する重みは0になります。
+    pub fn infer_with_alignment(
+        &self,
+        input: &str,
+        options: &InferOptions,
+    ) -> Result<Vec<KanaAlignment>, StrategyError> {
+        self.inner
+            .infer_with_alignment(&Self::split(input), options)
+    }
+
+    /// 1文字ずつ推論を行うイテレータを返す。
+    ///
+    /// [C2k::infer]と同じオプションを使います。
+    /// エンコードはこのメソッドの呼び出し時に行われ、デコードはイテレータを進めるごとに行われます。
+    pub fn infer_stream(&self, input: &str) -> InferStream<'_> {
+        self.inner.infer_stream(&Self::split(input))
+    }
+
+    /// オプションを指定して、1文字ずつ推論を行うイテレータを返す。
+    pub fn infer_stream_with(
+        &self,
+        input: &str,
+        options: &InferOptions,
+    ) -> Result<InferStream<'_>, StrategyError> {
+        self.inner.infer_stream_with(&Self::split(input), options)
+    }
+
+    /// ビームサーチで、対数確率の高い順に最大`n`個の候補を返す。
+    ///
+    /// 詳細は[BaseE2k::infer_n_best]を参照してください。
+    pub fn infer_n_best(&self, input: &str, n: usize, options: &InferOptions) -> Vec<Candidate> {
+        join_candidates(self.inner.infer_n_best(&Self::split(input), n, options))
+    }
+
+    /// [C2k::infer]で使われるオプションを返す。
+    pub fn default_options(&self) -> &InferOptions {
+        &self.inner.options
+    }
+
+    /// アルゴリズムを設定する。
+    ///
+    /// [C2k::infer]で使われるオプションを変更します。
+    /// 推論ごとにアルゴリズムを変える場合は[C2k::infer_with]を使ってください。
+    ///
+    /// # Panics
+    ///
+    /// パラメータが[Strategy::validate]の範囲外の場合。
+    pub fn set_decode_strategy(&mut self, strategy: Strategy) {
+        self.inner.set_decode_strategy(strategy);
+    }
+
+    fn split(input: &str) -> Vec<String> {
+        input.chars().map(|c| c.to_string()).collect()
+    }
+}
+
+/// ARPAbet（CMUdictの発音記号） -> カタカナの変換器。
+///
+/// 入力は`["K", "AE1", "T"]`のような発音記号の列です。
+/// 大文字・小文字は区別せず、ストレスを表す数字が無い母音は第1強勢（`1`）として扱います。
+///
+/// モデルは同梱されていないため、[P2k::new]か[P2k::from_bytes]で重みを指定してください。
+pub struct P2k {
+    inner: BaseE2k<String, char>,
+}
+
+impl std::fmt::Debug for P2k {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("P2k").finish()
+    }
+}
+
+impl P2k {
+    /// 新しいインスタンスを生成する。
+    ///
+    /// # Arguments
+    ///
+    /// - `tensors`: モデルの重み。
+    /// - `max_length`: 読みの最大長。
+    ///
+    /// 語彙などには組み込みの値が使われます。
+    /// メタデータを読む場合は[P2k::from_bytes]を使ってください。
+    ///
+    /// # Panics
+    ///
+    /// 重みが不正な場合はパニックします。
+    pub fn new(tensors: safetensors::SafeTensors, max_length: usize) -> Self {
+        let info = ModelInfo {
+            input_vocab: constants::EN_PHONES.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        };
+        Self::with_model_info(
+            Weights::new(tensors, Precision::default()),
+            info,
+            max_length,
+        )
+        .unwrap_or_else(|e| panic!("model corrupted: {e}"))
+    }
+
+    /// safetensors形式のバイト列からインスタンスを生成する。
+    ///
+    /// メタデータが無い値には組み込みの値が使われます。詳細は[ModelInfo]を参照してください。
+    pub fn from_bytes(data: &[u8], max_length: usize) -> Result<Self, LoadError> {
+        Self::from_bytes_with_precision(data, max_length, Precision::default())
+    }
+
+    /// 重みを保持する形式を指定して、safetensors形式のバイト列からインスタンスを生成する。
+    pub fn from_bytes_with_precision(
+        data: &[u8],
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        let info = ModelInfo::read(data, Some(constants::EN_PHONES))?;
+        let tensors = safetensors::SafeTensors::deserialize(data)?;
+        Self::with_model_info(Weights::new(tensors, precision), info, max_length)
+    }
+
+    /// safetensors形式のファイルを読み込んでインスタンスを生成する。
+    ///
+    /// 重みの扱いについては[BaseE2k::from_file]を参照してください。
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        Self::from_shared(&read_file(path.as_ref())?, max_length, precision)
+    }
+
+    /// safetensors形式のファイルをメモリマップしてインスタンスを生成する。
+    ///
+    /// 重みの扱いについては[BaseE2k::from_file_mmap]を参照してください。
+    ///
+    /// # Safety
+    ///
+    /// インスタンスが存在する間、他のプロセスなどがファイルを変更したり切り詰めたりしてはいけません。
+    /// 変更された場合の動作は未定義です。
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_file_mmap(
+        path: impl AsRef<std::path::Path>,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        // SAFETY: ファイルが変更されないことは呼び出し側が保証する。
+        Self::from_shared(&unsafe { map_file(path.as_ref()) }?, max_length, precision)
+    }
+
+    fn from_shared(
+        data: &layers::SharedBytes,
+        max_length: usize,
+        precision: Precision,
+    ) -> Result<Self, LoadError> {
+        let info = ModelInfo::read(data.as_bytes(), Some(constants::EN_PHONES))?;
+        Self::with_model_info(Weights::shared(data, precision)?, info, max_length)
+    }
+
+    fn with_model_info(
+        weights: Weights,
+        info: ModelInfo,
+        max_length: usize,
+    ) -> Result<Self, LoadError> {
+        let mut in_table = vocab_table(&info.input_vocab);
+        for (i, phone) in info.input_vocab.iter().enumerate() {
+            if let Some(base) = phone.strip_suffix('1') {
+                in_table.insert(base.to_string(), i);
+            }
+        }
+        let out_table = kana_table(&info.output_vocab);
+        let inner = BaseE2k::from_weights(weights, in_table, out_table, info, max_length)?;
+        Ok(Self { inner })
+    }
+
+    /// モデルの情報を返す。
+    pub fn model_info(&self) -> &ModelInfo {
+        self.inner.model_info()
+    }
+
+    /// 推論を行う。
+    ///
+    /// [P2k::new]と[P2k::set_decode_strategy]で設定したオプションを使います。
+    pub fn infer<S: AsRef<str>>(&self, input: &[S]) -> String {
+        self.inner
+            .infer(&Self::normalize(input))
+            .into_iter()
+            .collect()
+    }
+
+    /// オプションを指定して推論を行う。
+    ///
+    /// アルゴリズムのパラメータが[Strategy::validate]の範囲外の場合はエラーを返します。
+    pub fn infer_with<S: AsRef<str>>(
+        &self,
+        input: &[S],
+        options: &InferOptions,
+    ) -> Result<String, StrategyError> {
+        Ok(self
+            .inner
+            .infer_with(&Self::normalize(input), options)?
+            .into_iter()
+            .collect())
+    }
+
+    /// 指定した乱数生成器を使って推論を行う。
+    ///
+    /// アルゴリズムに設定されたシードは無視され、`rng`がサンプリングに使われます。
+    pub fn infer_with_rng<S: AsRef<str>, R: rand::Rng + ?Sized>(
+        &self,
+        input: &[S],
+        rng: &mut R,
+    ) -> String {
+        self.inner
+            .infer_with_rng(&Self::normalize(input), rng)
+            .into_iter()
+            .collect()
+    }
+
+    /// 推論を行い、出力した文字ごとに入力の発音記号に対する注意の重みを返す。
+    ///
+    /// 重みの位置は入力の発音記号単位です。詳細は[C2k::infer_with_alignment]を参照してください。
+    pub fn infer_with_alignment<S: AsRef<str>>(
+        &self,
+        input: &[S],
+        options: &InferOptions,
+    ) -> Result<Vec<KanaAlignment>, StrategyError> {
+        self.inner
+            .infer_with_alignment(&Self::normalize(input), options)
+    }
+
+    /// 1文字ずつ推論を行うイテレータを返す。
+    ///
+    /// [P2k::infer]と同じオプションを使います。
+    pub fn infer_stream<S: AsRef<str>>(&self, input: &[S]) -> InferStream<'_> {
+        self.inner.infer_stream(&Self::normalize(input))
+    }
+
+    /// オプションを指定して、1文字ずつ推論を行うイテレータを返す。
+    pub fn infer_stream_with<S: AsRef<str>>(
+        &self,
+        input: &[S],
+        options: &InferOptions,
+    ) -> Result<InferStream<'_>, StrategyError> {
+        self.inner
+            .infer_stream_with(&Self::normalize(input), options)
+    }
+
+    /// ビームサーチで、対数確率の高い順に最大`n`個の候補を返す。
+    ///
+    /// 詳細は[BaseE2k::infer_n_best]を参照してください。
+    pub fn infer_n_best<S: AsRef<str>>(
+        &self,
+        input: &[S],
+        n: usize,
+        options: &InferOptions,
+    ) -> Vec<Candidate> {
+        join_candidates(self.inner.infer_n_best(&Self::normalize(input), n, options))
+    }
+
+    /// [P2k::infer]で使われるオプションを返す。
+    pub fn default_options(&self) -> &InferOptions {
+        &self.inner.options
     }
 
     /// アルゴリズムを設定する。
+    ///
+    /// [P2k::infer]で使われるオプションを変更します。
+    /// 推論ごとにアルゴリズムを変える場合は[P2k::infer_with]を使ってください。
+    ///
+    /// # Panics
+    ///
+    /// パラメータが[Strategy::validate]の範囲外の場合。
     pub fn set_decode_strategy(&mut self, strategy: Strategy) {
         self.inner.set_decode_strategy(strategy);
     }
+
+    fn normalize<S: AsRef<str>>(input: &[S]) -> Vec<String> {
+        input
+            .iter()
+            .map(|p| p.as_ref().trim().to_ascii_uppercase())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_logits() {
+        let mut logits = ndarray::array![0.5, 1.0, 2.0];
+        mask_logits(&mut logits, &[0], 2, true);
+        assert_eq!(
+            logits,
+            ndarray::array![f32::NEG_INFINITY, 1.0, f32::NEG_INFINITY]
+        );
+
+        // すべてのトークンが対象になる場合はEOSを残す
+        let mut logits = ndarray::array![0.5, 1.0, 2.0];
+        mask_logits(&mut logits, &[0, 1, 2], 2, true);
+        assert_eq!(
+            logits,
+            ndarray::array![f32::NEG_INFINITY, f32::NEG_INFINITY, 2.0]
+        );
+    }
+
+    #[test]
+    fn test_normalize_heads() {
+        let weights = normalize_heads(ndarray::array![[1.0, 3.0], [0.0, 0.0]]);
+        assert_eq!(weights, ndarray::array![[0.25, 0.75], [0.0, 0.0]]);
+    }
 }
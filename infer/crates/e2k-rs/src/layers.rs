@@ -1,3 +1,4 @@
+use crate::Precision;
 use itertools::Itertools;
 use ndarray::prelude::*;
 
@@ -39,12 +40,6 @@ pub(crate) fn split_ndarray<T, D: ndarray::Dimension>(
         .collect()
 }
 
-macro_rules! split_ndarray {
-    ($array:expr, $n:expr, $axis:expr) => {{
-        let split = split_ndarray($array, $n, $axis);
-        split.into_iter().collect_tuple().unwrap()
-    }};
-}
 macro_rules! split_ndarray_owned {
     ($array:expr, $n:expr, $axis:expr) => {{
         let split = split_ndarray($array, $n, $axis);
@@ -58,22 +53,183 @@ macro_rules! split_ndarray_owned {
 
 #[derive(Debug)]
 pub(crate) struct Linear {
-    weight: ndarray::Array2<f32>,
+    weight: Weight,
     bias: ndarray::Array1<f32>,
 }
 
+/// [Linear]の重み。
+#[derive(Debug)]
+enum Weight {
+    F32(ndarray::Array2<f32>),
+    /// 推論時に行ごとにf32に変換する。
+    F16(ndarray::Array2<half::f16>),
+    /// 他と共有しているバイト列（メモリマップしたファイルなど）の中のf16の重み。
+    SharedF16 {
+        data: SharedBytes,
+        offset: usize,
+        shape: (usize, usize),
+    },
+    /// 行ごとに量子化した重み。`weight[i][j] = values[i][j] * scales[i]`。
+    Int8 {
+        values: ndarray::Array2<i8>,
+        scales: ndarray::Array1<f32>,
+    },
+}
+
+/// 複数の層で共有するバイト列。
+#[derive(Clone)]
+pub(crate) struct SharedBytes(pub std::sync::Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+impl std::fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedBytes({} bytes)", self.as_bytes().len())
+    }
+}
+
+impl SharedBytes {
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.0.as_ref().as_ref()
+    }
+
+    /// `offset`から`len`個のf16を、コピーせずに返す。
+    ///
+    /// 範囲外の場合や、f16として読めない（アラインされていない、ビッグエンディアン）場合は`None`を返す。
+    fn f16_slice(&self, offset: usize, len: usize) -> Option<&[half::f16]> {
+        if cfg!(target_endian = "big") {
+            return None;
+        }
+        let bytes = self.as_bytes().get(offset..offset.checked_add(len * 2)?)?;
+        bytemuck::try_cast_slice(bytes).ok()
+    }
+}
+
 impl Linear {
     pub fn new(weight: ndarray::Array2<f32>, bias: ndarray::Array1<f32>) -> Self {
-        Self { weight, bias }
+        Self {
+            weight: Weight::F32(weight),
+            bias,
+        }
+    }
+    /// `data`の`offset`バイト目からのリトルエンディアンのf16を、コピーせずに重みとして使う。
+    ///
+    /// 重みとして読めない場合は`None`を返す。
+    pub fn shared_f16(
+        data: SharedBytes,
+        offset: usize,
+        shape: (usize, usize),
+        bias: ndarray::Array1<f32>,
+    ) -> Option<Self> {
+        data.f16_slice(offset, shape.0 * shape.1)?;
+        Some(Self {
+            weight: Weight::SharedF16 {
+                data,
+                offset,
+                shape,
+            },
+            bias,
+        })
+    }
+    pub fn out_features(&self) -> usize {
+        self.shape().0
+    }
+    pub fn in_features(&self) -> usize {
+        self.shape().1
+    }
+    fn shape(&self) -> (usize, usize) {
+        match &self.weight {
+            Weight::F32(weight) => weight.dim(),
+            Weight::F16(weight) => weight.dim(),
+            Weight::SharedF16 { shape, .. } => *shape,
+            Weight::Int8 { values, .. } => values.dim(),
+        }
     }
     pub fn forward_2d(&self, input: &ndarray::ArrayView2<f32>) -> ndarray::Array2<f32> {
-        let output = input.dot(&self.weight.t());
-        output + &self.bias
+        match &self.weight {
+            Weight::F32(weight) => input.dot(&weight.t()) + &self.bias,
+            _ => {
+                let mut output = ndarray::Array2::zeros((input.nrows(), self.out_features()));
+                for (input, mut output) in input.outer_iter().zip(output.outer_iter_mut()) {
+                    output.assign(&self.forward_1d(&input));
+                }
+                output
+            }
+        }
     }
     pub fn forward_1d(&self, input: &ndarray::ArrayView1<f32>) -> ndarray::Array1<f32> {
-        let output = input.dot(&self.weight.t());
+        let output = match &self.weight {
+            Weight::F32(weight) => input.dot(&weight.t()),
+            Weight::F16(weight) => f16_matvec(
+                weight.as_slice().expect("weight should be contiguous"),
+                weight.ncols(),
+                input,
+            ),
+            Weight::SharedF16 {
+                data,
+                offset,
+                shape,
+            } => f16_matvec(
+                data.f16_slice(*offset, shape.0 * shape.1)
+                    .expect("Unreachable: checked in Linear::shared_f16"),
+                shape.1,
+                input,
+            ),
+            Weight::Int8 { values, scales } => {
+                let mut row_f32 = ndarray::Array1::zeros(values.ncols());
+                values
+                    .outer_iter()
+                    .zip(scales)
+                    .map(|(row, scale)| {
+                        row_f32.zip_mut_with(&row, |x, &v| *x = v as f32);
+                        row_f32.dot(input) * scale
+                    })
+                    .collect()
+            }
+        };
         output + &self.bias
     }
+
+    /// 重みを`precision`の形式に変換する。f32の重みにのみ使える。
+    pub fn quantize(&mut self, precision: Precision) {
+        let weight = match &self.weight {
+            Weight::F32(weight) => weight,
+            _ => panic!("weight is already quantized"),
+        };
+        self.weight = match precision {
+            Precision::F32 => return,
+            Precision::F16 => Weight::F16(weight.mapv(half::f16::from_f32)),
+            Precision::Int8 => {
+                let scales = weight
+                    .outer_iter()
+                    .map(|row| row.fold(0f32, |max, x| max.max(x.abs())) / i8::MAX as f32)
+                    .collect::<ndarray::Array1<f32>>();
+                let values = ndarray::Array2::from_shape_fn(weight.dim(), |(i, j)| {
+                    if scales[i] == 0.0 {
+                        0
+                    } else {
+                        (weight[[i, j]] / scales[i]).round() as i8
+                    }
+                });
+                Weight::Int8 { values, scales }
+            }
+        };
+    }
+}
+
+/// 行優先のf16の行列と、ベクトルの積。
+fn f16_matvec(
+    weight: &[half::f16],
+    cols: usize,
+    input: &ndarray::ArrayView1<f32>,
+) -> ndarray::Array1<f32> {
+    use half::slice::HalfFloatSliceExt;
+    let mut row_f32 = ndarray::Array1::zeros(cols);
+    weight
+        .chunks_exact(cols)
+        .map(|row| {
+            row.convert_to_f32_slice(row_f32.as_slice_mut().unwrap());
+            row_f32.dot(input)
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -85,6 +241,9 @@ impl Embedding {
     pub fn new(weight: ndarray::Array2<f32>) -> Self {
         Self { weight }
     }
+    pub fn num_embeddings(&self) -> usize {
+        self.weight.nrows()
+    }
     pub fn forward(&self, input: &ndarray::Array1<usize>) -> ndarray::Array2<f32> {
         ndarray::stack(
             ndarray::Axis(0),
@@ -109,21 +268,18 @@ pub(crate) struct Mha {
 }
 
 impl Mha {
+    /// クエリ・キー・値への射影と出力の射影から生成する。
     pub(crate) fn new(
-        in_proj_weight: ndarray::Array2<f32>,
-        in_proj_bias: ndarray::Array1<f32>,
-        out_proj_weight: ndarray::Array2<f32>,
-        out_proj_bias: ndarray::Array1<f32>,
+        q_proj: Linear,
+        k_proj: Linear,
+        v_proj: Linear,
+        out_proj: Linear,
         num_heads: usize,
     ) -> Self {
-        let (q_w, k_w, v_w) = split_ndarray!(&in_proj_weight, 3, ndarray::Axis(0));
-        let (q_b, k_b, v_b) = split_ndarray!(&in_proj_bias, 3, ndarray::Axis(0));
-        let dim = q_w.shape()[q_w.ndim() - 1];
-        let q_proj = Linear::new(q_w.to_owned(), q_b.to_owned());
-        let k_proj = Linear::new(k_w.to_owned(), k_b.to_owned());
-        let v_proj = Linear::new(v_w.to_owned(), v_b.to_owned());
-        let out_proj = Linear::new(out_proj_weight, out_proj_bias);
-        let scale = (dim as f32).sqrt();
+        let dim = q_proj.in_features();
+        assert_eq!(dim % num_heads, 0, "dim should be divisible by num_heads");
+        // PyTorchと同じく、ヘッドごとの次元数で割る。
+        let scale = ((dim / num_heads) as f32).sqrt();
         Self {
             q_proj,
             k_proj,
@@ -134,12 +290,13 @@ impl Mha {
         }
     }
 
+    /// 出力と、各ヘッドの注意の重み（`[n_heads, query_len, key_len]`）を返す。
     pub(crate) fn forward(
         &self,
         query: &ndarray::ArrayView2<f32>,
         key: &ndarray::ArrayView2<f32>,
         value: &ndarray::ArrayView2<f32>,
-    ) -> ndarray::Array2<f32> {
+    ) -> (ndarray::Array2<f32>, ndarray::Array3<f32>) {
         let q = self.q_proj.forward_2d(query);
         let k = self.k_proj.forward_2d(key);
         let v = self.v_proj.forward_2d(value);
@@ -153,7 +310,14 @@ impl Mha {
         transposed.swap_axes(2, 1);
         let attn = matmul_3d(&q, &transposed);
         let attn = attn / self.scale;
-        let attn = attn.exp();
+        let attn_max = attn
+            .fold_axis(
+                ndarray::Axis(attn.ndim() - 1),
+                f32::NEG_INFINITY,
+                |&a, &b| a.max(b),
+            )
+            .insert_axis(ndarray::Axis(attn.ndim() - 1));
+        let attn = (attn - attn_max).exp();
         let attn_sum = attn
             .sum_axis(ndarray::Axis(attn.ndim() - 1))
             .insert_axis(ndarray::Axis(attn.ndim() - 1));
@@ -163,7 +327,7 @@ impl Mha {
         let output = output
             .to_shape((output.shape()[0], output.shape()[1] * output.shape()[2]))
             .unwrap();
-        self.out_proj.forward_2d(&output.view())
+        (self.out_proj.forward_2d(&output.view()), attn)
     }
 }
 
@@ -174,14 +338,8 @@ pub(crate) struct GruCell {
 }
 
 impl GruCell {
-    pub(crate) fn new(
-        weight_ih: ndarray::Array2<f32>,
-        weight_hh: ndarray::Array2<f32>,
-        bias_ih: ndarray::Array1<f32>,
-        bias_hh: ndarray::Array1<f32>,
-    ) -> Self {
-        let ih = Linear::new(weight_ih, bias_ih);
-        let hh = Linear::new(weight_hh, bias_hh);
+    /// 入力と隠れ状態に対する全結合層から生成する。
+    pub(crate) fn new(ih: Linear, hh: Linear) -> Self {
         Self { ih, hh }
     }
 
@@ -191,7 +349,7 @@ impl GruCell {
         hidden: &Option<ndarray::ArrayView1<f32>>,
     ) -> ndarray::Array1<f32> {
         let hidden = hidden.map_or_else(
-            || ndarray::Array1::zeros((self.hh.weight.shape()[self.hh.weight.ndim() - 1],)),
+            || ndarray::Array1::zeros(self.hh.in_features()),
             |x| x.to_owned(),
         );
         let rzn_ih = self.ih.forward_1d(input);
@@ -258,6 +416,327 @@ impl Gru {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct LstmCell {
+    ih: Linear,
+    hh: Linear,
+}
+
+impl LstmCell {
+    /// 入力と隠れ状態に対する全結合層から生成する。
+    pub(crate) fn new(ih: Linear, hh: Linear) -> Self {
+        Self { ih, hh }
+    }
+
+    /// 新しい`(h, c)`を返す。
+    pub(crate) fn forward(
+        &self,
+        input: &ndarray::ArrayView1<f32>,
+        state: Option<(ndarray::ArrayView1<f32>, ndarray::ArrayView1<f32>)>,
+    ) -> (ndarray::Array1<f32>, ndarray::Array1<f32>) {
+        let (h, c) = state.map_or_else(
+            || {
+                let zeros = ndarray::Array1::zeros(self.hh.in_features());
+                (zeros.clone(), zeros)
+            },
+            |(h, c)| (h.to_owned(), c.to_owned()),
+        );
+        let gates = self.ih.forward_1d(input) + self.hh.forward_1d(&h.view());
+        // PyTorchと同じく、入力・忘却・セル・出力ゲートの順に並んでいる。
+        let (i, f, g, o) = split_ndarray_owned!(&gates, 4, ndarray::Axis(0));
+        let c = sigmoid_1d(f) * c + sigmoid_1d(i) * g.mapv(f32::tanh);
+        let h = sigmoid_1d(o) * c.mapv(f32::tanh);
+        (h, c)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Lstm {
+    cell: LstmCell,
+    reverse: bool,
+}
+
+impl Lstm {
+    pub(crate) fn new(cell: LstmCell, reverse: bool) -> Self {
+        Self { cell, reverse }
+    }
+
+    pub(crate) fn forward(
+        &self,
+        input: &ndarray::ArrayView2<f32>,
+        state: Option<(ndarray::ArrayView1<f32>, ndarray::ArrayView1<f32>)>,
+    ) -> (
+        ndarray::Array2<f32>,
+        (ndarray::Array1<f32>, ndarray::Array1<f32>),
+    ) {
+        let mut state = state.map(|(h, c)| (h.to_owned(), c.to_owned()));
+        let input = if self.reverse {
+            input.slice(s![..; -1, ..])
+        } else {
+            input.view()
+        };
+        let mut outputs = Vec::with_capacity(input.shape()[0]);
+        for x in input.outer_iter() {
+            let next = self
+                .cell
+                .forward(&x, state.as_ref().map(|(h, c)| (h.view(), c.view())));
+            outputs.push(next.0.clone());
+            state = Some(next);
+        }
+        let mut outputs = ndarray::stack(
+            ndarray::Axis(0),
+            &outputs.iter().map(|o| o.view()).collect_vec(),
+        )
+        .unwrap();
+        if self.reverse {
+            outputs = outputs.slice(s![..; -1, ..]).to_owned();
+        }
+        (outputs, state.unwrap())
+    }
+}
+
+/// 系列を先頭から（逆方向の場合は末尾から）1つずつ処理する層。
+pub(crate) trait Recurrent {
+    /// 隠れ状態。
+    type State: Clone;
+
+    /// 出力と、最後の隠れ状態を返す。
+    fn forward_with_state(
+        &self,
+        input: &ndarray::ArrayView2<f32>,
+        state: Option<&Self::State>,
+    ) -> (ndarray::Array2<f32>, Self::State);
+}
+
+impl Recurrent for Gru {
+    type State = ndarray::Array1<f32>;
+
+    fn forward_with_state(
+        &self,
+        input: &ndarray::ArrayView2<f32>,
+        state: Option<&Self::State>,
+    ) -> (ndarray::Array2<f32>, Self::State) {
+        self.forward(input, state.map(|h| h.view()))
+    }
+}
+
+impl Recurrent for Lstm {
+    type State = (ndarray::Array1<f32>, ndarray::Array1<f32>);
+
+    fn forward_with_state(
+        &self,
+        input: &ndarray::ArrayView2<f32>,
+        state: Option<&Self::State>,
+    ) -> (ndarray::Array2<f32>, Self::State) {
+        self.forward(input, state.map(|(h, c)| (h.view(), c.view())))
+    }
+}
+
+/// 多層・双方向に対応した再帰層。PyTorchの`nn.GRU`や`nn.LSTM`に相当する。
+#[derive(Debug)]
+pub(crate) struct StackedRnn<L> {
+    /// 層ごとの順方向と逆方向の層。
+    layers: Vec<(L, Option<L>)>,
+}
+
+pub(crate) type StackedGru = StackedRnn<Gru>;
+pub(crate) type StackedLstm = StackedRnn<Lstm>;
+
+impl<L: Recurrent> StackedRnn<L> {
+    /// 層ごとの順方向と逆方向の層から生成する。逆方向の層は全ての層にあるか、全ての層に無い必要がある。
+    pub(crate) fn new(layers: Vec<(L, Option<L>)>) -> Self {
+        assert!(!layers.is_empty(), "RNN should have at least one layer");
+        assert!(
+            layers.iter().map(|(_, r)| r.is_some()).all_equal(),
+            "all layers should have the same direction"
+        );
+        Self { layers }
+    }
+
+    pub(crate) fn is_bidirectional(&self) -> bool {
+        self.layers[0].1.is_some()
+    }
+
+    /// 出力と、最後の隠れ状態を返す。
+    ///
+    /// 隠れ状態はPyTorchと同じく、`[層0の順方向, 層0の逆方向, 層1の順方向, ...]`の順に並ぶ。
+    pub(crate) fn forward(
+        &self,
+        input: &ndarray::ArrayView2<f32>,
+        state: Option<&[L::State]>,
+    ) -> (ndarray::Array2<f32>, Vec<L::State>) {
+        let directions = if self.is_bidirectional() { 2 } else { 1 };
+        let mut output = input.to_owned();
+        let mut last_state = Vec::with_capacity(self.layers.len() * directions);
+        for (i, (forward, reverse)) in self.layers.iter().enumerate() {
+            let state = |d: usize| state.map(|s| &s[i * directions + d]);
+            let (out, state_forward) = forward.forward_with_state(&output.view(), state(0));
+            last_state.push(state_forward);
+            output = match reverse {
+                Some(reverse) => {
+                    let (out_reverse, state_reverse) =
+                        reverse.forward_with_state(&output.view(), state(1));
+                    last_state.push(state_reverse);
+                    ndarray::concatenate(Axis(1), &[out.view(), out_reverse.view()]).unwrap()
+                }
+                None => out,
+            };
+        }
+        (output, last_state)
+    }
+}
+
+/// [StackedGru]か[StackedLstm]。
+#[derive(Debug)]
+pub(crate) enum Rnn {
+    Gru(StackedGru),
+    Lstm(StackedLstm),
+}
+
+/// [Rnn]の隠れ状態。
+#[derive(Debug, Clone)]
+pub(crate) enum RnnState {
+    Gru(Vec<ndarray::Array1<f32>>),
+    Lstm(Vec<(ndarray::Array1<f32>, ndarray::Array1<f32>)>),
+}
+
+impl Rnn {
+    pub(crate) fn is_bidirectional(&self) -> bool {
+        match self {
+            Rnn::Gru(rnn) => rnn.is_bidirectional(),
+            Rnn::Lstm(rnn) => rnn.is_bidirectional(),
+        }
+    }
+
+    /// 出力と、最後の隠れ状態を返す。
+    pub(crate) fn forward(
+        &self,
+        input: &ndarray::ArrayView2<f32>,
+        state: Option<&RnnState>,
+    ) -> (ndarray::Array2<f32>, RnnState) {
+        match (self, state) {
+            (Rnn::Gru(rnn), None) => {
+                let (output, state) = rnn.forward(input, None);
+                (output, RnnState::Gru(state))
+            }
+            (Rnn::Gru(rnn), Some(RnnState::Gru(state))) => {
+                let (output, state) = rnn.forward(input, Some(state));
+                (output, RnnState::Gru(state))
+            }
+            (Rnn::Lstm(rnn), None) => {
+                let (output, state) = rnn.forward(input, None);
+                (output, RnnState::Lstm(state))
+            }
+            (Rnn::Lstm(rnn), Some(RnnState::Lstm(state))) => {
+                let (output, state) = rnn.forward(input, Some(state));
+                (output, RnnState::Lstm(state))
+            }
+            _ => panic!("Unreachable: The state should match the layer"),
+        }
+    }
+}
+
+/// PyTorchの`nn.LayerNorm`に相当する。最後の次元で正規化する。
+#[derive(Debug)]
+pub(crate) struct LayerNorm {
+    weight: ndarray::Array1<f32>,
+    bias: ndarray::Array1<f32>,
+    eps: f32,
+}
+
+impl LayerNorm {
+    pub(crate) fn new(weight: ndarray::Array1<f32>, bias: ndarray::Array1<f32>) -> Self {
+        Self {
+            weight,
+            bias,
+            eps: 1e-5,
+        }
+    }
+
+    pub(crate) fn forward_1d(&self, input: &ndarray::ArrayView1<f32>) -> ndarray::Array1<f32> {
+        let mean = input.mean().expect("input should not be empty");
+        let var = input.mapv(|x| (x - mean).powi(2)).mean().unwrap();
+        (input - mean) / (var + self.eps).sqrt() * &self.weight + &self.bias
+    }
+}
+
+/// PyTorchの`nn.TransformerDecoderLayer`（`norm_first=False`、`activation="relu"`）に相当する。
+#[derive(Debug)]
+pub(crate) struct TransformerDecoderLayer {
+    self_attn: Mha,
+    cross_attn: Mha,
+    linear1: Linear,
+    linear2: Linear,
+    norm1: LayerNorm,
+    norm2: LayerNorm,
+    norm3: LayerNorm,
+}
+
+impl TransformerDecoderLayer {
+    pub(crate) fn new(
+        self_attn: Mha,
+        cross_attn: Mha,
+        linear1: Linear,
+        linear2: Linear,
+        [norm1, norm2, norm3]: [LayerNorm; 3],
+    ) -> Self {
+        Self {
+            self_attn,
+            cross_attn,
+            linear1,
+            linear2,
+            norm1,
+            norm2,
+            norm3,
+        }
+    }
+
+    /// 1位置分を処理する。
+    ///
+    /// `cache`はこの層のそれまでの位置の入力（`[position, dim]`）で、この位置の入力が追加される。
+    /// 後ろの位置を参照しないため、因果マスクを使って系列全体を処理した場合と同じ結果になる。
+    ///
+    /// 出力と、`memory`に対する各ヘッドの注意の重み（`[n_heads, memory_len]`）を返す。
+    pub(crate) fn forward_step(
+        &self,
+        input: &ndarray::ArrayView1<f32>,
+        cache: &mut ndarray::Array2<f32>,
+        memory: &ndarray::ArrayView2<f32>,
+    ) -> (ndarray::Array1<f32>, ndarray::Array2<f32>) {
+        cache.push_row(input.view()).unwrap();
+        let query = input.insert_axis(Axis(0));
+        let (x, _) = self.self_attn.forward(&query, &cache.view(), &cache.view());
+        let x = self.norm1.forward_1d(&(input + &x.row(0)).view());
+        let (y, weights) = self
+            .cross_attn
+            .forward(&x.view().insert_axis(Axis(0)), memory, memory);
+        let x = self.norm2.forward_1d(&(&x + &y.row(0)).view());
+        let y = self.linear2.forward_1d(
+            &self
+                .linear1
+                .forward_1d(&x.view())
+                .mapv(|v| v.max(0.0))
+                .view(),
+        );
+        let x = self.norm3.forward_1d(&(&x + &y).view());
+        (x, weights.index_axis_move(Axis(1), 0))
+    }
+}
+
+/// 正弦波による位置エンコーディング。
+///
+/// `pe[2i] = sin(position / 10000^(2i / dim))`、`pe[2i + 1] = cos(position / 10000^(2i / dim))`。
+pub(crate) fn sinusoidal_position_encoding(position: usize, dim: usize) -> ndarray::Array1<f32> {
+    ndarray::Array1::from_shape_fn(dim, |i| {
+        let angle = position as f32 / 10000f32.powf((i - i % 2) as f32 / dim as f32);
+        if i % 2 == 0 {
+            angle.sin()
+        } else {
+            angle.cos()
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,6 +750,55 @@ mod tests {
         assert_eq!(output, array![[28.0, 59.0], [34.0, 73.0]]);
     }
 
+    #[test]
+    fn test_linear_quantize() {
+        let weight = weight(5, 7, 0.3);
+        let input = Array2::from_shape_fn((2, 7), |(i, j)| (i as f32 * 1.3 + j as f32 * 0.7).sin());
+        let expected = Linear::new(weight.clone(), bias(5, 0.3)).forward_2d(&input.view());
+
+        let mut linear = Linear::new(
+            weight.mapv(|x| half::f16::from_f32(x).to_f32()),
+            bias(5, 0.3),
+        );
+        let expected_f16 = linear.forward_2d(&input.view());
+        linear.quantize(Precision::F16);
+        assert_close(&linear.forward_2d(&input.view()).view(), &expected_f16);
+
+        // 量子化の誤差は各重みでスケールの半分まで。
+        let mut linear = Linear::new(weight.clone(), bias(5, 0.3));
+        linear.quantize(Precision::Int8);
+        assert_eq!((linear.out_features(), linear.in_features()), (5, 7));
+        let output = linear.forward_2d(&input.view());
+        for ((i, j), &actual) in output.indexed_iter() {
+            let scale = weight.row(j).fold(0f32, |max, x| max.max(x.abs())) / 127.0;
+            let bound = input.row(i).mapv(f32::abs).sum() * scale / 2.0;
+            assert!((actual - expected[[i, j]]).abs() <= bound + 1e-6);
+        }
+        assert_eq!(output.row(1), linear.forward_1d(&input.row(1)));
+    }
+
+    #[test]
+    fn test_linear_shared_f16() {
+        let weight = weight(3, 4, 0.3).mapv(half::f16::from_f32);
+        // 先頭の1バイトはアラインメントを確かめるためのもの。
+        let mut data = vec![0u8; 2];
+        data.extend(weight.iter().flat_map(|x| x.to_le_bytes()));
+        let data = SharedBytes(std::sync::Arc::new(data));
+        let input = array![0.1, -0.2, 0.3, 0.4];
+
+        let shared = Linear::shared_f16(data.clone(), 2, (3, 4), bias(3, 0.3)).unwrap();
+        let mut owned = Linear::new(weight.mapv(half::f16::to_f32), bias(3, 0.3));
+        owned.quantize(Precision::F16);
+        assert_eq!(
+            shared.forward_1d(&input.view()),
+            owned.forward_1d(&input.view())
+        );
+        assert_eq!((shared.out_features(), shared.in_features()), (3, 4));
+
+        assert!(Linear::shared_f16(data.clone(), 1, (3, 4), bias(3, 0.3)).is_none());
+        assert!(Linear::shared_f16(data, 4, (3, 4), bias(3, 0.3)).is_none());
+    }
+
     #[test]
     fn test_embedding() {
         let embedding = Embedding::new(array![[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
@@ -288,6 +816,415 @@ mod tests {
         assert_eq!(split[1], array![[7, 8, 9], [10, 11, 12]]);
     }
 
+    #[test]
+    fn test_mha_attention_weights() {
+        let mha = mha(
+            Array2::from_shape_fn((12, 4), |(i, j)| (i * 4 + j) as f32 * 0.1),
+            Array1::zeros(12),
+            Array2::eye(4),
+            Array1::zeros(4),
+            2,
+        );
+        let query = array![[1.0, 0.0, 0.0, 1.0]];
+        let key = array![
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0]
+        ];
+        let (output, weights) = mha.forward(&query.view(), &key.view(), &key.view());
+        assert_eq!(output.shape(), &[1, 4]);
+        assert_eq!(weights.shape(), &[2, 1, 3]);
+        for sum in weights.sum_axis(Axis(2)) {
+            assert!((sum - 1.0).abs() < 1e-6);
+        }
+    }
+
+    fn weight(rows: usize, cols: usize, offset: f32) -> Array2<f32> {
+        Array2::from_shape_fn((rows, cols), |(i, j)| {
+            ((i * cols + j) as f32 * 0.37 + offset).sin() * 0.5
+        })
+    }
+
+    fn bias(len: usize, offset: f32) -> Array1<f32> {
+        Array1::from_shape_fn(len, |i| (i as f32 + offset).cos() * 0.1)
+    }
+
+    /// PyTorchの`nn.MultiheadAttention`と同じく、まとめた射影の重みから生成する。
+    fn mha(
+        in_proj_weight: Array2<f32>,
+        in_proj_bias: Array1<f32>,
+        out_proj_weight: Array2<f32>,
+        out_proj_bias: Array1<f32>,
+        num_heads: usize,
+    ) -> Mha {
+        let dim = in_proj_weight.ncols();
+        let proj = |i: usize| {
+            Linear::new(
+                in_proj_weight
+                    .slice(s![i * dim..(i + 1) * dim, ..])
+                    .to_owned(),
+                in_proj_bias.slice(s![i * dim..(i + 1) * dim]).to_owned(),
+            )
+        };
+        Mha::new(
+            proj(0),
+            proj(1),
+            proj(2),
+            Linear::new(out_proj_weight, out_proj_bias),
+            num_heads,
+        )
+    }
+
+    fn gru_cell(input: usize, hidden: usize, offset: f32) -> GruCell {
+        GruCell::new(
+            Linear::new(weight(3 * hidden, input, offset), bias(3 * hidden, offset)),
+            Linear::new(weight(3 * hidden, hidden, offset), bias(3 * hidden, offset)),
+        )
+    }
+
+    fn assert_close(actual: &ArrayView2<f32>, expected: &Array2<f32>) {
+        assert_eq!(actual.shape(), expected.shape());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{actual} != {expected}");
+        }
+    }
+
+    // 以下の参照値は、PyTorchのドキュメントにある各層の定義式をそのまま倍精度で計算したもの。
+    // 入力は`sin(i * 1.3 + j * 0.7)`と`cos(i * 0.9 + j * 0.4)`。
+    // PyTorch自体の出力との比較は`test_torch_reference`で行う。
+
+    #[test]
+    fn test_lstm() {
+        let input = Array2::from_shape_fn((3, 2), |(i, j)| (i as f32 * 1.3 + j as f32 * 0.7).sin());
+        let cell = || {
+            LstmCell::new(
+                Linear::new(weight(12, 2, 0.0), bias(12, 0.0)),
+                Linear::new(weight(12, 3, 0.5), bias(12, 0.5)),
+            )
+        };
+
+        let (output, (h, c)) = Lstm::new(cell(), false).forward(&input.view(), None);
+        assert_close(
+            &output.view(),
+            &array![
+                [-0.036763, -0.033699, -0.022779],
+                [-0.230549, -0.273464, -0.118587],
+                [-0.18148, -0.195043, -0.068623]
+            ],
+        );
+        assert_eq!(h, output.row(2));
+        assert_close(
+            &c.view().insert_axis(Axis(0)),
+            &array![[-0.352456, -0.369655, -0.131213]],
+        );
+
+        let (output, _) = Lstm::new(cell(), true).forward(&input.view(), None);
+        assert_close(
+            &output.view(),
+            &array![
+                [-0.216756, -0.208477, -0.043775],
+                [-0.20126, -0.260641, -0.127432],
+                [0.005973, -0.018349, -0.047385]
+            ],
+        );
+    }
+
+    #[test]
+    fn test_layer_norm() {
+        let layer_norm = LayerNorm::new(array![1.0, 0.5, 2.0, 1.0], array![0.0, 0.1, 0.0, -0.1]);
+        let output = layer_norm.forward_1d(&array![1.0, 2.0, 3.0, 4.0].view());
+        assert_close(
+            &output.view().insert_axis(Axis(0)),
+            &array![[-1.341635, -0.123606, 0.894424, 1.241635]],
+        );
+    }
+
+    #[test]
+    fn test_mha() {
+        let mha = mha(
+            weight(12, 4, 0.1),
+            bias(12, 0.2),
+            weight(4, 4, 0.3),
+            bias(4, 0.4),
+            2,
+        );
+        let query = Array2::from_shape_fn((2, 4), |(i, j)| (i as f32 * 1.3 + j as f32 * 0.7).sin());
+        let key = Array2::from_shape_fn((3, 4), |(i, j)| (i as f32 * 0.9 + j as f32 * 0.4).cos());
+        let (output, weights) = mha.forward(&query.view(), &key.view(), &key.view());
+        assert_close(
+            &output.view(),
+            &array![
+                [0.066105, 0.182103, -0.017798, -0.251642],
+                [0.070015, 0.173037, -0.023351, -0.243582]
+            ],
+        );
+        assert_close(
+            &weights.index_axis(Axis(0), 0),
+            &array![
+                [0.691762, 0.227848, 0.08039],
+                [0.674169, 0.242296, 0.083535]
+            ],
+        );
+        assert_close(
+            &weights.index_axis(Axis(0), 1),
+            &array![
+                [0.696109, 0.225632, 0.078258],
+                [0.676439, 0.241321, 0.08224]
+            ],
+        );
+    }
+
+    #[test]
+    fn test_transformer_decoder_layer() {
+        let layer = TransformerDecoderLayer::new(
+            mha(
+                weight(12, 4, 0.1),
+                bias(12, 0.2),
+                weight(4, 4, 0.3),
+                bias(4, 0.4),
+                2,
+            ),
+            mha(
+                weight(12, 4, 0.5),
+                bias(12, 0.6),
+                weight(4, 4, 0.7),
+                bias(4, 0.8),
+                2,
+            ),
+            Linear::new(weight(8, 4, 0.9), bias(8, 1.0)),
+            Linear::new(weight(4, 8, 1.1), bias(4, 1.2)),
+            [
+                LayerNorm::new(
+                    Array1::from_shape_fn(4, |i| 1.0 + 0.1 * i as f32),
+                    bias(4, 1.3),
+                ),
+                LayerNorm::new(
+                    Array1::from_shape_fn(4, |i| 1.0 - 0.1 * i as f32),
+                    bias(4, 1.4),
+                ),
+                LayerNorm::new(Array1::ones(4), bias(4, 1.5)),
+            ],
+        );
+        let target =
+            Array2::from_shape_fn((3, 4), |(i, j)| (i as f32 * 1.3 + j as f32 * 0.7).sin());
+        let memory =
+            Array2::from_shape_fn((2, 4), |(i, j)| (i as f32 * 0.9 + j as f32 * 0.4).cos());
+
+        // 1位置ずつ処理した結果が、因果マスクを使って系列全体を処理した参照値と一致する。
+        let mut cache = Array2::zeros((0, 4));
+        let (outputs, weights): (Vec<_>, Vec<_>) = target
+            .outer_iter()
+            .map(|x| layer.forward_step(&x, &mut cache, &memory.view()))
+            .unzip();
+        assert_eq!(cache, target);
+        let outputs =
+            ndarray::stack(Axis(0), &outputs.iter().map(|o| o.view()).collect_vec()).unwrap();
+        assert_close(
+            &outputs.view(),
+            &array![
+                [-1.23318, 0.799475, 0.987517, -0.741578],
+                [1.009288, 0.276343, 0.213293, -1.686689],
+                [1.567805, -0.248707, -0.257437, -1.249428]
+            ],
+        );
+        let weights =
+            ndarray::stack(Axis(1), &weights.iter().map(|w| w.view()).collect_vec()).unwrap();
+        assert_close(
+            &weights.index_axis(Axis(0), 0),
+            &array![
+                [0.527067, 0.472933],
+                [0.520394, 0.479606],
+                [0.490106, 0.509894]
+            ],
+        );
+        assert_close(
+            &weights.index_axis(Axis(0), 1),
+            &array![
+                [0.54470, 0.45530],
+                [0.529847, 0.470153],
+                [0.499295, 0.500705]
+            ],
+        );
+    }
+
+    /// `train/src/dump_layers.py`で書き出した、PyTorchの層の重みと出力を読み込む。
+    fn torch_reference() -> std::collections::HashMap<String, ArrayD<f32>> {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/torch_layers.safetensors"
+        );
+        let data = std::fs::read(path).unwrap_or_else(|e| {
+            panic!("failed to read {path}: {e}; run train/src/dump_layers.py to create it")
+        });
+        let tensors = safetensors::SafeTensors::deserialize(&data).unwrap();
+        ndarray_safetensors::parse_tensors::<f32>(&tensors)
+            .unwrap()
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_torch_reference() {
+        let reference = torch_reference();
+        let get = |name: &str| reference[name].clone();
+        let get1 = |name: &str| get(name).into_dimensionality::<Ix1>().unwrap();
+        let get2 = |name: &str| get(name).into_dimensionality::<Ix2>().unwrap();
+
+        // nn.LSTM（双方向）
+        let lstm = |suffix: &str, reverse: bool| {
+            Lstm::new(
+                LstmCell::new(
+                    Linear::new(
+                        get2(&format!("lstm.weight_ih_l0{suffix}")),
+                        get1(&format!("lstm.bias_ih_l0{suffix}")),
+                    ),
+                    Linear::new(
+                        get2(&format!("lstm.weight_hh_l0{suffix}")),
+                        get1(&format!("lstm.bias_hh_l0{suffix}")),
+                    ),
+                ),
+                reverse,
+            )
+        };
+        let input = get2("lstm.input");
+        let (forward, (h, c)) = lstm("", false).forward(&input.view(), None);
+        let (reverse, (h_reverse, c_reverse)) = lstm("_reverse", true).forward(&input.view(), None);
+        assert_close(
+            &ndarray::concatenate(Axis(1), &[forward.view(), reverse.view()])
+                .unwrap()
+                .view(),
+            &get2("lstm.output"),
+        );
+        assert_close(
+            &ndarray::stack(Axis(0), &[h.view(), h_reverse.view()])
+                .unwrap()
+                .view(),
+            &get2("lstm.h_n"),
+        );
+        assert_close(
+            &ndarray::stack(Axis(0), &[c.view(), c_reverse.view()])
+                .unwrap()
+                .view(),
+            &get2("lstm.c_n"),
+        );
+
+        // nn.LayerNorm
+        let layer_norm = LayerNorm::new(get1("layer_norm.weight"), get1("layer_norm.bias"));
+        let outputs = get2("layer_norm.input")
+            .outer_iter()
+            .map(|x| layer_norm.forward_1d(&x))
+            .collect_vec();
+        assert_close(
+            &ndarray::stack(Axis(0), &outputs.iter().map(|o| o.view()).collect_vec())
+                .unwrap()
+                .view(),
+            &get2("layer_norm.output"),
+        );
+
+        // nn.TransformerDecoderLayer（因果マスクあり）
+        let attention = |name: &str| {
+            mha(
+                get2(&format!("decoder_layer.{name}.in_proj_weight")),
+                get1(&format!("decoder_layer.{name}.in_proj_bias")),
+                get2(&format!("decoder_layer.{name}.out_proj.weight")),
+                get1(&format!("decoder_layer.{name}.out_proj.bias")),
+                2,
+            )
+        };
+        let linear = |name: &str| {
+            Linear::new(
+                get2(&format!("decoder_layer.{name}.weight")),
+                get1(&format!("decoder_layer.{name}.bias")),
+            )
+        };
+        let norm = |name: &str| {
+            LayerNorm::new(
+                get1(&format!("decoder_layer.{name}.weight")),
+                get1(&format!("decoder_layer.{name}.bias")),
+            )
+        };
+        let layer = TransformerDecoderLayer::new(
+            attention("self_attn"),
+            attention("multihead_attn"),
+            linear("linear1"),
+            linear("linear2"),
+            [norm("norm1"), norm("norm2"), norm("norm3")],
+        );
+        let target = get2("decoder_layer.target");
+        let memory = get2("decoder_layer.memory");
+        let mut cache = Array2::zeros((0, target.ncols()));
+        let outputs = target
+            .outer_iter()
+            .map(|x| layer.forward_step(&x, &mut cache, &memory.view()).0)
+            .collect_vec();
+        assert_close(
+            &ndarray::stack(Axis(0), &outputs.iter().map(|o| o.view()).collect_vec())
+                .unwrap()
+                .view(),
+            &get2("decoder_layer.output"),
+        );
+    }
+
+    #[test]
+    fn test_sinusoidal_position_encoding() {
+        let encoding = sinusoidal_position_encoding(1, 4);
+        assert_close(
+            &encoding.view().insert_axis(Axis(0)),
+            &array![[1f32.sin(), 1f32.cos(), 0.01f32.sin(), 0.01f32.cos()]],
+        );
+        assert_eq!(
+            sinusoidal_position_encoding(0, 4),
+            array![0.0, 1.0, 0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_stacked_gru() {
+        let input = Array2::from_shape_fn((5, 3), |(i, j)| (i as f32 - j as f32) * 0.2);
+
+        let stacked = StackedGru::new(vec![
+            (Gru::new(gru_cell(3, 4, 0.0), false), None),
+            (Gru::new(gru_cell(4, 4, 1.0), false), None),
+        ]);
+        let (output, hidden) = stacked.forward(&input.view(), None);
+        let (first, first_hidden) =
+            Gru::new(gru_cell(3, 4, 0.0), false).forward(&input.view(), None);
+        let (second, second_hidden) =
+            Gru::new(gru_cell(4, 4, 1.0), false).forward(&first.view(), None);
+        assert_eq!(output, second);
+        assert_eq!(hidden, vec![first_hidden, second_hidden]);
+
+        let (_, next_hidden) = stacked.forward(&input.view(), Some(&hidden));
+        assert_ne!(next_hidden, hidden);
+    }
+
+    #[test]
+    fn test_stacked_gru_bidirectional() {
+        let input = Array2::from_shape_fn((5, 3), |(i, j)| (i as f32 - j as f32) * 0.2);
+
+        let stacked = StackedGru::new(vec![
+            (
+                Gru::new(gru_cell(3, 4, 0.0), false),
+                Some(Gru::new(gru_cell(3, 4, 2.0), true)),
+            ),
+            (
+                Gru::new(gru_cell(8, 4, 1.0), false),
+                Some(Gru::new(gru_cell(8, 4, 3.0), true)),
+            ),
+        ]);
+        let (output, hidden) = stacked.forward(&input.view(), None);
+        assert_eq!(output.shape(), &[5, 8]);
+        assert_eq!(hidden.len(), 4);
+
+        let (forward, _) = Gru::new(gru_cell(3, 4, 0.0), false).forward(&input.view(), None);
+        let (reverse, _) = Gru::new(gru_cell(3, 4, 2.0), true).forward(&input.view(), None);
+        let first = ndarray::concatenate(Axis(1), &[forward.view(), reverse.view()]).unwrap();
+        let (forward, _) = Gru::new(gru_cell(8, 4, 1.0), false).forward(&first.view(), None);
+        assert_eq!(output.slice(s![.., ..4]), forward);
+        // 逆方向の最初の出力は、入力全体を逆から読んだ後の隠れ状態になる。
+        assert_eq!(output.slice(s![0, 4..]), hidden[3]);
+    }
+
     #[test]
     fn test_matmul_3d() {
         // In : import numpy as np
@@ -18,15 +18,65 @@
 //! brotliを使用してモデルを圧縮します。
 //! このfeatureはデフォルトで有効です。
 //!
+//! ### `cmudict`
+//! CMUdict形式の発音辞書を読み込む[CmuDict]と、辞書にある単語は[P2k]、
+//! 無い単語は[C2k]で変換する[CmuDictConverter]を有効にします。
+//!
+//! ### `mmap`
+//! safetensors形式のファイルをメモリマップして読み込む[C2k::from_file_mmap]、[P2k::from_file_mmap]、
+//! [BaseE2k::from_file_mmap]を有効にします。
+//! [Precision::F16]を指定すると重みをコピーせずに参照するため、複数のプロセスでページキャッシュを共有できます。
+//! 読み込んだ後にファイルが変更されると未定義動作になるため、これらは`unsafe`です。
+//! 組み込みのモデルを使う[C2k::new]には影響しません。
+//!
+//! ### `tokio`
+//! tokioのランタイムから推論するための[AsyncC2k]を有効にします。
+//! 推論をブロッキング用スレッドで行い、同時に届いた単語は同じブロッキングタスクで順に推論します。
+//!
+//! ### `serde`
+//! JSONなどから[Strategy]を読み込むための[StrategyConfig]を有効にします。
+//!
+//! ### `eval`
+//! 正解の読みとの編集距離やモーラ単位の正解率を計算する[EvalReport]と、
+//! 評価データを読み込む[EvalEntry::read_tsv]を有効にします。
+//!
 //! ### `getrandom_on_wasm32_unknown`
-//! wasm32-unknown-unknownでのTopK/TopPサンプリングに`getrandom`を使用します。
+//! wasm32-unknown-unknownでのTopK/TopPサンプリングのシード生成に`getrandom`を使用します。
 //! このfeatureを有効にしてコンパイルするには[getrandomのドキュメント](https://docs.rs/getrandom/latest/getrandom/#webassembly-support)を参照してください。
-//! オフの場合、Hashと適当な値を使用してサンプリングします。
+//! オフの場合、Hashと適当な値を使用してシードを生成します。
+//!
+//! シードを指定した場合（[StrategyTopK::seed]など）はこのfeatureに関係なく、
+//! どのプラットフォームでも同じ結果になります。
 //!
 
+#[cfg(feature = "tokio")]
+mod async_c2k;
+mod cache;
+#[cfg(feature = "cmudict")]
+mod cmudict;
 mod constants;
+mod error;
+#[cfg(feature = "eval")]
+mod eval;
 mod inference;
 mod layers;
+mod model_info;
+mod sampling;
+#[cfg(feature = "serde")]
+mod strategy_config;
+mod user_dict;
 
-pub use constants::{ASCII_ENTRIES, KANAS};
+#[cfg(feature = "tokio")]
+pub use async_c2k::*;
+pub use cache::*;
+#[cfg(feature = "cmudict")]
+pub use cmudict::*;
+pub use constants::{ASCII_ENTRIES, EN_PHONES, KANAS};
+pub use error::*;
+#[cfg(feature = "eval")]
+pub use eval::*;
 pub use inference::*;
+pub use model_info::*;
+#[cfg(feature = "serde")]
+pub use strategy_config::*;
+pub use user_dict::*;
@@ -0,0 +1,303 @@
+use crate::{constants, LoadError};
+
+/// モデルの情報。
+///
+/// safetensorsのメタデータ（`__metadata__`）の以下のキーから読み込みます。
+/// メタデータに無い値は組み込みの値になります。
+///
+/// | キー | 値 | 無い場合 |
+/// | --- | --- | --- |
+/// | `version` | 任意の文字列 | `None` |
+/// | `input_vocab` | 文字列のJSON配列 | 変換器ごとの組み込みの語彙 |
+/// | `output_vocab` | 文字列のJSON配列 | [KANAS](crate::KANAS) |
+/// | `n_heads` | 整数 | `4` |
+/// | `sos_idx` | 整数 | `1` |
+/// | `eos_idx` | 整数 | `2` |
+/// | `encoder_type` | `gru`か`lstm` | `gru` |
+/// | `decoder_type` | `gru`、`lstm`、`transformer`のいずれか | `gru` |
+/// | `position_encoding` | `none`か`sinusoidal` | `none` |
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// モデルのバージョン。
+    pub version: Option<String>,
+    /// 入力の語彙。位置がモデルの入力のインデックスになる。
+    pub input_vocab: Vec<String>,
+    /// 出力の語彙。位置がモデルの出力のインデックスになる。
+    pub output_vocab: Vec<String>,
+    /// 注意機構のヘッド数。
+    pub n_heads: usize,
+    /// SOSのインデックス。
+    pub sos_idx: usize,
+    /// EOSのインデックス。
+    pub eos_idx: usize,
+    /// エンコーダの層の種類。
+    pub encoder_type: LayerType,
+    /// デコーダの層の種類。
+    pub decoder_type: LayerType,
+    /// Transformerのデコーダの入力に加える位置エンコーディング。再帰層のデコーダでは使われません。
+    pub position_encoding: PositionEncoding,
+}
+
+/// モデルの層の種類。
+///
+/// 種類ごとに必要な重みについては[BaseE2k](crate::BaseE2k)を参照してください。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LayerType {
+    /// GRU。
+    #[default]
+    Gru,
+    /// LSTM。
+    Lstm,
+    /// Transformer。デコーダのみ対応しています。
+    Transformer,
+}
+
+/// 位置エンコーディングの種類。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// 位置エンコーディングを加えない。
+    #[default]
+    None,
+    /// 正弦波による位置エンコーディング（`pe[2i] = sin(pos / 10000^(2i / dim))`、`pe[2i + 1] = cos(...)`）。
+    Sinusoidal,
+}
+
+impl PositionEncoding {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "none" => Some(PositionEncoding::None),
+            "sinusoidal" => Some(PositionEncoding::Sinusoidal),
+            _ => None,
+        }
+    }
+}
+
+impl LayerType {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "gru" => Some(LayerType::Gru),
+            "lstm" => Some(LayerType::Lstm),
+            "transformer" => Some(LayerType::Transformer),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ModelInfo {
+    /// 組み込みの[C2k](crate::C2k)のモデルの情報（バージョンを除く）。
+    fn default() -> Self {
+        Self {
+            version: None,
+            input_vocab: to_vec(constants::ASCII_ENTRIES),
+            output_vocab: to_vec(constants::KANAS),
+            n_heads: 4,
+            sos_idx: constants::SOS_IDX,
+            eos_idx: constants::EOS_IDX,
+            encoder_type: LayerType::Gru,
+            decoder_type: LayerType::Gru,
+            position_encoding: PositionEncoding::None,
+        }
+    }
+}
+
+impl ModelInfo {
+    /// safetensors形式のバイト列のメタデータから読み込む。
+    ///
+    /// `default_input_vocab`が`None`の場合、`input_vocab`と`output_vocab`は必須になる。
+    pub(crate) fn read(
+        data: &[u8],
+        default_input_vocab: Option<&[&str]>,
+    ) -> Result<Self, LoadError> {
+        let (_, metadata) = safetensors::SafeTensors::read_metadata(data)?;
+        let metadata = metadata.metadata().as_ref();
+        let get = |key: &str| metadata.and_then(|metadata| metadata.get(key));
+
+        let (input_vocab, output_vocab) = match default_input_vocab {
+            Some(default_input_vocab) => (
+                read_vocab(get("input_vocab"), "input_vocab")?
+                    .unwrap_or_else(|| to_vec(default_input_vocab)),
+                read_vocab(get("output_vocab"), "output_vocab")?
+                    .unwrap_or_else(|| to_vec(constants::KANAS)),
+            ),
+            None => (
+                read_vocab(get("input_vocab"), "input_vocab")?
+                    .ok_or_else(|| missing("input_vocab"))?,
+                read_vocab(get("output_vocab"), "output_vocab")?
+                    .ok_or_else(|| missing("output_vocab"))?,
+            ),
+        };
+        let defaults = Self::default();
+        let info = Self {
+            version: get("version").cloned(),
+            input_vocab,
+            output_vocab,
+            n_heads: read_usize(get("n_heads"), "n_heads")?.unwrap_or(defaults.n_heads),
+            sos_idx: read_usize(get("sos_idx"), "sos_idx")?.unwrap_or(defaults.sos_idx),
+            eos_idx: read_usize(get("eos_idx"), "eos_idx")?.unwrap_or(defaults.eos_idx),
+            encoder_type: read_layer_type(get("encoder_type"), "encoder_type")?
+                .unwrap_or(defaults.encoder_type),
+            decoder_type: read_layer_type(get("decoder_type"), "decoder_type")?
+                .unwrap_or(defaults.decoder_type),
+            position_encoding: read_position_encoding(get("position_encoding"))?
+                .unwrap_or(defaults.position_encoding),
+        };
+        info.validate()?;
+        Ok(info)
+    }
+
+    fn validate(&self) -> Result<(), LoadError> {
+        if self.n_heads == 0 {
+            return Err(invalid("n_heads", "must be greater than 0".to_string()));
+        }
+        if self.encoder_type == LayerType::Transformer {
+            return Err(invalid(
+                "encoder_type",
+                "transformer is not supported for the encoder".to_string(),
+            ));
+        }
+        if self.output_vocab.iter().any(|v| v.is_empty()) {
+            return Err(invalid(
+                "output_vocab",
+                "must not contain an empty string".to_string(),
+            ));
+        }
+        for (key, idx) in [("sos_idx", self.sos_idx), ("eos_idx", self.eos_idx)] {
+            if idx >= self.input_vocab.len() || idx >= self.output_vocab.len() {
+                return Err(invalid(key, format!("{idx} is out of the vocabulary")));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_vec(vocab: &[&str]) -> Vec<String> {
+    vocab.iter().map(|v| v.to_string()).collect()
+}
+
+/// JSON配列の語彙を読み込む。
+fn read_vocab(value: Option<&String>, key: &str) -> Result<Option<Vec<String>>, LoadError> {
+    value
+        .map(|value| serde_json::from_str(value).map_err(|e| invalid(key, e.to_string())))
+        .transpose()
+}
+
+fn read_usize(value: Option<&String>, key: &str) -> Result<Option<usize>, LoadError> {
+    value
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|e| invalid(key, format!("{e}")))
+        })
+        .transpose()
+}
+
+fn read_layer_type(value: Option<&String>, key: &str) -> Result<Option<LayerType>, LoadError> {
+    value
+        .map(|value| {
+            LayerType::parse(value)
+                .ok_or_else(|| invalid(key, format!("unknown layer type `{value}`")))
+        })
+        .transpose()
+}
+
+fn read_position_encoding(value: Option<&String>) -> Result<Option<PositionEncoding>, LoadError> {
+    value
+        .map(|value| {
+            PositionEncoding::parse(value).ok_or_else(|| {
+                invalid(
+                    "position_encoding",
+                    format!("unknown position encoding `{value}`"),
+                )
+            })
+        })
+        .transpose()
+}
+
+fn missing(key: &str) -> LoadError {
+    LoadError::MissingMetadata {
+        key: key.to_string(),
+    }
+}
+
+fn invalid(key: &str, reason: String) -> LoadError {
+    LoadError::InvalidMetadata {
+        key: key.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serialize(metadata: &[(&str, &str)]) -> Vec<u8> {
+        let metadata = metadata
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<std::collections::HashMap<_, _>>();
+        let tensors: Vec<(&str, safetensors::tensor::TensorView)> = Vec::new();
+        safetensors::serialize(tensors, &Some(metadata).filter(|m| !m.is_empty())).unwrap()
+    }
+
+    #[test]
+    fn test_read_defaults() {
+        let data = serialize(&[]);
+        let info = ModelInfo::read(&data, Some(constants::EN_PHONES)).unwrap();
+        assert_eq!(info.input_vocab, to_vec(constants::EN_PHONES));
+        assert_eq!(info.output_vocab, to_vec(constants::KANAS));
+        assert_eq!(info.n_heads, 4);
+        assert_eq!(info.version, None);
+
+        assert!(matches!(
+            ModelInfo::read(&data, None),
+            Err(LoadError::MissingMetadata { key }) if key == "input_vocab"
+        ));
+    }
+
+    #[test]
+    fn test_read_metadata() {
+        let data = serialize(&[
+            ("version", "v2"),
+            ("input_vocab", r#"["<pad>", "<eos>", "<sos>", "a"]"#),
+            ("output_vocab", r#"["<pad>", "<eos>", "<sos>", "ア"]"#),
+            ("n_heads", "2"),
+            ("sos_idx", "2"),
+            ("eos_idx", "1"),
+            ("encoder_type", "lstm"),
+            ("decoder_type", "transformer"),
+            ("position_encoding", "sinusoidal"),
+        ]);
+        let info = ModelInfo::read(&data, None).unwrap();
+        assert_eq!(info.version.as_deref(), Some("v2"));
+        assert_eq!(info.input_vocab.len(), 4);
+        assert_eq!(info.output_vocab[3], "ア");
+        assert_eq!((info.n_heads, info.sos_idx, info.eos_idx), (2, 2, 1));
+        assert_eq!(info.encoder_type, LayerType::Lstm);
+        assert_eq!(info.decoder_type, LayerType::Transformer);
+        assert_eq!(info.position_encoding, PositionEncoding::Sinusoidal);
+    }
+
+    #[test]
+    fn test_read_invalid_metadata() {
+        for (key, value) in [
+            ("n_heads", "four"),
+            ("n_heads", "0"),
+            ("eos_idx", "100"),
+            ("input_vocab", "a, b"),
+            ("encoder_type", "transformer"),
+            ("decoder_type", "rnn"),
+            ("position_encoding", "learned"),
+            ("output_vocab", r#"["<pad>", "<sos>", "<eos>", ""]"#),
+        ] {
+            let data = serialize(&[(key, value)]);
+            assert!(
+                matches!(
+                    ModelInfo::read(&data, Some(constants::ASCII_ENTRIES)),
+                    Err(LoadError::InvalidMetadata { key: k, .. }) if k == key
+                ),
+                "{key}: {value}"
+            );
+        }
+    }
+}
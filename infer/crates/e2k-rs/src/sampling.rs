@@ -0,0 +1,293 @@
+use ndarray::prelude::*;
+
+/// 温度付きのsoftmax。
+///
+/// オーバーフローを防ぐため、最大値を引いてから`exp`を計算する。
+pub(crate) fn softmax(logits: &ArrayView1<f32>, temperature: f32) -> Array1<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp = logits.mapv(|x| ((x - max) / temperature).exp());
+    let sum = exp.sum();
+    exp / sum
+}
+
+/// 対数softmax。
+pub(crate) fn log_softmax(logits: &ArrayView1<f32>) -> Array1<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let log_sum = logits.mapv(|x| (x - max).exp()).sum().ln();
+    logits.mapv(|x| x - max - log_sum)
+}
+
+pub(crate) fn argmax(logits: &ArrayView1<f32>) -> usize {
+    logits
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(i, _)| i)
+        .expect("logits should not be empty")
+}
+
+/// ロジットの大きい順に並べたインデックスを返す。
+fn sorted_indices(logits: &ArrayView1<f32>) -> Vec<usize> {
+    let mut indices = (0..logits.len()).collect::<Vec<_>>();
+    indices.sort_unstable_by(|&i, &j| logits[j].partial_cmp(&logits[i]).unwrap());
+    indices
+}
+
+/// `candidates`の中から、`weights`に比例した確率で1つ選ぶ。
+/// `weights`は正規化されていなくてもよい。
+fn sample_weighted<R: rand::Rng + ?Sized>(
+    candidates: &[usize],
+    weights: &[f32],
+    rng: &mut R,
+) -> usize {
+    let total = weights.iter().sum::<f32>();
+    let mut threshold = rng.random::<f32>() * total;
+    for (&candidate, &weight) in candidates.iter().zip(weights) {
+        if threshold < weight {
+            return candidate;
+        }
+        threshold -= weight;
+    }
+    // 浮動小数点の誤差で最後まで到達した場合。
+    *candidates.last().expect("candidates should not be empty")
+}
+
+/// 候補のロジットに温度付きsoftmaxを適用し、その分布からサンプリングする。
+fn sample_candidates<R: rand::Rng + ?Sized>(
+    logits: &ArrayView1<f32>,
+    candidates: &[usize],
+    temperature: f32,
+    rng: &mut R,
+) -> usize {
+    let candidate_logits = Array1::from_iter(candidates.iter().map(|&i| logits[i]));
+    let probs = softmax(&candidate_logits.view(), temperature);
+    sample_weighted(candidates, probs.as_slice().unwrap(), rng)
+}
+
+// 以下のサンプリング関数のパラメータは、Strategy::validateで検証済みであること。
+
+pub(crate) fn top_k<R: rand::Rng + ?Sized>(
+    logits: &ArrayView1<f32>,
+    k: usize,
+    temperature: f32,
+    rng: &mut R,
+) -> usize {
+    let mut indices = sorted_indices(logits);
+    indices.truncate(k);
+
+    sample_candidates(logits, &indices, temperature, rng)
+}
+
+pub(crate) fn top_p<R: rand::Rng + ?Sized>(
+    logits: &ArrayView1<f32>,
+    top_p: f32,
+    temperature: f32,
+    rng: &mut R,
+) -> usize {
+    let indices = sorted_indices(logits);
+    let probs = softmax(logits, temperature);
+    let mut i = 0;
+    let mut cumsum = 0.0;
+    while i < indices.len() && (i == 0 || cumsum < top_p) {
+        cumsum += probs[indices[i]];
+        i += 1;
+    }
+    let candidates = &indices[..i];
+    let weights = candidates.iter().map(|&i| probs[i]).collect::<Vec<_>>();
+
+    sample_weighted(candidates, &weights, rng)
+}
+
+pub(crate) fn temperature<R: rand::Rng + ?Sized>(
+    logits: &ArrayView1<f32>,
+    temperature: f32,
+    rng: &mut R,
+) -> usize {
+    let probs = softmax(logits, temperature);
+    let candidates = (0..probs.len()).collect::<Vec<_>>();
+
+    sample_weighted(&candidates, probs.as_slice().unwrap(), rng)
+}
+
+pub(crate) fn min_p<R: rand::Rng + ?Sized>(
+    logits: &ArrayView1<f32>,
+    min_p: f32,
+    temperature: f32,
+    rng: &mut R,
+) -> usize {
+    let probs = softmax(logits, temperature);
+    let threshold = min_p * probs.iter().copied().fold(0.0, f32::max);
+    let (candidates, weights): (Vec<_>, Vec<_>) = probs
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|&(_, p)| p >= threshold)
+        .unzip();
+
+    sample_weighted(&candidates, &weights, rng)
+}
+
+/// Locally Typical Sampling（<https://arxiv.org/abs/2202.00666>）。
+pub(crate) fn typical<R: rand::Rng + ?Sized>(
+    logits: &ArrayView1<f32>,
+    typical_p: f32,
+    temperature: f32,
+    rng: &mut R,
+) -> usize {
+    let probs = softmax(logits, temperature);
+    let log_probs = probs.mapv(f32::ln);
+    let entropy = -probs
+        .iter()
+        .zip(&log_probs)
+        .filter(|(&p, _)| p > 0.0)
+        .map(|(p, log_p)| p * log_p)
+        .sum::<f32>();
+    // 情報量がエントロピーに近い順に並べる。
+    let mut indices = (0..probs.len()).collect::<Vec<_>>();
+    indices.sort_unstable_by(|&i, &j| {
+        let a = (-log_probs[i] - entropy).abs();
+        let b = (-log_probs[j] - entropy).abs();
+        a.partial_cmp(&b).unwrap()
+    });
+    let mut i = 0;
+    let mut cumsum = 0.0;
+    while i < indices.len() && (i == 0 || cumsum < typical_p) {
+        cumsum += probs[indices[i]];
+        i += 1;
+    }
+    let candidates = &indices[..i];
+    let weights = candidates.iter().map(|&i| probs[i]).collect::<Vec<_>>();
+
+    sample_weighted(candidates, &weights, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+    use rand::SeedableRng;
+
+    const NUM_SAMPLES: usize = 20000;
+
+    fn histogram(len: usize, mut sample: impl FnMut() -> usize) -> Vec<f32> {
+        let mut counts = vec![0; len];
+        for _ in 0..NUM_SAMPLES {
+            counts[sample()] += 1;
+        }
+        counts
+            .into_iter()
+            .map(|c| c as f32 / NUM_SAMPLES as f32)
+            .collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 0.015, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_softmax() {
+        // softmax([1, 2, 3] / 0.5) = exp([2, 4, 6]) / sum(exp([2, 4, 6]))
+        //                         = [0.0159, 0.1173, 0.8668]
+        let probs = softmax(&array![1.0, 2.0, 3.0].view(), 0.5);
+        assert_close(probs.as_slice().unwrap(), &[0.0159, 0.1173, 0.8668]);
+    }
+
+    #[test]
+    fn test_softmax_large_logits() {
+        let probs = softmax(&array![1000.0, 1000.0].view(), 1.0);
+        assert_eq!(probs, array![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let logits = array![1.0, 2.0, 3.0, f32::NEG_INFINITY];
+        let log_probs = log_softmax(&logits.view());
+        assert_close(
+            &log_probs.mapv(f32::exp).to_vec(),
+            softmax(&logits.view(), 1.0).as_slice().unwrap(),
+        );
+        assert_eq!(log_probs[3], f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_top_k_distribution() {
+        let logits = array![2.0f32.ln(), 1.0f32.ln(), 1.0f32.ln(), -100.0];
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(4, || top_k(&logits.view(), 3, 1.0, &mut rng));
+        assert_close(&freq, &[0.5, 0.25, 0.25, 0.0]);
+        assert_eq!(freq[3], 0.0);
+    }
+
+    #[test]
+    fn test_top_k_temperature() {
+        // 温度2で確率が 2:1 から sqrt(2):1 になる。
+        let logits = array![2.0f32.ln(), 1.0f32.ln(), -100.0];
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(3, || top_k(&logits.view(), 2, 2.0, &mut rng));
+        let sqrt2 = 2.0f32.sqrt();
+        assert_close(&freq, &[sqrt2 / (sqrt2 + 1.0), 1.0 / (sqrt2 + 1.0), 0.0]);
+    }
+
+    #[test]
+    fn test_top_p_distribution() {
+        // 確率は 0.5, 0.3, 0.15, 0.05 。
+        // 0.5 + 0.3 < 0.85 なので、上位3つが候補になる。
+        let logits = array![0.5f32, 0.3, 0.15, 0.05].mapv(f32::ln);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(4, || top_p(&logits.view(), 0.85, 1.0, &mut rng));
+        assert_close(&freq, &[0.5 / 0.95, 0.3 / 0.95, 0.15 / 0.95, 0.0]);
+        assert_eq!(freq[3], 0.0);
+    }
+
+    #[test]
+    fn test_top_p_keeps_at_least_one() {
+        let logits = array![0.5f32, 0.3, 0.2].mapv(f32::ln);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        for _ in 0..100 {
+            assert_eq!(top_p(&logits.view(), 0.0, 1.0, &mut rng), 0);
+        }
+    }
+
+    #[test]
+    fn test_temperature_distribution() {
+        let logits = array![0.5f32, 0.3, 0.2].mapv(f32::ln);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(3, || temperature(&logits.view(), 1.0, &mut rng));
+        assert_close(&freq, &[0.5, 0.3, 0.2]);
+    }
+
+    #[test]
+    fn test_min_p_distribution() {
+        // 最大の確率0.5の0.25倍（0.125）未満の候補は除外される。
+        let logits = array![0.5f32, 0.3, 0.15, 0.05].mapv(f32::ln);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(4, || min_p(&logits.view(), 0.25, 1.0, &mut rng));
+        assert_close(&freq, &[0.5 / 0.95, 0.3 / 0.95, 0.15 / 0.95, 0.0]);
+        assert_eq!(freq[3], 0.0);
+    }
+
+    #[test]
+    fn test_typical_distribution() {
+        // 確率は 0.7, 0.1, 0.1, 0.1 、エントロピーは約0.94。
+        // 情報量は 0.36, 2.30, 2.30, 2.30 なので、最も確率の高い候補が最初に選ばれ、
+        // 0.7 < 0.75 なので確率0.1の候補が1つ追加される。
+        let logits = array![0.7f32, 0.1, 0.1, 0.1].mapv(f32::ln);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(4, || typical(&logits.view(), 0.75, 1.0, &mut rng));
+        assert_close(&[freq[0]], &[0.7 / 0.8]);
+        assert_close(&[freq[1] + freq[2] + freq[3]], &[0.1 / 0.8]);
+    }
+
+    #[test]
+    fn test_typical_excludes_too_likely() {
+        // 確率は 0.4, 0.2, 0.2, 0.2 、エントロピーは約1.33。
+        // 情報量は 0.92, 1.61, 1.61, 1.61 なので、確率0.2の候補の方がエントロピーに近い。
+        let logits = array![0.4f32, 0.2, 0.2, 0.2].mapv(f32::ln);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let freq = histogram(4, || typical(&logits.view(), 0.5, 1.0, &mut rng));
+        assert_eq!(freq[0], 0.0);
+        assert_close(&freq, &[0.0, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0]);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    Strategy, StrategyError, StrategyMinP, StrategyTemperature, StrategyTopK, StrategyTopP,
+    StrategyTypical,
+};
+use serde::Deserialize;
+
+/// JSONなどから[Strategy]を読み込むための形式。
+///
+/// `type`でアルゴリズムを指定し、省略したパラメータは既定値になります。
+/// `top_p`、`min_p`、`typical_p`はそれぞれ`topP`、`minP`、`typicalP`とも書けます。
+///
+/// ```json
+/// {"type": "top_p", "top_p": 0.8, "temperature": 1.5, "seed": 42}
+/// ```
+///
+/// [Strategy]には[TryFrom]で変換し、その際に[Strategy::validate]で検証します。
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StrategyConfig {
+    Greedy,
+    TopK {
+        k: Option<usize>,
+        temperature: Option<f32>,
+        seed: Option<u64>,
+    },
+    TopP {
+        #[serde(alias = "topP")]
+        top_p: Option<f32>,
+        temperature: Option<f32>,
+        seed: Option<u64>,
+    },
+    MinP {
+        #[serde(alias = "minP")]
+        min_p: Option<f32>,
+        temperature: Option<f32>,
+        seed: Option<u64>,
+    },
+    Typical {
+        #[serde(alias = "typicalP")]
+        typical_p: Option<f32>,
+        temperature: Option<f32>,
+        seed: Option<u64>,
+    },
+    Temperature {
+        temperature: Option<f32>,
+        seed: Option<u64>,
+    },
+}
+
+impl TryFrom<StrategyConfig> for Strategy {
+    type Error = StrategyError;
+
+    fn try_from(config: StrategyConfig) -> Result<Self, Self::Error> {
+        let strategy = match config {
+            StrategyConfig::Greedy => Strategy::Greedy,
+            StrategyConfig::TopK {
+                k,
+                temperature,
+                seed,
+            } => {
+                let default = StrategyTopK::default();
+                Strategy::TopK(StrategyTopK {
+                    k: k.unwrap_or(default.k),
+                    temperature: temperature.unwrap_or(default.temperature),
+                    seed,
+                })
+            }
+            StrategyConfig::TopP {
+                top_p,
+                temperature,
+                seed,
+            } => {
+                let default = StrategyTopP::default();
+                Strategy::TopP(StrategyTopP {
+                    top_p: top_p.unwrap_or(default.top_p),
+                    temperature: temperature.unwrap_or(default.temperature),
+                    seed,
+                })
+            }
+            StrategyConfig::MinP {
+                min_p,
+                temperature,
+                seed,
+            } => {
+                let default = StrategyMinP::default();
+                Strategy::MinP(StrategyMinP {
+                    min_p: min_p.unwrap_or(default.min_p),
+                    temperature: temperature.unwrap_or(default.temperature),
+                    seed,
+                })
+            }
+            StrategyConfig::Typical {
+                typical_p,
+                temperature,
+                seed,
+            } => {
+                let default = StrategyTypical::default();
+                Strategy::Typical(StrategyTypical {
+                    typical_p: typical_p.unwrap_or(default.typical_p),
+                    temperature: temperature.unwrap_or(default.temperature),
+                    seed,
+                })
+            }
+            StrategyConfig::Temperature { temperature, seed } => {
+                let default = StrategyTemperature::default();
+                Strategy::Temperature(StrategyTemperature {
+                    temperature: temperature.unwrap_or(default.temperature),
+                    seed,
+                })
+            }
+        };
+        strategy.validate()?;
+        Ok(strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<Strategy, StrategyError> {
+        serde_json::from_str::<StrategyConfig>(json)
+            .unwrap()
+            .try_into()
+    }
+
+    #[test]
+    fn test_strategy_config() {
+        assert_eq!(parse(r#"{"type": "greedy"}"#), Ok(Strategy::Greedy));
+        assert_eq!(
+            parse(r#"{"type": "top_p", "topP": 0.8, "seed": 42}"#),
+            Ok(Strategy::TopP(StrategyTopP {
+                top_p: 0.8,
+                temperature: 1.0,
+                seed: Some(42),
+            }))
+        );
+        assert_eq!(
+            parse(r#"{"type": "top_k"}"#),
+            Ok(Strategy::TopK(StrategyTopK::default()))
+        );
+        assert_eq!(
+            parse(r#"{"type": "top_k", "k": 0}"#),
+            Err(StrategyError::InvalidK(0))
+        );
+        assert_eq!(
+            parse(r#"{"type": "min_p", "min_p": 0}"#),
+            Err(StrategyError::InvalidProbability {
+                name: "min_p",
+                value: 0.0
+            })
+        );
+        assert_eq!(
+            parse(r#"{"type": "temperature", "temperature": 0}"#),
+            Err(StrategyError::InvalidTemperature(0.0))
+        );
+        assert!(serde_json::from_str::<StrategyConfig>(r#"{"type": "top_k", "p": 0.5}"#).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::LineFileError;
+use std::collections::HashMap;
+
+/// 単語を推論に使う形に正規化する。
+///
+/// 全角英数字・記号を半角にし、`’`を`'`にし、英字を小文字にし、
+/// 連続する空白を1つの半角スペースにして前後の空白を取り除きます。
+pub fn normalize(word: &str) -> String {
+    let mut normalized = String::with_capacity(word.len());
+    for c in word.chars() {
+        let c = match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '’' | '‘' => '\'',
+            c => c,
+        };
+        if c.is_whitespace() {
+            if !normalized.is_empty() && !normalized.ends_with(' ') {
+                normalized.push(' ');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    if normalized.ends_with(' ') {
+        normalized.pop();
+    }
+    normalized
+}
+
+/// 単語の読みを登録するユーザー辞書。
+///
+/// 単語は[normalize]で正規化してから登録・検索します。
+/// ファイルは`単語<TAB>読み`の行からなり、空行と`#`で始まる行は無視します。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserDict {
+    entries: HashMap<String, String>,
+}
+
+impl UserDict {
+    /// 空の辞書を生成する。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 文字列から辞書を読み込む。
+    pub fn parse(text: &str) -> Result<Self, LineFileError> {
+        let mut dict = Self::default();
+        for (i, line) in text.lines().enumerate() {
+            dict.parse_line(i + 1, line)?;
+        }
+        Ok(dict)
+    }
+
+    /// [std::io::BufRead]から辞書を読み込む。
+    pub fn from_reader<R: std::io::BufRead>(reader: R) -> Result<Self, LineFileError> {
+        let mut dict = Self::default();
+        for (i, line) in reader.lines().enumerate() {
+            dict.parse_line(i + 1, &line?)?;
+        }
+        Ok(dict)
+    }
+
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), LineFileError> {
+        if line.trim().is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let Some((word, reading)) = line.split_once('\t') else {
+            return Err(LineFileError::InvalidLine { line: line_number });
+        };
+        let (word, reading) = (word.trim(), reading.trim());
+        if word.is_empty() || reading.is_empty() {
+            return Err(LineFileError::InvalidLine { line: line_number });
+        }
+        self.insert(word, reading);
+        Ok(())
+    }
+
+    /// 単語の読みを登録する。既に登録されている場合は以前の読みを返す。
+    pub fn insert(&mut self, word: &str, reading: &str) -> Option<String> {
+        self.entries.insert(normalize(word), reading.to_string())
+    }
+
+    /// 単語を削除する。登録されていた場合は読みを返す。
+    pub fn remove(&mut self, word: &str) -> Option<String> {
+        self.entries.remove(&normalize(word))
+    }
+
+    /// 単語の読みを返す。
+    pub fn get(&self, word: &str) -> Option<&str> {
+        self.entries.get(&normalize(word)).map(|v| v.as_str())
+    }
+
+    /// 登録されている単語の数。
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 単語が1つも登録されていないかどうか。
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 正規化された単語と読みの組を順不同で返す。
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Hello"), "hello");
+        assert_eq!(normalize("  New \t York  "), "new york");
+        assert_eq!(normalize("ＶＯＩＣＥＶＯＸ"), "voicevox");
+        assert_eq!(normalize("it’s"), "it's");
+        assert_eq!(normalize("カタカナ"), "カタカナ");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn test_parse() {
+        let dict = UserDict::parse(
+            "# comment
+VOICEVOX\tボイスボックス
+
+new york\tニューヨーク
+",
+        )
+        .unwrap();
+        assert_eq!(dict.len(), 2);
+        assert_eq!(dict.get("voicevox"), Some("ボイスボックス"));
+        assert_eq!(dict.get("ＶｏｉｃｅＶｏｘ"), Some("ボイスボックス"));
+        assert_eq!(dict.get("New  York"), Some("ニューヨーク"));
+        assert_eq!(dict.get("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_invalid_line() {
+        let error = UserDict::parse("word\tワード\nbroken\n").unwrap_err();
+        assert!(matches!(error, LineFileError::InvalidLine { line: 2 }));
+        let error = UserDict::parse("word\t\n").unwrap_err();
+        assert!(matches!(error, LineFileError::InvalidLine { line: 1 }));
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut dict = UserDict::new();
+        assert_eq!(dict.insert("Word", "ワード"), None);
+        assert_eq!(dict.insert("word", "ワアド"), Some("ワード".to_string()));
+        assert_eq!(dict.iter().collect::<Vec<_>>(), [("word", "ワアド")]);
+        assert_eq!(dict.remove("WORD"), Some("ワアド".to_string()));
+        assert!(dict.is_empty());
+    }
+}
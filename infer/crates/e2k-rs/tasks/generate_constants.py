@@ -17,6 +17,9 @@ pub const KANAS: &[&str] = &[
 pub const ASCII_ENTRIES: &[&str] = &[
 {", ".join([f'"{entry}"' for entry in constants.ascii_entries])}
 ];
+pub const EN_PHONES: &[&str] = &[
+{", ".join([f'"{phone}"' for phone in constants.en_phones])}
+];
 """
 
 formatted_content = subprocess.run(
@@ -0,0 +1,188 @@
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// f16のテンソル。
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<u8>,
+}
+
+impl Tensor {
+    pub fn view(&self) -> safetensors::tensor::TensorView<'_> {
+        safetensors::tensor::TensorView::new(
+            safetensors::Dtype::F16,
+            self.shape.clone(),
+            &self.data,
+        )
+        .unwrap()
+    }
+}
+
+/// テスト用に、ランダムな重みを持つモデルを生成する。
+///
+/// 既定ではGRUの1層・双方向のエンコーダと、GRUのデコーダを持つモデルを生成する。
+pub struct ModelBuilder {
+    in_vocab: usize,
+    out_vocab: usize,
+    dim: usize,
+    num_layers: usize,
+    bidirectional: bool,
+    encoder_type: String,
+    decoder_type: String,
+    metadata: HashMap<String, String>,
+    extra_tensors: Vec<(String, Vec<usize>)>,
+}
+
+impl ModelBuilder {
+    /// # Arguments
+    ///
+    /// - `in_vocab`: 入力の語彙数。
+    /// - `out_vocab`: 出力の語彙数。
+    /// - `dim`: モデルの次元数。
+    pub fn new(in_vocab: usize, out_vocab: usize, dim: usize) -> Self {
+        Self {
+            in_vocab,
+            out_vocab,
+            dim,
+            num_layers: 1,
+            bidirectional: true,
+            encoder_type: "gru".to_string(),
+            decoder_type: "gru".to_string(),
+            metadata: HashMap::new(),
+            extra_tensors: Vec::new(),
+        }
+    }
+
+    /// メタデータを追加する。メタデータが無い場合は`__metadata__`を書き込まない。
+    pub fn metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// 層の数とエンコーダの方向を指定する。
+    pub fn layers(mut self, num_layers: usize, bidirectional: bool) -> Self {
+        self.num_layers = num_layers;
+        self.bidirectional = bidirectional;
+        self
+    }
+
+    /// エンコーダとデコーダの層の種類（`gru`、`lstm`、`transformer`）を指定する。
+    ///
+    /// 層の種類はメタデータの`encoder_type`と`decoder_type`にも書き込まれる。
+    /// 重みと異なる種類をメタデータに書く場合は、この後に[ModelBuilder::metadata]で上書きする。
+    pub fn types(self, encoder_type: &str, decoder_type: &str) -> Self {
+        let mut builder = self
+            .metadata("encoder_type", encoder_type)
+            .metadata("decoder_type", decoder_type);
+        builder.encoder_type = encoder_type.to_string();
+        builder.decoder_type = decoder_type.to_string();
+        builder
+    }
+
+    /// ランダムな値のテンソルを追加する。追加しても他のテンソルの値は変わらない。
+    pub fn tensor(mut self, name: &str, shape: &[usize]) -> Self {
+        self.extra_tensors.push((name.to_string(), shape.to_vec()));
+        self
+    }
+
+    /// モデルを生成し、safetensors形式のバイト列を返す。
+    pub fn build(self) -> Vec<u8> {
+        let Self {
+            in_vocab,
+            out_vocab,
+            dim,
+            num_layers,
+            bidirectional,
+            encoder_type,
+            decoder_type,
+            metadata,
+            extra_tensors,
+        } = self;
+        let directions = if bidirectional { 2 } else { 1 };
+        let gates = |layer_type: &str| if layer_type == "lstm" { 4 } else { 3 };
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let mut tensor = |shape: &[usize]| Tensor {
+            shape: shape.to_vec(),
+            data: (0..shape.iter().product())
+                .flat_map(|_| half::f16::from_f32(rng.random_range(-0.5..0.5)).to_le_bytes())
+                .collect(),
+        };
+        let mut tensors = vec![
+            ("e_emb.weight".to_string(), tensor(&[in_vocab, dim])),
+            ("k_emb.weight".to_string(), tensor(&[out_vocab, dim])),
+            (
+                "encoder_fc.0.weight".to_string(),
+                tensor(&[dim, directions * dim]),
+            ),
+            ("encoder_fc.0.bias".to_string(), tensor(&[dim])),
+        ];
+        let mha = |name: &str, tensor: &mut dyn FnMut(&[usize]) -> Tensor| {
+            vec![
+                (format!("{name}.in_proj_weight"), tensor(&[3 * dim, dim])),
+                (format!("{name}.in_proj_bias"), tensor(&[3 * dim])),
+                (format!("{name}.out_proj.weight"), tensor(&[dim, dim])),
+                (format!("{name}.out_proj.bias"), tensor(&[dim])),
+            ]
+        };
+        let mut rnns = vec![("encoder", "", dim, gates(&encoder_type))];
+        if decoder_type != "transformer" {
+            tensors.extend(mha("attn", &mut tensor));
+            rnns.push(("pre_decoder", "", dim, gates(&decoder_type)));
+            rnns.push(("post_decoder", "", 2 * dim, gates(&decoder_type)));
+        }
+        tensors.push(("fc.weight".to_string(), tensor(&[out_vocab, dim])));
+        tensors.push(("fc.bias".to_string(), tensor(&[out_vocab])));
+        if bidirectional {
+            rnns.push(("encoder", "_reverse", dim, gates(&encoder_type)));
+        }
+        for (name, suffix, input_dim, gates) in rnns {
+            for layer in 0..num_layers {
+                let input_dim = match (layer, name) {
+                    (0, _) => input_dim,
+                    (_, "encoder") => directions * dim,
+                    _ => dim,
+                };
+                tensors.push((
+                    format!("{name}.weight_ih_l{layer}{suffix}"),
+                    tensor(&[gates * dim, input_dim]),
+                ));
+                tensors.push((
+                    format!("{name}.weight_hh_l{layer}{suffix}"),
+                    tensor(&[gates * dim, dim]),
+                ));
+                tensors.push((
+                    format!("{name}.bias_ih_l{layer}{suffix}"),
+                    tensor(&[gates * dim]),
+                ));
+                tensors.push((
+                    format!("{name}.bias_hh_l{layer}{suffix}"),
+                    tensor(&[gates * dim]),
+                ));
+            }
+        }
+        if decoder_type == "transformer" {
+            for layer in 0..num_layers {
+                let name = format!("decoder.layers.{layer}");
+                tensors.extend(mha(&format!("{name}.self_attn"), &mut tensor));
+                tensors.extend(mha(&format!("{name}.multihead_attn"), &mut tensor));
+                for (linear, shape) in [("linear1", [2 * dim, dim]), ("linear2", [dim, 2 * dim])] {
+                    tensors.push((format!("{name}.{linear}.weight"), tensor(&shape)));
+                    tensors.push((format!("{name}.{linear}.bias"), tensor(&shape[..1])));
+                }
+                for norm in ["norm1", "norm2", "norm3"] {
+                    tensors.push((format!("{name}.{norm}.weight"), tensor(&[dim])));
+                    tensors.push((format!("{name}.{norm}.bias"), tensor(&[dim])));
+                }
+            }
+        }
+        for (name, shape) in extra_tensors {
+            tensors.push((name, tensor(&shape)));
+        }
+        let tensors = tensors
+            .iter()
+            .map(|(name, tensor)| (name.as_str(), tensor.view()))
+            .collect::<Vec<_>>();
+        let metadata = (!metadata.is_empty()).then_some(metadata);
+        safetensors::serialize(tensors, &metadata).unwrap()
+    }
+}
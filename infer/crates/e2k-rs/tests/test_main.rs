@@ -1,3 +1,5 @@
+mod common;
+
 #[test]
 fn test_c2k() {
     let src = "constants";
@@ -15,3 +17,986 @@ fn test_c2k_empty() {
     let dst = c2k.infer(src);
     assert_eq!(dst, "");
 }
+
+#[test]
+fn test_c2k_seed() {
+    let src = "constants";
+
+    let mut c2k = e2k::C2k::new(32);
+    c2k.set_decode_strategy(e2k::Strategy::TopK(e2k::StrategyTopK {
+        k: 5,
+        seed: Some(42),
+        ..Default::default()
+    }));
+    let first = c2k.infer(src);
+    for _ in 0..8 {
+        assert_eq!(c2k.infer(src), first);
+    }
+
+    c2k.set_decode_strategy(e2k::Strategy::TopP(e2k::StrategyTopP {
+        seed: Some(42),
+        ..Default::default()
+    }));
+    let first = c2k.infer(src);
+    for _ in 0..8 {
+        assert_eq!(c2k.infer(src), first);
+    }
+}
+
+#[test]
+fn test_c2k_infer_with_rng() {
+    use rand::SeedableRng;
+
+    let src = "constants";
+
+    let mut c2k = e2k::C2k::new(32);
+    c2k.set_decode_strategy(e2k::Strategy::TopK(e2k::StrategyTopK::default()));
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(42);
+    let first = c2k.infer_with_rng(src, &mut rng);
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(42);
+    let second = c2k.infer_with_rng(src, &mut rng);
+    assert_eq!(first, second);
+}
+
+#[test]
+fn test_c2k_sampling_strategies() {
+    let src = "constants";
+
+    let mut c2k = e2k::C2k::new(32);
+    let strategies = [
+        e2k::Strategy::MinP(e2k::StrategyMinP {
+            seed: Some(42),
+            ..Default::default()
+        }),
+        e2k::Strategy::Typical(e2k::StrategyTypical {
+            seed: Some(42),
+            ..Default::default()
+        }),
+        e2k::Strategy::Temperature(e2k::StrategyTemperature {
+            seed: Some(42),
+            ..Default::default()
+        }),
+    ];
+    for strategy in strategies {
+        c2k.set_decode_strategy(strategy);
+        let first = c2k.infer(src);
+        assert_eq!(c2k.infer(src), first);
+    }
+}
+
+#[test]
+fn test_strategy_validate() {
+    assert_eq!(e2k::Strategy::Greedy.validate(), Ok(()));
+    assert_eq!(
+        e2k::Strategy::TopP(e2k::StrategyTopP {
+            top_p: 1.0,
+            ..Default::default()
+        })
+        .validate(),
+        Ok(())
+    );
+    assert_eq!(
+        e2k::Strategy::TopK(e2k::StrategyTopK {
+            k: 0,
+            ..Default::default()
+        })
+        .validate(),
+        Err(e2k::StrategyError::InvalidK(0))
+    );
+    assert_eq!(
+        e2k::Strategy::Typical(e2k::StrategyTypical {
+            typical_p: 1.5,
+            ..Default::default()
+        })
+        .validate(),
+        Err(e2k::StrategyError::InvalidProbability {
+            name: "typical_p",
+            value: 1.5
+        })
+    );
+    for temperature in [0.0, -1.0, f32::INFINITY] {
+        assert_eq!(
+            e2k::Strategy::Temperature(e2k::StrategyTemperature {
+                temperature,
+                seed: None,
+            })
+            .validate(),
+            Err(e2k::StrategyError::InvalidTemperature(temperature))
+        );
+    }
+    assert!(e2k::Strategy::MinP(e2k::StrategyMinP {
+        temperature: f32::NAN,
+        ..Default::default()
+    })
+    .validate()
+    .is_err());
+}
+
+#[test]
+fn test_c2k_infer_with() {
+    let src = "constants";
+
+    let c2k = e2k::C2k::new(32);
+    let options = e2k::InferOptions {
+        strategy: e2k::Strategy::TopK(e2k::StrategyTopK::default()),
+        seed: Some(42),
+        ..Default::default()
+    };
+    let expected = c2k.infer_with(src, &options).unwrap();
+    std::thread::scope(|s| {
+        let handles = (0..4)
+            .map(|_| s.spawn(|| c2k.infer_with(src, &options).unwrap()))
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    });
+    assert_eq!(
+        c2k.infer(src),
+        c2k.infer_with(src, c2k.default_options()).unwrap()
+    );
+}
+
+#[test]
+fn test_c2k_infer_with_invalid_strategy() {
+    let c2k = e2k::C2k::new(32);
+    for strategy in [
+        e2k::Strategy::TopK(e2k::StrategyTopK {
+            k: 0,
+            ..Default::default()
+        }),
+        e2k::Strategy::Temperature(e2k::StrategyTemperature {
+            temperature: 0.0,
+            ..Default::default()
+        }),
+    ] {
+        let options = e2k::InferOptions {
+            strategy,
+            ..Default::default()
+        };
+        assert!(c2k.infer_with("constants", &options).is_err());
+        assert!(c2k.infer_stream_with("constants", &options).is_err());
+        assert!(c2k.infer_with_alignment("constants", &options).is_err());
+    }
+}
+
+#[test]
+#[should_panic(expected = "invalid strategy")]
+fn test_c2k_set_invalid_strategy() {
+    let mut c2k = e2k::C2k::new(32);
+    c2k.set_decode_strategy(e2k::Strategy::TopP(e2k::StrategyTopP {
+        top_p: 1.5,
+        ..Default::default()
+    }));
+}
+
+#[test]
+fn test_c2k_infer_with_constraints() {
+    let src = "constants";
+
+    let c2k = e2k::C2k::new(32);
+    let options = e2k::InferOptions {
+        max_length: 4,
+        ..Default::default()
+    };
+    assert!(c2k.infer_with(src, &options).unwrap().chars().count() <= 4);
+
+    let options = e2k::InferOptions {
+        constraints: e2k::Constraints {
+            min_length: 8,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(c2k.infer_with(src, &options).unwrap().chars().count() >= 8);
+
+    let banned_chars = c2k.infer(src).chars().collect::<Vec<_>>();
+    let options = e2k::InferOptions {
+        constraints: e2k::Constraints {
+            banned_chars: banned_chars.clone(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let dst = c2k.infer_with(src, &options).unwrap();
+    assert!(dst.chars().all(|c| !banned_chars.contains(&c)));
+}
+
+#[test]
+fn test_c2k_infer_with_all_chars_banned() {
+    let src = "constants";
+
+    let c2k = e2k::C2k::new(32);
+    // 特殊トークンの"<pad>"などは'<'に変換されるので、全ての文字を禁止する。
+    let options = e2k::InferOptions {
+        constraints: e2k::Constraints {
+            banned_chars: e2k::KANAS.iter().flat_map(|k| k.chars()).collect(),
+            min_length: 4,
+        },
+        ..Default::default()
+    };
+    assert_eq!(c2k.infer_with(src, &options).unwrap(), "");
+    let mut stream = c2k.infer_stream_with(src, &options).unwrap();
+    assert_eq!(stream.next(), None);
+    assert_eq!(stream.eos_probability(), Some(1.0));
+    assert_eq!(c2k.infer_n_best(src, 2, &options).len(), 1);
+}
+
+#[test]
+fn test_c2k_infer_with_hiragana() {
+    let src = "constants";
+
+    let c2k = e2k::C2k::new(32);
+    let katakana = c2k.infer(src);
+    let hiragana = c2k
+        .infer_with(
+            src,
+            &e2k::InferOptions {
+                output_format: e2k::OutputFormat::Hiragana,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(katakana.chars().count(), hiragana.chars().count());
+    assert!(!hiragana.chars().any(|c| ('ァ'..='ヶ').contains(&c)));
+}
+
+#[test]
+fn test_c2k_infer_with_alignment() {
+    let src = "Constants";
+
+    let c2k = e2k::C2k::new(32);
+    let options = e2k::InferOptions::default();
+    let alignments = c2k.infer_with_alignment(src, &options).unwrap();
+    assert_eq!(
+        alignments.iter().map(|a| a.kana).collect::<String>(),
+        c2k.infer_with(src, &options).unwrap()
+    );
+    for alignment in alignments {
+        assert_eq!(alignment.weights.len(), src.chars().count());
+        assert!((alignment.weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        // 大文字はモデルの入力に含まれないので、重みは0になる。
+        assert_eq!(alignment.weights[0], 0.0);
+        assert!(alignment.source_index > 0 && alignment.source_index < src.len());
+        for head_weights in &alignment.head_weights {
+            assert_eq!(head_weights.len(), src.chars().count());
+            assert!((head_weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+        }
+    }
+}
+
+#[test]
+fn test_c2k_infer_stream() {
+    let src = "constants";
+
+    let c2k = e2k::C2k::new(32);
+    let streamed = c2k.infer_stream(src).collect::<Vec<_>>();
+    assert_eq!(
+        streamed.iter().map(|s| s.kana).collect::<String>(),
+        c2k.infer(src)
+    );
+    for s in &streamed {
+        assert!(s.probability > 0.0 && s.probability <= 1.0);
+    }
+
+    // 確率は禁止した文字を除いた分布でのものになる。
+    let first = streamed[0].kana;
+    let options = e2k::InferOptions {
+        constraints: e2k::Constraints {
+            banned_chars: e2k::KANAS
+                .iter()
+                .flat_map(|k| k.chars())
+                .filter(|&c| c != first)
+                .collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let constrained = c2k
+        .infer_stream_with(src, &options)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(constrained.kana, first);
+    assert_eq!(constrained.probability, 1.0);
+    assert!(streamed[0].probability < 1.0);
+
+    let mut stream = c2k.infer_stream(src);
+    assert_eq!(stream.next(), streamed.first().copied());
+    assert_eq!(stream.eos_probability(), None);
+    drop(stream);
+
+    assert_eq!(c2k.infer_stream("").count(), 0);
+}
+
+#[test]
+fn test_c2k_infer_n_best() {
+    let src = "constants";
+
+    let c2k = e2k::C2k::new(32);
+    let options = e2k::InferOptions::default();
+    let candidates = c2k.infer_n_best(src, 4, &options);
+    assert_eq!(candidates.len(), 4);
+    for pair in candidates.windows(2) {
+        assert!(pair[0].log_prob >= pair[1].log_prob);
+        assert_ne!(pair[0].kana, pair[1].kana);
+    }
+    assert!(candidates.iter().all(|c| c.log_prob <= 0.0));
+    assert!(candidates.iter().all(|c| c.kana.chars().count() <= 32));
+
+    let best = c2k.infer_n_best(src, 1, &options);
+    assert_eq!(best.len(), 1);
+    // ビーム幅が1のときはGreedyと同じになる。
+    assert_eq!(best[0].kana, c2k.infer_with(src, &options).unwrap());
+    // EOSの確率を含めると、ストリームの確率から同じ対数確率が求まる。
+    for src in [src, "a"] {
+        let mut stream = c2k.infer_stream_with(src, &options).unwrap();
+        let log_prob = stream.by_ref().map(|s| s.probability.ln()).sum::<f32>()
+            + stream.eos_probability().map_or(0.0, f32::ln);
+        let expected = c2k.infer_n_best(src, 1, &options)[0].log_prob;
+        assert!(
+            (log_prob - expected).abs() < 1e-4,
+            "{log_prob} != {expected}"
+        );
+    }
+
+    assert!(c2k.infer_n_best(src, 0, &options).is_empty());
+    assert!(c2k.infer_n_best("", 4, &options).is_empty());
+
+    let banned_chars = candidates[0].kana.chars().collect::<Vec<_>>();
+    let options = e2k::InferOptions {
+        constraints: e2k::Constraints {
+            banned_chars: banned_chars.clone(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    for candidate in c2k.infer_n_best(src, 4, &options) {
+        assert!(candidate.kana.chars().all(|c| !banned_chars.contains(&c)));
+    }
+}
+
+#[test]
+fn test_cached_c2k() {
+    let c2k = e2k::CachedC2k::new(e2k::C2k::new(32), 2);
+    let expected = c2k.c2k().infer("constants");
+
+    assert_eq!(c2k.infer("constants"), expected);
+    assert_eq!(c2k.infer("  Constants "), expected);
+    assert_eq!(c2k.infer("ｃｏｎｓｔａｎｔｓ"), expected);
+    // 正規化してから推論するため、正規化していない入力ではC2k::inferと異なることがある。
+    let word = "ＣＯＮＳＴＡＮＴＳ";
+    assert_eq!(c2k.infer(word), c2k.c2k().infer(&e2k::normalize(word)));
+    let stats = c2k.stats();
+    assert_eq!((stats.hits, stats.misses, stats.len), (3, 1, 1));
+    assert!((stats.hit_rate() - 3.0 / 4.0).abs() < 1e-9);
+
+    // シードの無いサンプリングはキャッシュしない。
+    let sampling = e2k::InferOptions {
+        strategy: e2k::Strategy::TopK(Default::default()),
+        ..Default::default()
+    };
+    assert!(!sampling.is_deterministic());
+    c2k.infer_with("constants", &sampling).unwrap();
+    assert_eq!(c2k.stats().len, 1);
+    let seeded = e2k::InferOptions {
+        seed: Some(42),
+        ..sampling
+    };
+    assert!(seeded.is_deterministic());
+    assert_eq!(
+        c2k.infer_with("constants", &seeded).unwrap(),
+        c2k.c2k().infer_with("constants", &seeded).unwrap()
+    );
+    assert_eq!(c2k.stats().len, 2);
+
+    // 容量を超えると最も古いものが捨てられる。
+    c2k.infer("hello");
+    assert_eq!(c2k.stats().len, 2);
+    c2k.infer_with("constants", &seeded).unwrap();
+    assert_eq!(c2k.stats().misses, 3);
+
+    let mut saved = Vec::new();
+    c2k.save(&mut saved).unwrap();
+    let warmed = e2k::CachedC2k::new(e2k::C2k::new(32), 8);
+    assert_eq!(warmed.warm(saved.as_slice()).unwrap(), 2);
+    assert_eq!(warmed.infer("hello"), c2k.c2k().infer("hello"));
+    assert_eq!(warmed.stats().hits, 1);
+
+    c2k.clear();
+    assert_eq!(
+        c2k.stats(),
+        e2k::CacheStats {
+            capacity: 2,
+            ..Default::default()
+        }
+    );
+
+    assert!(matches!(
+        warmed.warm("# e2k-cache\t0.0.0\t\n".as_bytes()),
+        Err(e2k::LineFileError::Incompatible)
+    ));
+    // キーの形式が異なるファイルも読み込まない。
+    let saved = String::from_utf8(saved).unwrap();
+    let old_format = saved.replacen("# e2k-cache\t2\t", "# e2k-cache\t1\t", 1);
+    assert_ne!(old_format, saved);
+    assert!(matches!(
+        warmed.warm(old_format.as_bytes()),
+        Err(e2k::LineFileError::Incompatible)
+    ));
+    // 重みの保持する形式が異なるモデルで保存したファイルも読み込まない。
+    let int8 = e2k::CachedC2k::new(e2k::C2k::with_precision(32, e2k::Precision::Int8), 8);
+    assert!(matches!(
+        int8.warm(saved.as_bytes()),
+        Err(e2k::LineFileError::Incompatible)
+    ));
+    // バージョンの無いモデル同士でも、重みが異なれば読み込まない。
+    let versionless = |dim| {
+        let model =
+            common::ModelBuilder::new(e2k::ASCII_ENTRIES.len(), e2k::KANAS.len(), dim).build();
+        let c2k = e2k::C2k::from_bytes(&model, 8).unwrap();
+        assert_eq!(c2k.model_info().version, None);
+        e2k::CachedC2k::new(c2k, 8)
+    };
+    let small = versionless(8);
+    small.infer("cat");
+    let mut saved = Vec::new();
+    small.save(&mut saved).unwrap();
+    assert_eq!(versionless(8).warm(saved.as_slice()).unwrap(), 1);
+    assert!(matches!(
+        versionless(16).warm(saved.as_slice()),
+        Err(e2k::LineFileError::Incompatible)
+    ));
+    assert!(matches!(
+        warmed.warm("word\tワード\n".as_bytes()),
+        Err(e2k::LineFileError::InvalidLine { line: 1 })
+    ));
+}
+
+#[test]
+fn test_base_e2k_from_bytes() {
+    let input_vocab = ["<pad>", "<sos>", "<eos>", "α", "β", "γ"];
+    let output_vocab = ["<pad>", "<sos>", "<eos>", "ka", "ki", "ku", "ke"];
+    let model = common::ModelBuilder::new(input_vocab.len(), output_vocab.len(), 16)
+        .metadata("input_vocab", &format!("{input_vocab:?}"))
+        .metadata("output_vocab", &format!("{output_vocab:?}"))
+        .build();
+    let mut e2k = e2k::BaseE2k::from_bytes(&model, 8).unwrap();
+
+    let src = ["α", "β", "γ"].map(String::from);
+    let dst = e2k.infer(&src);
+    assert!(dst.len() <= 8);
+    assert!(dst.iter().all(|o| output_vocab.contains(&o.as_str())));
+    assert_eq!(
+        e2k.infer_stream(&src).map(|s| s.kana).collect::<Vec<_>>(),
+        dst
+    );
+    assert!(e2k.infer(&["δ".to_string()]).is_empty());
+
+    let candidates = e2k.infer_n_best(&src, 2, e2k.default_options());
+    assert_eq!(candidates.len(), 2);
+    assert!(candidates[0].log_prob >= candidates[1].log_prob);
+
+    e2k.set_decode_strategy(e2k::Strategy::TopK(e2k::StrategyTopK {
+        seed: Some(42),
+        ..Default::default()
+    }));
+    assert_eq!(e2k.infer(&src), e2k.infer(&src));
+}
+
+#[test]
+fn test_base_e2k_from_bytes_without_metadata() {
+    let model = common::ModelBuilder::new(e2k::ASCII_ENTRIES.len(), e2k::KANAS.len(), 16).build();
+    let error = e2k::BaseE2k::from_bytes(&model, 8).unwrap_err();
+    assert!(matches!(
+        error,
+        e2k::LoadError::MissingMetadata { key } if key == "input_vocab"
+    ));
+}
+
+#[test]
+fn test_c2k_from_bytes() {
+    let model = common::ModelBuilder::new(e2k::ASCII_ENTRIES.len(), e2k::KANAS.len(), 16).build();
+    let mut c2k = e2k::C2k::from_bytes(&model, 8).unwrap();
+
+    let dst = c2k.infer("cat");
+    assert!(dst.chars().count() <= 8);
+    assert!(dst
+        .chars()
+        .all(|c| e2k::KANAS.contains(&c.to_string().as_str())));
+    c2k.set_decode_strategy(e2k::Strategy::TopK(e2k::StrategyTopK {
+        seed: Some(42),
+        ..Default::default()
+    }));
+    assert_eq!(c2k.infer("cat"), c2k.infer("cat"));
+
+    let error = e2k::C2k::from_bytes(b"not a model", 8).unwrap_err();
+    assert!(matches!(error, e2k::LoadError::SafeTensors(_)));
+
+    // 入力の語彙が埋め込みより大きい。
+    let model = common::ModelBuilder::new(10, e2k::KANAS.len(), 16).build();
+    let error = e2k::C2k::from_bytes(&model, 8).unwrap_err();
+    assert!(matches!(
+        error,
+        e2k::LoadError::InvalidTensor { name, .. } if name == "e_emb.weight"
+    ));
+}
+
+#[test]
+fn test_p2k() {
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16).build();
+    let p2k = e2k::P2k::from_bytes(&model, 8).unwrap();
+
+    let dst = p2k.infer(&["K", "AE1", "T"]);
+    assert!(dst.chars().count() <= 8);
+    assert_eq!(p2k.infer(&["k", "ae1", "t"]), dst);
+    // ストレスの無い母音は第1強勢として扱う。
+    assert_eq!(p2k.infer(&["K", "AE", "T"]), dst);
+    assert_eq!(p2k.infer::<&str>(&[]), "");
+
+    let alignments = p2k
+        .infer_with_alignment(&["K", "AE1", "T"], p2k.default_options())
+        .unwrap();
+    assert_eq!(alignments.iter().map(|a| a.kana).collect::<String>(), dst);
+    for alignment in alignments {
+        assert_eq!(alignment.weights.len(), 3);
+    }
+    assert_eq!(
+        p2k.infer_stream(&["K", "AE1", "T"])
+            .map(|s| s.kana)
+            .collect::<String>(),
+        dst
+    );
+}
+
+#[test]
+fn test_c2k_model_info() {
+    let c2k = e2k::C2k::new(32);
+    let info = c2k.model_info();
+    assert_eq!(info.input_vocab, e2k::ASCII_ENTRIES);
+    assert_eq!(info.output_vocab, e2k::KANAS);
+    assert_eq!(info.n_heads, 4);
+}
+
+#[test]
+fn test_p2k_model_info() {
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+        .metadata("version", "test")
+        .metadata("n_heads", "2")
+        .build();
+    let p2k = e2k::P2k::from_bytes(&model, 8).unwrap();
+    let info = p2k.model_info();
+    assert_eq!(info.version.as_deref(), Some("test"));
+    assert_eq!(info.input_vocab, e2k::EN_PHONES);
+    assert_eq!(info.n_heads, 2);
+    for alignment in p2k
+        .infer_with_alignment(&["K", "AE1", "T"], p2k.default_options())
+        .unwrap()
+    {
+        assert_eq!(alignment.head_weights.len(), 2);
+    }
+
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16).build();
+    let p2k = e2k::P2k::from_bytes(&model, 8).unwrap();
+    assert_eq!(p2k.model_info().version, None);
+    assert_eq!(p2k.model_info().n_heads, 4);
+}
+
+#[test]
+fn test_p2k_custom_architecture() {
+    for (num_layers, bidirectional) in [(2, true), (1, false), (3, false)] {
+        let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+            .layers(num_layers, bidirectional)
+            .metadata("n_heads", "2")
+            .build();
+        let p2k = e2k::P2k::from_bytes(&model, 8).unwrap();
+        let alignments = p2k
+            .infer_with_alignment(&["K", "AE1", "T"], p2k.default_options())
+            .unwrap();
+        assert!(alignments.len() <= 8);
+        for alignment in alignments {
+            assert_eq!(alignment.head_weights.len(), 2);
+            assert_eq!(alignment.weights.len(), 3);
+        }
+    }
+}
+
+#[test]
+fn test_c2k_precision() {
+    let words = include_str!("data/heldout_words.tsv")
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split_once('\t').unwrap().0)
+        .collect::<Vec<_>>();
+    let infer_all = |c2k: &e2k::C2k| words.iter().map(|word| c2k.infer(word)).collect::<Vec<_>>();
+
+    // F32の出力と単語ごとに比べる。
+    let baseline = infer_all(&e2k::C2k::new(16));
+    for (precision, min_agreement) in [(e2k::Precision::F16, 1.0), (e2k::Precision::Int8, 0.9)] {
+        let outputs = infer_all(&e2k::C2k::with_precision(16, precision));
+        let agreement = baseline
+            .iter()
+            .zip(&outputs)
+            .filter(|(expected, actual)| expected == actual)
+            .count() as f64
+            / words.len() as f64;
+        assert!(
+            agreement >= min_agreement,
+            "{precision:?}: {agreement} < {min_agreement}"
+        );
+    }
+}
+
+#[test]
+fn test_c2k_precision_tolerance() {
+    let words = include_str!("data/heldout_words.tsv")
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(word, _)| word)
+        .collect::<Vec<_>>();
+    let baseline = e2k::C2k::new(16);
+    let options = baseline.default_options();
+    for (precision, tolerance) in [(e2k::Precision::F16, 1e-4), (e2k::Precision::Int8, 0.1)] {
+        let c2k = e2k::C2k::with_precision(16, precision);
+        let mut max_diff = 0f32;
+        for word in &words {
+            // 同じ文字を出力している間は、同じ位置の確率を比較できる。
+            for (expected, actual) in baseline
+                .infer_stream_with(word, options)
+                .unwrap()
+                .zip(c2k.infer_stream_with(word, options).unwrap())
+            {
+                max_diff = max_diff.max((expected.probability - actual.probability).abs());
+                if expected.kana != actual.kana {
+                    break;
+                }
+            }
+        }
+        assert!(
+            max_diff <= tolerance,
+            "{precision:?}: {max_diff} > {tolerance}"
+        );
+    }
+}
+
+#[test]
+fn test_p2k_layer_types() {
+    let layer_type = |name| match name {
+        "lstm" => e2k::LayerType::Lstm,
+        "transformer" => e2k::LayerType::Transformer,
+        _ => e2k::LayerType::Gru,
+    };
+    for (encoder_type, decoder_type) in [
+        ("lstm", "lstm"),
+        ("gru", "lstm"),
+        ("gru", "transformer"),
+        ("lstm", "transformer"),
+    ] {
+        for num_layers in [1, 2] {
+            let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+                .layers(num_layers, true)
+                .types(encoder_type, decoder_type)
+                .metadata("n_heads", "2")
+                .build();
+            let p2k = e2k::P2k::from_bytes(&model, 8).unwrap();
+            assert_eq!(
+                (p2k.model_info().encoder_type, p2k.model_info().decoder_type),
+                (layer_type(encoder_type), layer_type(decoder_type))
+            );
+            let alignments = p2k
+                .infer_with_alignment(&["K", "AE1", "T"], p2k.default_options())
+                .unwrap();
+            assert!(alignments.len() <= 8);
+            for alignment in alignments {
+                assert_eq!(alignment.head_weights.len(), 2);
+                assert_eq!(alignment.weights.len(), 3);
+            }
+            // n=1のビームサーチは貪欲法と同じ。
+            let n_best = p2k.infer_n_best(&["K", "AE1", "T"], 1, p2k.default_options());
+            assert_eq!(n_best[0].kana, p2k.infer(&["K", "AE1", "T"]));
+        }
+    }
+
+    // 層の種類と重みが一致しない。
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+        .metadata("n_heads", "2")
+        .metadata("decoder_type", "lstm")
+        .build();
+    let error = e2k::P2k::from_bytes(&model, 8).unwrap_err();
+    assert!(matches!(
+        error,
+        e2k::LoadError::InvalidTensor { name, .. } if name.starts_with("pre_decoder.")
+    ));
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+        .metadata("decoder_type", "transformer")
+        .build();
+    let error = e2k::P2k::from_bytes(&model, 8).unwrap_err();
+    assert!(matches!(
+        error,
+        e2k::LoadError::InvalidTensor { name, .. } if name.starts_with("decoder.layers.0.")
+    ));
+}
+
+#[test]
+fn test_p2k_transformer_options() {
+    let model = |position_encoding| {
+        common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+            .types("gru", "transformer")
+            .metadata("n_heads", "2")
+            .metadata("position_encoding", position_encoding)
+    };
+    let log_prob = |model: &[u8]| {
+        let p2k = e2k::P2k::from_bytes(model, 8).unwrap();
+        p2k.infer_n_best(&["K", "AE1", "T"], 1, p2k.default_options())[0].log_prob
+    };
+
+    // 位置エンコーディングはメタデータで指定した場合のみ加える。
+    let plain = model("none").build();
+    let p2k = e2k::P2k::from_bytes(&plain, 8).unwrap();
+    assert_eq!(
+        p2k.model_info().position_encoding,
+        e2k::PositionEncoding::None
+    );
+    let sinusoidal = model("sinusoidal").build();
+    assert_eq!(
+        e2k::P2k::from_bytes(&sinusoidal, 8)
+            .unwrap()
+            .model_info()
+            .position_encoding,
+        e2k::PositionEncoding::Sinusoidal
+    );
+    assert_ne!(log_prob(&plain), log_prob(&sinusoidal));
+
+    // `decoder.norm`は重みがある場合のみ使う。
+    let with_norm = model("none")
+        .tensor("decoder.norm.weight", &[16])
+        .tensor("decoder.norm.bias", &[16])
+        .build();
+    assert_ne!(log_prob(&plain), log_prob(&with_norm));
+    let without_bias = model("none").tensor("decoder.norm.weight", &[16]).build();
+    assert!(matches!(
+        e2k::P2k::from_bytes(&without_bias, 8).unwrap_err(),
+        e2k::LoadError::InvalidTensor { name, .. } if name == "decoder.norm.bias"
+    ));
+}
+
+#[test]
+fn test_p2k_invalid_architecture() {
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16)
+        .metadata("n_heads", "3")
+        .build();
+    let error = e2k::P2k::from_bytes(&model, 8).unwrap_err();
+    assert!(matches!(
+        error,
+        e2k::LoadError::InvalidTensor { name, .. } if name == "attn.in_proj_weight"
+    ));
+
+    // 入力の語彙が埋め込みより大きい。
+    let model = common::ModelBuilder::new(e2k::ASCII_ENTRIES.len(), e2k::KANAS.len(), 16).build();
+    let error = e2k::P2k::from_bytes(&model, 8).unwrap_err();
+    assert!(matches!(
+        error,
+        e2k::LoadError::InvalidTensor { name, .. } if name == "e_emb.weight"
+    ));
+}
+
+#[test]
+fn test_p2k_from_file() {
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16).build();
+    let path = std::env::temp_dir().join(format!("e2k-test-{}.safetensors", std::process::id()));
+    std::fs::write(&path, &model).unwrap();
+
+    let input = ["K", "AE1", "T"];
+    for precision in [
+        e2k::Precision::F32,
+        e2k::Precision::F16,
+        e2k::Precision::Int8,
+    ] {
+        let p2k = e2k::P2k::from_bytes_with_precision(&model, 8, precision).unwrap();
+        let expected = p2k
+            .infer_with_alignment(&input, p2k.default_options())
+            .unwrap();
+        let loaded = e2k::P2k::from_file(&path, 8, precision).unwrap();
+        assert_eq!(
+            loaded
+                .infer_with_alignment(&input, loaded.default_options())
+                .unwrap(),
+            expected,
+            "{precision:?}"
+        );
+        #[cfg(feature = "mmap")]
+        {
+            // SAFETY: テスト中はファイルを変更しない。
+            let mapped = unsafe { e2k::P2k::from_file_mmap(&path, 8, precision) }.unwrap();
+            assert_eq!(
+                mapped
+                    .infer_with_alignment(&input, mapped.default_options())
+                    .unwrap(),
+                expected,
+                "{precision:?}"
+            );
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        e2k::P2k::from_file(&path, 8, e2k::Precision::F16),
+        Err(e2k::LoadError::Io(_))
+    ));
+    #[cfg(feature = "mmap")]
+    assert!(matches!(
+        // SAFETY: ファイルが存在しないため、マップされない。
+        unsafe { e2k::P2k::from_file_mmap(&path, 8, e2k::Precision::F16) },
+        Err(e2k::LoadError::Io(_))
+    ));
+}
+
+#[test]
+fn test_c2k_from_file() {
+    let model = common::ModelBuilder::new(e2k::ASCII_ENTRIES.len(), e2k::KANAS.len(), 16).build();
+    let path =
+        std::env::temp_dir().join(format!("e2k-test-c2k-{}.safetensors", std::process::id()));
+    std::fs::write(&path, &model).unwrap();
+
+    for precision in [e2k::Precision::F32, e2k::Precision::F16] {
+        let c2k = e2k::C2k::from_bytes_with_precision(&model, 8, precision).unwrap();
+        let loaded = e2k::C2k::from_file(&path, 8, precision).unwrap();
+        assert_eq!(
+            loaded.infer("constants"),
+            c2k.infer("constants"),
+            "{precision:?}"
+        );
+        #[cfg(feature = "mmap")]
+        {
+            // SAFETY: テスト中はファイルを変更しない。
+            let mapped = unsafe { e2k::C2k::from_file_mmap(&path, 8, precision) }.unwrap();
+            assert_eq!(
+                mapped.infer("constants"),
+                c2k.infer("constants"),
+                "{precision:?}"
+            );
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        e2k::C2k::from_file(&path, 8, e2k::Precision::F16),
+        Err(e2k::LoadError::Io(_))
+    ));
+    #[cfg(feature = "mmap")]
+    assert!(matches!(
+        // SAFETY: ファイルが存在しないため、マップされない。
+        unsafe { e2k::C2k::from_file_mmap(&path, 8, e2k::Precision::F16) },
+        Err(e2k::LoadError::Io(_))
+    ));
+}
+
+#[cfg(feature = "cmudict")]
+#[test]
+fn test_cmudict_converter() {
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16).build();
+    let p2k = e2k::P2k::from_bytes(&model, 32).unwrap();
+    let c2k = e2k::C2k::new(32);
+    let dict = e2k::CmuDict::parse("CAT  K AE1 T\n").unwrap();
+    let converter = e2k::CmuDictConverter::new(dict, p2k, c2k);
+
+    let conversion = converter.infer("Cat");
+    assert_eq!(conversion.path, e2k::ConversionPath::Dictionary);
+    let model = common::ModelBuilder::new(e2k::EN_PHONES.len(), e2k::KANAS.len(), 16).build();
+    let p2k = e2k::P2k::from_bytes(&model, 32).unwrap();
+    assert_eq!(conversion.kana, p2k.infer(&["K", "AE1", "T"]));
+
+    let conversion = converter.infer("constants");
+    assert_eq!(conversion.path, e2k::ConversionPath::Spelling);
+    assert_eq!(conversion.kana, e2k::C2k::new(32).infer("constants"));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_async_c2k() {
+    let words = ["constants", "hello", "world", "voicevox"];
+    for max_words_per_task in [1, 4] {
+        let c2k = e2k::AsyncC2k::new(
+            e2k::C2k::new(32),
+            e2k::AsyncC2kConfig {
+                max_words_per_task,
+                ..Default::default()
+            },
+        );
+        let handles = (0..16)
+            .map(|i| {
+                let c2k = c2k.clone();
+                let word = words[i % words.len()];
+                tokio::spawn(async move { (word, c2k.infer(word).await) })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let (word, kana) = handle.await.unwrap();
+            assert_eq!(kana.unwrap(), c2k.c2k().infer(word));
+        }
+    }
+
+    let c2k = e2k::AsyncC2k::new(e2k::C2k::new(32), e2k::AsyncC2kConfig::default());
+
+    let options = e2k::InferOptions {
+        strategy: e2k::Strategy::TopK(e2k::StrategyTopK {
+            seed: Some(42),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        c2k.infer_with("constants", &options).await,
+        Ok(c2k.c2k().infer_with("constants", &options).unwrap())
+    );
+    let inputs = words.map(String::from).to_vec();
+    assert_eq!(
+        c2k.infer_batch(inputs.clone(), &options).await.unwrap(),
+        inputs
+            .iter()
+            .map(|word| c2k.c2k().infer_with(word, &options).unwrap())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_async_c2k_timeout_and_cancel() {
+    use std::time::Duration;
+
+    // まとめるための待ち時間がタイムアウトより長いため、必ずタイムアウトする。
+    let c2k = e2k::AsyncC2k::new(
+        e2k::C2k::new(32),
+        e2k::AsyncC2kConfig {
+            max_words_per_task: 2,
+            batch_window: Duration::from_millis(500),
+            timeout: Some(Duration::from_millis(10)),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        c2k.infer("constants").await,
+        Err(e2k::AsyncInferError::Timeout)
+    );
+
+    // 途中でdropしたバッチは残りの単語を推論せず、ブロッキングタスクの枠を空ける。
+    let c2k = e2k::AsyncC2k::new(
+        e2k::C2k::new(32),
+        e2k::AsyncC2kConfig {
+            max_concurrency: 1,
+            ..Default::default()
+        },
+    );
+    let inputs = vec!["constants".to_string(); 100_000];
+    let options = c2k.c2k().default_options().clone();
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), c2k.infer_batch(inputs, &options))
+            .await
+            .is_err()
+    );
+    let kana = tokio::time::timeout(Duration::from_secs(10), c2k.infer("constants"))
+        .await
+        .expect("the canceled batch should release the blocking task");
+    assert_eq!(kana.unwrap(), c2k.c2k().infer("constants"));
+}
@@ -0,0 +1,253 @@
+//! [e2k](https://github.com/VOICEVOX/e2k/tree/main/infer/crates/e2k-rs)をJSON APIとして提供するHTTPサーバー。
+//!
+//! | メソッド | パス | 内容 |
+//! | --- | --- | --- |
+//! | `POST` | `/convert` | 単語（`word`）か単語の配列（`words`）を変換する |
+//! | `GET` | `/health` | 死活監視。`{"status": "ok"}`を返す |
+//! | `GET` | `/info` | モデルとサーバーの設定を返す |
+//!
+//! 推論は[e2k::C2k::infer_with]で行うため、リクエストごとに異なるアルゴリズムを使ってもロックは不要です。
+//! 推論はtokioのブロッキング用スレッドで行い、同時に推論するリクエストが[Config::max_concurrent_requests]に
+//! 達している場合は`503 Service Unavailable`を返します。
+
+use axum::extract::State;
+use axum::extract::rejection::JsonRejection;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// サーバーの設定。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// 読みの最大長の既定値と上限。リクエストの`max_length`はこれ以下である必要がある。
+    pub max_length: usize,
+    /// 1リクエストで変換できる単語数の上限。
+    pub max_batch_size: usize,
+    /// `n_best`の上限。
+    pub max_n_best: usize,
+    /// 同時に推論するリクエスト数の上限。既定値はCPUのスレッド数。
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_length: 32,
+            max_batch_size: 1024,
+            max_n_best: 16,
+            max_concurrent_requests: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+struct AppState {
+    c2k: e2k::C2k,
+    config: Config,
+    /// 推論中のリクエスト数を制限するセマフォ。
+    permits: Arc<tokio::sync::Semaphore>,
+}
+
+/// ルーターを作る。
+pub fn router(c2k: e2k::C2k, config: Config) -> Router {
+    let permits = Arc::new(tokio::sync::Semaphore::new(config.max_concurrent_requests));
+    let state = Arc::new(AppState {
+        c2k,
+        config,
+        permits,
+    });
+    Router::new()
+        .route("/convert", post(convert))
+        .route("/health", get(health))
+        .route("/info", get(info))
+        .with_state(state)
+}
+
+/// `{"error": "..."}`を返すエラー。
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({"error": self.message}))).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self {
+            status: rejection.status(),
+            message: rejection.body_text(),
+        }
+    }
+}
+
+/// `POST /convert`のリクエスト。
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConvertRequest {
+    word: Option<String>,
+    words: Option<Vec<String>>,
+    strategy: Option<e2k::StrategyConfig>,
+    n_best: Option<usize>,
+    max_length: Option<usize>,
+}
+
+impl AppState {
+    fn options(&self, request: &mut ConvertRequest) -> Result<e2k::InferOptions, ApiError> {
+        let max_length = request.max_length.unwrap_or(self.config.max_length);
+        if max_length > self.config.max_length {
+            return Err(ApiError::bad_request(format!(
+                "max_length must be at most {}",
+                self.config.max_length
+            )));
+        }
+        let strategy = match request.strategy.take() {
+            Some(strategy) => strategy
+                .try_into()
+                .map_err(|e: e2k::StrategyError| ApiError::bad_request(e.to_string()))?,
+            None => e2k::Strategy::Greedy,
+        };
+        Ok(e2k::InferOptions {
+            strategy,
+            max_length,
+            ..Default::default()
+        })
+    }
+
+    fn convert(
+        &self,
+        word: &str,
+        options: &e2k::InferOptions,
+        n_best: Option<usize>,
+    ) -> serde_json::Value {
+        match n_best {
+            Some(n) => {
+                let candidates = self
+                    .c2k
+                    .infer_n_best(word, n, options)
+                    .into_iter()
+                    .map(|c| json!({"kana": c.kana, "log_prob": c.log_prob}))
+                    .collect::<Vec<_>>();
+                json!({"word": word, "candidates": candidates})
+            }
+            None => {
+                let kana = self
+                    .c2k
+                    .infer_with(word, options)
+                    .expect("Unreachable: strategy is validated in AppState::options");
+                json!({"word": word, "kana": kana})
+            }
+        }
+    }
+}
+
+/// `POST /convert`。
+///
+/// `word`を指定した場合は`{"word", "kana"}`を、`words`を指定した場合は`{"results": [...]}`を返す。
+/// `n_best`を指定した場合、`kana`の代わりに`candidates: [{"kana", "log_prob"}]`を返す。
+async fn convert(
+    State(state): State<Arc<AppState>>,
+    request: Result<Json<ConvertRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let Json(mut request) = request?;
+    let options = state.options(&mut request)?;
+    if let Some(n) = request.n_best {
+        if n == 0 || n > state.config.max_n_best {
+            return Err(ApiError::bad_request(format!(
+                "n_best must be between 1 and {}",
+                state.config.max_n_best
+            )));
+        }
+    }
+    let (words, batch) = match (request.word, request.words) {
+        (Some(word), None) => (vec![word], false),
+        (None, Some(words)) => (words, true),
+        _ => {
+            return Err(ApiError::bad_request(
+                "exactly one of `word` and `words` must be specified",
+            ));
+        }
+    };
+    if words.len() > state.config.max_batch_size {
+        return Err(ApiError::bad_request(format!(
+            "words must contain at most {} words",
+            state.config.max_batch_size
+        )));
+    }
+
+    let permit = state
+        .permits
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| ApiError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: "too many concurrent requests".to_string(),
+        })?;
+    let n_best = request.n_best;
+    let mut results = tokio::task::spawn_blocking(move || {
+        // クライアントが切断しても、推論が終わるまで枠を解放しない。
+        let _permit = permit;
+        words
+            .iter()
+            .map(|word| state.convert(word, &options, n_best))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| ApiError {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        message: format!("inference failed: {e}"),
+    })?;
+
+    Ok(Json(if batch {
+        json!({"results": results})
+    } else {
+        results.remove(0)
+    }))
+}
+
+/// `GET /health`。
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({"status": "ok"}))
+}
+
+/// `GET /info`。
+async fn info(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    fn layer_type(layer_type: e2k::LayerType) -> &'static str {
+        match layer_type {
+            e2k::LayerType::Gru => "gru",
+            e2k::LayerType::Lstm => "lstm",
+            e2k::LayerType::Transformer => "transformer",
+        }
+    }
+
+    let info = state.c2k.model_info();
+    Json(json!({
+        "model": {
+            "version": info.version,
+            "input_vocab_size": info.input_vocab.len(),
+            "output_vocab_size": info.output_vocab.len(),
+            "n_heads": info.n_heads,
+            "encoder_type": layer_type(info.encoder_type),
+            "decoder_type": layer_type(info.decoder_type),
+        },
+        "max_length": state.config.max_length,
+        "max_batch_size": state.config.max_batch_size,
+        "max_n_best": state.config.max_n_best,
+        "max_concurrent_requests": state.config.max_concurrent_requests,
+    }))
+}
@@ -0,0 +1,53 @@
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(version, about = "e2kをJSON APIとして提供するHTTPサーバー。")]
+struct Args {
+    /// 待ち受けるアドレス。
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// 待ち受けるポート。
+    #[arg(short, long, default_value = "8080")]
+    port: u16,
+
+    /// 読みの最大長の既定値と上限。
+    #[arg(short, long, default_value = "32")]
+    max_length: usize,
+
+    /// 1リクエストで変換できる単語数の上限。
+    #[arg(long, default_value = "1024")]
+    max_batch_size: usize,
+
+    /// `n_best`の上限。
+    #[arg(long, default_value = "16")]
+    max_n_best: usize,
+
+    /// 同時に推論するリクエスト数の上限。省略した場合はCPUのスレッド数。
+    #[arg(long)]
+    max_concurrent_requests: Option<usize>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let config = e2k_server::Config {
+        max_length: args.max_length,
+        max_batch_size: args.max_batch_size,
+        max_n_best: args.max_n_best,
+        max_concurrent_requests: args
+            .max_concurrent_requests
+            .unwrap_or_else(|| e2k_server::Config::default().max_concurrent_requests),
+    };
+    let c2k = e2k::C2k::new(config.max_length);
+
+    let listener = tokio::net::TcpListener::bind((args.host.as_str(), args.port)).await?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, e2k_server::router(c2k, config))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use serde_json::{Value, json};
+
+/// ローカルのポートでサーバーを起動し、URLを返す。
+fn start_server(config: e2k_server::Config) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                let c2k = e2k::C2k::new(config.max_length);
+                axum::serve(listener, e2k_server::router(c2k, config))
+                    .await
+                    .unwrap();
+            });
+    });
+    format!("http://{addr}")
+}
+
+fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into()
+}
+
+fn post(url: &str, body: Value) -> (u16, Value) {
+    let mut response = agent()
+        .post(format!("{url}/convert"))
+        .send_json(body)
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.body_mut().read_json().unwrap())
+}
+
+fn get(url: &str, path: &str) -> (u16, Value) {
+    let mut response = agent().get(format!("{url}{path}")).call().unwrap();
+    let status = response.status().as_u16();
+    (status, response.body_mut().read_json().unwrap())
+}
+
+#[test]
+fn test_health_and_info() {
+    let url = start_server(e2k_server::Config::default());
+    assert_eq!(get(&url, "/health"), (200, json!({"status": "ok"})));
+
+    let (status, info) = get(&url, "/info");
+    assert_eq!(status, 200);
+    let model_info = e2k::C2k::new(32).model_info().clone();
+    assert_eq!(info["model"]["version"], json!(model_info.version));
+    assert_eq!(info["model"]["n_heads"], model_info.n_heads);
+    assert_eq!(info["model"]["encoder_type"], "gru");
+    assert_eq!(info["max_length"], 32);
+}
+
+#[test]
+fn test_convert() {
+    let url = start_server(e2k_server::Config::default());
+    let c2k = e2k::C2k::new(32);
+
+    assert_eq!(
+        post(&url, json!({"word": "constants"})),
+        (
+            200,
+            json!({"word": "constants", "kana": c2k.infer("constants")})
+        )
+    );
+
+    let (status, body) = post(&url, json!({"words": ["constants", "hello"]}));
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        json!({"results": [
+            {"word": "constants", "kana": c2k.infer("constants")},
+            {"word": "hello", "kana": c2k.infer("hello")},
+        ]})
+    );
+}
+
+#[test]
+fn test_convert_options() {
+    let url = start_server(e2k_server::Config::default());
+    let c2k = e2k::C2k::new(32);
+
+    let options = e2k::InferOptions {
+        strategy: e2k::Strategy::TopP(e2k::StrategyTopP {
+            top_p: 0.8,
+            temperature: 1.5,
+            seed: Some(42),
+        }),
+        max_length: 8,
+        ..Default::default()
+    };
+    let (status, body) = post(
+        &url,
+        json!({
+            "word": "constants",
+            "strategy": {"type": "top_p", "top_p": 0.8, "temperature": 1.5, "seed": 42},
+            "max_length": 8,
+        }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(body["kana"], c2k.infer_with("constants", &options).unwrap());
+
+    let (status, body) = post(&url, json!({"words": ["constants"], "n_best": 3}));
+    assert_eq!(status, 200);
+    let expected = c2k.infer_n_best("constants", 3, &e2k::InferOptions::default());
+    let candidates = body["results"][0]["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), expected.len());
+    for (candidate, expected) in candidates.iter().zip(&expected) {
+        assert_eq!(candidate["kana"], expected.kana);
+    }
+}
+
+#[test]
+fn test_convert_errors() {
+    let url = start_server(e2k_server::Config {
+        max_batch_size: 2,
+        ..Default::default()
+    });
+
+    for body in [
+        json!({}),
+        json!({"word": "a", "words": ["b"]}),
+        json!({"words": ["a", "b", "c"]}),
+        json!({"word": "a", "max_length": 33}),
+        json!({"word": "a", "n_best": 0}),
+        json!({"word": "a", "strategy": {"type": "top_k", "k": 0}}),
+        json!({"word": "a", "strategy": {"type": "top_p", "top_p": 1.5}}),
+        json!({"word": "a", "strategy": {"type": "unknown"}}),
+        json!({"word": "a", "strategy": {"type": "top_k", "p": 0.5}}),
+        json!({"word": "a", "unknown": 1}),
+    ] {
+        let (status, response) = post(&url, body.clone());
+        assert!((400..500).contains(&status), "{body}: {status}");
+        assert!(response["error"].is_string(), "{body}: {response}");
+    }
+}
+
+#[test]
+fn test_concurrent_requests() {
+    let url = start_server(e2k_server::Config {
+        max_concurrent_requests: 16,
+        ..Default::default()
+    });
+    let c2k = e2k::C2k::new(32);
+    let words = ["constants", "hello", "world", "voicevox"];
+
+    std::thread::scope(|s| {
+        let handles = (0..16)
+            .map(|i| {
+                let url = &url;
+                let word = words[i % words.len()];
+                let strategy = if i % 2 == 0 {
+                    json!({"type": "greedy"})
+                } else {
+                    json!({"type": "top_k", "seed": i})
+                };
+                s.spawn(move || {
+                    let (status, body) = post(url, json!({"word": word, "strategy": strategy}));
+                    assert_eq!(status, 200);
+                    (i, word, body["kana"].as_str().unwrap().to_string())
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let (i, word, kana) = handle.join().unwrap();
+            let strategy = if i % 2 == 0 {
+                e2k::Strategy::Greedy
+            } else {
+                e2k::Strategy::TopK(e2k::StrategyTopK {
+                    seed: Some(i as u64),
+                    ..Default::default()
+                })
+            };
+            let options = e2k::InferOptions {
+                strategy,
+                ..Default::default()
+            };
+            assert_eq!(kana, c2k.infer_with(word, &options).unwrap());
+        }
+    });
+}
+
+#[test]
+fn test_too_many_requests() {
+    let url = start_server(e2k_server::Config {
+        max_concurrent_requests: 1,
+        ..Default::default()
+    });
+    assert_eq!(get(&url, "/info").1["max_concurrent_requests"], 1);
+
+    // 多くの単語で推論に時間をかけ、その間に来たリクエストを拒否させる。
+    let words = vec!["constants"; if cfg!(debug_assertions) { 64 } else { 1024 }];
+    std::thread::scope(|s| {
+        let slow = s.spawn(|| post(&url, json!({"words": words})).0);
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        let (status, body) = post(&url, json!({"word": "a"}));
+        assert_eq!(status, 503);
+        assert!(body["error"].is_string());
+        assert_eq!(slow.join().unwrap(), 200);
+    });
+    // 推論が終われば再び受け付ける。
+    assert_eq!(post(&url, json!({"word": "a"})).0, 200);
+}
@@ -0,0 +1,103 @@
+//! [e2k](https://github.com/VOICEVOX/e2k/tree/main/infer/crates/e2k-rs)のJavaScript向けバインディング。
+//!
+//! wasm-bindgenでJavaScriptに公開します。型定義は生成される`.d.ts`を参照してください。
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const STRATEGY_TYPE: &str = r#"
+/**
+ * デコードに使うアルゴリズムとパラメータ。省略したパラメータは既定値になる。
+ *
+ * `seed`を省略した場合は推論ごとに異なるシードを使う。
+ */
+export type Strategy =
+  | { type: "greedy" }
+  | { type: "top_k"; k?: number; temperature?: number; seed?: number | bigint }
+  | { type: "top_p"; topP?: number; temperature?: number; seed?: number | bigint }
+  | { type: "min_p"; minP?: number; temperature?: number; seed?: number | bigint }
+  | { type: "typical"; typicalP?: number; temperature?: number; seed?: number | bigint }
+  | { type: "temperature"; temperature?: number; seed?: number | bigint };
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "Strategy")]
+    pub type Strategy;
+}
+
+impl TryFrom<Strategy> for e2k::Strategy {
+    type Error = JsError;
+
+    fn try_from(strategy: Strategy) -> Result<Self, Self::Error> {
+        let strategy: e2k::StrategyConfig = serde_wasm_bindgen::from_value(strategy.into())
+            .map_err(|e| JsError::new(&format!("invalid strategy: {e}")))?;
+        strategy.try_into().map_err(JsError::from)
+    }
+}
+
+/// 英単語 -> カタカナの変換器。
+#[wasm_bindgen]
+pub struct C2k {
+    inner: e2k::C2k,
+}
+
+#[wasm_bindgen]
+impl C2k {
+    /// 新しいインスタンスを生成する。
+    ///
+    /// `maxLength`は読みの最大長。省略した場合は32。
+    #[wasm_bindgen(constructor)]
+    pub fn new(#[wasm_bindgen(js_name = maxLength)] max_length: Option<usize>) -> C2k {
+        C2k {
+            inner: e2k::C2k::new(max_length.unwrap_or(32)),
+        }
+    }
+
+    /// `infer`などで`strategy`を省略した場合に使うアルゴリズムを設定する。
+    #[wasm_bindgen(js_name = setStrategy)]
+    pub fn set_strategy(&mut self, strategy: Strategy) -> Result<(), JsError> {
+        self.inner.set_decode_strategy(strategy.try_into()?);
+        Ok(())
+    }
+
+    /// 推論を行う。
+    ///
+    /// `strategy`を指定した場合、この呼び出しでのみそのアルゴリズムを使う。
+    pub fn infer(&self, input: &str, strategy: Option<Strategy>) -> Result<String, JsError> {
+        let options = self.options(strategy)?;
+        Ok(self.inner.infer_with(input, &options)?)
+    }
+
+    /// 複数の単語の推論を行う。
+    ///
+    /// `strategy`を指定した場合、この呼び出しでのみそのアルゴリズムを使う。
+    #[wasm_bindgen(js_name = inferBatch)]
+    pub fn infer_batch(
+        &self,
+        inputs: Vec<String>,
+        strategy: Option<Strategy>,
+    ) -> Result<Vec<String>, JsError> {
+        let options = self.options(strategy)?;
+        inputs
+            .iter()
+            .map(|input| Ok(self.inner.infer_with(input, &options)?))
+            .collect()
+    }
+
+    /// モデルのバージョン。
+    #[wasm_bindgen(getter, js_name = modelVersion)]
+    pub fn model_version(&self) -> Option<String> {
+        self.inner.model_info().version.clone()
+    }
+}
+
+impl C2k {
+    fn options(&self, strategy: Option<Strategy>) -> Result<e2k::InferOptions, JsError> {
+        let mut options = self.inner.default_options().clone();
+        if let Some(strategy) = strategy {
+            options.strategy = strategy.try_into()?;
+        }
+        Ok(options)
+    }
+}
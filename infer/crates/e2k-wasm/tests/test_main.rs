@@ -0,0 +1,106 @@
+//! `cargo test -p e2k-wasm --target wasm32-unknown-unknown`でNode.js上で実行する。
+//! `wasm-bindgen-test-runner`が必要です（`cargo install wasm-bindgen-cli`）。
+#![cfg(target_arch = "wasm32")]
+
+use e2k_wasm::{C2k, Strategy};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn strategy(json: &str) -> Strategy {
+    js_sys::JSON::parse(json).unwrap().unchecked_into()
+}
+
+#[wasm_bindgen_test]
+fn test_infer() {
+    let c2k = C2k::new(None);
+    let dst = c2k.infer("constants", None).unwrap();
+    assert!(!dst.is_empty());
+    assert_eq!(
+        c2k.infer("constants", Some(strategy(r#"{"type": "greedy"}"#)))
+            .unwrap(),
+        dst
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_infer_batch() {
+    let c2k = C2k::new(Some(16));
+    let words = ["constants", "hello", "world"];
+    let dst = c2k
+        .infer_batch(words.iter().map(|w| w.to_string()).collect(), None)
+        .unwrap();
+    assert_eq!(
+        dst,
+        words
+            .iter()
+            .map(|w| c2k.infer(w, None).unwrap())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_seeded_strategy() {
+    let c2k = C2k::new(None);
+    for json in [
+        r#"{"type": "top_k", "k": 3, "temperature": 1.5, "seed": 42}"#,
+        r#"{"type": "top_p", "topP": 0.9, "seed": 42}"#,
+        r#"{"type": "min_p", "minP": 0.1, "seed": 42}"#,
+        r#"{"type": "typical", "typicalP": 0.9, "seed": 42}"#,
+        r#"{"type": "temperature", "temperature": 2, "seed": 42}"#,
+    ] {
+        let first = c2k.infer("constants", Some(strategy(json))).unwrap();
+        let second = c2k.infer("constants", Some(strategy(json))).unwrap();
+        assert_eq!(first, second, "{json}");
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_seed_as_bigint() {
+    let c2k = C2k::new(None);
+    let with_number = c2k
+        .infer(
+            "constants",
+            Some(strategy(r#"{"type": "top_k", "seed": 7}"#)),
+        )
+        .unwrap();
+
+    let object = js_sys::Object::new();
+    js_sys::Reflect::set(&object, &"type".into(), &"top_k".into()).unwrap();
+    js_sys::Reflect::set(&object, &"seed".into(), &js_sys::BigInt::from(7u64).into()).unwrap();
+    let with_bigint = c2k
+        .infer("constants", Some(object.unchecked_into()))
+        .unwrap();
+
+    assert_eq!(with_number, with_bigint);
+}
+
+#[wasm_bindgen_test]
+fn test_set_strategy() {
+    let mut c2k = C2k::new(None);
+    let json = r#"{"type": "top_k", "seed": 1}"#;
+    let expected = c2k.infer("constants", Some(strategy(json))).unwrap();
+    c2k.set_strategy(strategy(json)).unwrap();
+    assert_eq!(c2k.infer("constants", None).unwrap(), expected);
+}
+
+#[wasm_bindgen_test]
+fn test_invalid_strategy() {
+    let c2k = C2k::new(None);
+    for json in [
+        r#"{"type": "unknown"}"#,
+        r#"{"type": "top_k", "k": 0}"#,
+        r#"{"type": "top_k", "top_k": 3}"#,
+        r#"{"type": "top_p", "topP": 1.5}"#,
+        r#"{"type": "temperature", "temperature": -1}"#,
+        r#"{"k": 3}"#,
+    ] {
+        assert!(
+            c2k.infer("constants", Some(strategy(json))).is_err(),
+            "{json}"
+        );
+    }
+    assert!(
+        c2k.infer("constants", Some(JsValue::from(1).unchecked_into()))
+            .is_err()
+    );
+}
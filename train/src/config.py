@@ -12,6 +12,12 @@ def migrate(config: dict):
         config["optimizer_lr"] = 1e-3
     if "exponential_lr_scheduler_gamma" not in config:
         config["exponential_lr_scheduler_gamma"] = 0.9
+    if "num_layers" not in config:
+        config["num_layers"] = 1
+    if "num_heads" not in config:
+        config["num_heads"] = 4
+    if "bidirectional_encoder" not in config:
+        config["bidirectional_encoder"] = True
 
     return config
 
@@ -28,6 +34,9 @@ class Config:
     seed: int
     optimizer_lr: float
     exponential_lr_scheduler_gamma: float
+    num_layers: int
+    num_heads: int
+    bidirectional_encoder: bool
 
     @classmethod
     def from_dict(cls, config: dict):
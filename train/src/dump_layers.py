@@ -0,0 +1,78 @@
+"""
+Dumps the weights and outputs of torch layers as reference values for the Rust implementation
+"""
+
+import argparse
+from pathlib import Path
+
+import torch
+from safetensors.numpy import save_file as save_safetensors
+from torch import nn
+
+parser = argparse.ArgumentParser()
+
+parser.add_argument(
+    "--output",
+    type=Path,
+    default=Path(__file__).parents[2]
+    / "infer/crates/e2k-rs/tests/data/torch_layers.safetensors",
+)
+
+args = parser.parse_args()
+
+torch.manual_seed(0)
+
+tensors = {}
+
+
+def add_module(prefix: str, module: nn.Module):
+    for name, param in module.state_dict().items():
+        tensors[f"{prefix}.{name}"] = param.detach().numpy()
+
+
+def add_tensor(name: str, tensor: torch.Tensor):
+    tensors[name] = tensor.detach().contiguous().numpy()
+
+
+def randomize_layer_norm(norm: nn.LayerNorm):
+    # 初期値の重み1、バイアス0のままでは重みとバイアスの読み込みを確かめられないため
+    nn.init.normal_(norm.weight, 1.0, 0.2)
+    nn.init.normal_(norm.bias, 0.0, 0.2)
+
+
+with torch.no_grad():
+    # 双方向のLSTM。Rust側では順方向と逆方向を別々に比較する
+    lstm = nn.LSTM(2, 3, batch_first=True, bidirectional=True)
+    x = torch.randn(1, 4, 2)
+    output, (h_n, c_n) = lstm(x)
+    add_module("lstm", lstm)
+    add_tensor("lstm.input", x[0])
+    add_tensor("lstm.output", output[0])
+    add_tensor("lstm.h_n", h_n[:, 0])
+    add_tensor("lstm.c_n", c_n[:, 0])
+
+    norm = nn.LayerNorm(4)
+    randomize_layer_norm(norm)
+    x = torch.randn(3, 4)
+    add_module("layer_norm", norm)
+    add_tensor("layer_norm.input", x)
+    add_tensor("layer_norm.output", norm(x))
+
+    layer = nn.TransformerDecoderLayer(
+        d_model=8, nhead=2, dim_feedforward=16, dropout=0.0, batch_first=True
+    )
+    layer.eval()
+    for norm in [layer.norm1, layer.norm2, layer.norm3]:
+        randomize_layer_norm(norm)
+    target = torch.randn(1, 5, 8)
+    memory = torch.randn(1, 3, 8)
+    mask = nn.Transformer.generate_square_subsequent_mask(5)
+    output = layer(target, memory, tgt_mask=mask, tgt_is_causal=True)
+    add_module("decoder_layer", layer)
+    add_tensor("decoder_layer.target", target[0])
+    add_tensor("decoder_layer.memory", memory[0])
+    add_tensor("decoder_layer.output", output[0])
+
+print(f"Saving to {args.output}")
+args.output.parent.mkdir(parents=True, exist_ok=True)
+save_safetensors(tensors, args.output, metadata={"torch": torch.__version__})
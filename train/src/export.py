@@ -2,6 +2,7 @@
 Exports the torch weights
 """
 
+import json
 import torch
 from safetensors.numpy import save_file as save_safetensors
 import argparse
@@ -9,6 +10,7 @@ from pathlib import Path
 import yaml
 from train import Model
 from config import Config
+from constants import EOS_IDX, SOS_IDX, ascii_entries, kanas
 
 parser = argparse.ArgumentParser()
 
@@ -16,6 +18,7 @@ parser.add_argument("--fp32", action="store_true")
 parser.add_argument("--model", type=Path, required=True)
 parser.add_argument("--output", type=Path, required=True)
 parser.add_argument("--config", type=Path, required=False)
+parser.add_argument("--version", type=str, required=False)
 
 args = parser.parse_args()
 
@@ -41,4 +44,15 @@ for name, param in model.named_parameters():
 
 print(f"Saving to {args.output}")
 
-save_safetensors(weights, args.output)
+# 語彙などをメタデータに含め、Rust側のModelInfoとして読み込めるようにする
+metadata = {
+    "input_vocab": json.dumps(ascii_entries, ensure_ascii=False),
+    "output_vocab": json.dumps(kanas, ensure_ascii=False),
+    "n_heads": str(model.attn.num_heads),
+    "sos_idx": str(SOS_IDX),
+    "eos_idx": str(EOS_IDX),
+}
+if args.version is not None:
+    metadata["version"] = args.version
+
+save_safetensors(weights, args.output, metadata=metadata)
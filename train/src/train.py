@@ -31,15 +31,26 @@ class Model(nn.Module):
         self.e_emb = nn.Embedding(len(ascii_entries), config.dim)
         self.k_emb = nn.Embedding(len(kanas), config.dim)
         self.encoder = nn.GRU(
-            config.dim, config.dim, batch_first=True, bidirectional=True
+            config.dim,
+            config.dim,
+            num_layers=config.num_layers,
+            batch_first=True,
+            bidirectional=config.bidirectional_encoder,
         )
+        directions = 2 if config.bidirectional_encoder else 1
         self.encoder_fc = nn.Sequential(
-            nn.Linear(2 * config.dim, config.dim),
+            nn.Linear(directions * config.dim, config.dim),
             nn.Tanh(),
         )
-        self.pre_decoder = nn.GRU(config.dim, config.dim, batch_first=True)
-        self.post_decoder = nn.GRU(2 * config.dim, config.dim, batch_first=True)
-        self.attn = nn.MultiheadAttention(config.dim, 4, batch_first=True, dropout=0.1)
+        self.pre_decoder = nn.GRU(
+            config.dim, config.dim, num_layers=config.num_layers, batch_first=True
+        )
+        self.post_decoder = nn.GRU(
+            2 * config.dim, config.dim, num_layers=config.num_layers, batch_first=True
+        )
+        self.attn = nn.MultiheadAttention(
+            config.dim, config.num_heads, batch_first=True, dropout=0.1
+        )
         self.fc = nn.Linear(config.dim, len(kanas))
 
     def forward(self, src, tgt, src_mask=None, tgt_mask=None):